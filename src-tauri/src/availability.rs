@@ -0,0 +1,159 @@
+use std::fs;
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+use crate::commands::AppState;
+use crate::database::Database;
+use crate::models::Availability;
+
+/// How often every stored file is checked for existence
+const CHECK_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Device id of the volume holding `path`; `None` where the platform has no
+/// stable equivalent
+pub fn device_id(path: &Path) -> Option<u64> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        fs::metadata(path).ok().map(|m| m.dev())
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+        None
+    }
+}
+
+/// Whether the volume holding a mounted folder is attached. A folder that
+/// can't be read is offline. The mount point of an unmounted volume is still
+/// there, as an empty directory on the parent volume, so an empty folder is
+/// offline when its device differs from the one seen by its last scan. Without
+/// a recorded device it is offline only if the library has videos in it; a
+/// new, empty folder is online.
+pub fn is_volume_online(db: &Database, folder_path: &str) -> bool {
+    let Ok(mut entries) = fs::read_dir(folder_path) else {
+        return false;
+    };
+    if entries.next().is_some() {
+        return true;
+    }
+    let recorded = db.get_folder_device(folder_path).ok().flatten();
+    match (recorded, device_id(Path::new(folder_path))) {
+        (Some(recorded), Some(current)) => recorded == current,
+        _ => !db.has_videos_at(folder_path).unwrap_or(false),
+    }
+}
+
+/// Start the thread that keeps `videos.availability` in line with the disk.
+/// The first check runs right away so the library is accurate after startup.
+pub fn spawn_checker(app: AppHandle) {
+    thread::spawn(move || loop {
+        match check(&app) {
+            Ok(changed) if !changed.is_empty() => {
+                let _ = app.emit("availability-changed", &changed);
+            }
+            Ok(_) => {}
+            Err(e) => log::error!("Failed to check file availability: {}", e),
+        }
+        thread::sleep(CHECK_INTERVAL);
+    });
+}
+
+/// Stat every stored file and record status changes. Returns the ids of
+/// videos whose status changed.
+fn check(app: &AppHandle) -> Result<Vec<String>, String> {
+    let state = app.state::<AppState>();
+    let folders = state.db.get_mounted_folders().map_err(|e| e.to_string())?;
+
+    let mut changes: Vec<(String, Availability)> = Vec::new();
+    for folder in folders {
+        let videos = state.db.get_folder_availability(&folder.path).map_err(|e| e.to_string())?;
+
+        // Skip the files of an offline volume
+        let online = is_volume_online(&state.db, &folder.path);
+        for (video_id, path, current) in videos {
+            // Only a scan with different rules brings these back
+            if current == Availability::Excluded {
+                continue;
+            }
+            let status = if !online {
+                Availability::OfflineVolume
+            } else if Path::new(&path).exists() {
+                Availability::Online
+            } else {
+                Availability::Missing
+            };
+            if status != current {
+                changes.push((video_id, status));
+            }
+        }
+    }
+
+    if !changes.is_empty() {
+        state.db.set_availability(&changes).map_err(|e| e.to_string())?;
+    }
+    // The workers skip files that aren't online; pick up the ones that came back
+    if changes.iter().any(|(_, status)| *status == Availability::Online) {
+        state.probe.wake();
+        state.thumbnails.wake();
+        state.hashing.wake();
+    }
+    Ok(changes.into_iter().map(|(id, _)| id).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Video;
+
+    fn video(path: &Path) -> Video {
+        Video {
+            id: "video".to_string(),
+            path: path.to_string_lossy().to_string(),
+            filename: path.file_name().unwrap().to_string_lossy().to_string(),
+            folder_path: path.parent().unwrap().to_string_lossy().to_string(),
+            size: 1000,
+            duration: None,
+            thumbnail_path: None,
+            mtime: None,
+            created_at: String::new(),
+            updated_at: String::new(),
+            availability: Availability::Online,
+        }
+    }
+
+    #[test]
+    fn empty_folders_are_offline_only_when_they_were_not_before() {
+        let dir = std::env::temp_dir().join(format!("videoplayer-availability-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let folder = dir.join("library");
+        fs::create_dir_all(&folder).unwrap();
+        let db = Database::open(&dir.join("database.sqlite")).unwrap();
+        let folder_str = folder.to_string_lossy().to_string();
+        db.add_mounted_folder(&folder_str, "library", 2).unwrap();
+
+        // A new, empty folder
+        assert!(is_volume_online(&db, &folder_str));
+        assert!(!is_volume_online(&db, &dir.join("absent").to_string_lossy()));
+
+        // Stored videos but no device yet: an empty folder looks unmounted
+        db.upsert_video(&video(&folder.join("a.mp4"))).unwrap();
+        assert!(!is_volume_online(&db, &folder_str));
+
+        if let Some(device) = device_id(&folder) {
+            // Emptied on the same volume: the files are missing, not offline
+            db.set_folder_device(&folder_str, device).unwrap();
+            assert!(is_volume_online(&db, &folder_str));
+
+            db.set_folder_device(&folder_str, device.wrapping_add(1)).unwrap();
+            assert!(!is_volume_online(&db, &folder_str));
+        }
+
+        // Files are there: a volume remounted under a new device id is online
+        fs::write(folder.join("a.mp4"), b"video").unwrap();
+        assert!(is_volume_online(&db, &folder_str));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -1,274 +1,930 @@
 use std::path::Path;
-use std::sync::Mutex;
-use tauri::State;
+use std::sync::Arc;
+use serde::Serialize;
+use serde_json::{json, Value};
+use tauri::{AppHandle, Manager, State};
 use crate::database::Database;
+use crate::hashing::HashQueue;
 use crate::models::{*, PaginatedVideos};
 use crate::scanner;
-use crate::player::PlayerState;
+use crate::playback;
+use crate::player::{ActivePlaylist, PlayerState};
+use crate::playlist;
+use crate::probe::ProbeQueue;
+use crate::query::{self, QueryError};
+use crate::queue;
+use crate::scan_queue::ScanQueue;
+use crate::scan_rules::RuleSet;
+use crate::thumbnails::{self, ThumbnailQueue};
+use crate::watcher::FolderWatcher;
 
 pub struct AppState {
-    pub db: Mutex<Database>,
+    pub db: Arc<Database>,
     pub player: PlayerState,
+    pub watcher: FolderWatcher,
+    pub probe: ProbeQueue,
+    pub thumbnails: ThumbnailQueue,
+    pub hashing: HashQueue,
+    pub scans: ScanQueue,
+}
+
+/// Run database work on the blocking thread pool. Commands that touch the
+/// database are async so a slow query or a scan never stalls the UI thread.
+async fn run_db<T, E, F>(state: &State<'_, AppState>, f: F) -> Result<T, String>
+where
+    F: FnOnce(&Database) -> Result<T, E> + Send + 'static,
+    T: Send + 'static,
+    E: ToString,
+{
+    let db = state.db.clone();
+    tauri::async_runtime::spawn_blocking(move || f(&db).map_err(|e| e.to_string()))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Run player and queue work on the blocking thread pool. mpv IPC calls can
+/// wait seconds for a reply, and the queue saves itself to the database.
+async fn run_player<T, F>(app: &AppHandle, f: F) -> Result<T, String>
+where
+    F: FnOnce(&AppHandle, &AppState) -> Result<T, String> + Send + 'static,
+    T: Send + 'static,
+{
+    let app = app.clone();
+    tauri::async_runtime::spawn_blocking(move || f(&app, &app.state::<AppState>()))
+        .await
+        .map_err(|e| e.to_string())?
 }
 
 // ========== Folder Commands ==========
 
 #[tauri::command]
-pub fn add_mounted_folder(state: State<AppState>, path: String, scan_depth: Option<usize>) -> Result<MountedFolder, String> {
+pub async fn add_mounted_folder(state: State<'_, AppState>, path: String, scan_depth: Option<usize>) -> Result<MountedFolder, String> {
     let name = Path::new(&path)
         .file_name()
         .map(|n| n.to_string_lossy().to_string())
         .unwrap_or_else(|| path.clone());
     
     let depth = scan_depth.unwrap_or(2);
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    db.add_mounted_folder(&path, &name, depth).map_err(|e| e.to_string())
+    let folder = run_db(&state, move |db| db.add_mounted_folder(&path, &name, depth)).await?;
+    
+    // A folder that can't be watched still works with manual rescans
+    if let Err(e) = state.watcher.watch(&folder) {
+        log::warn!("{}", e);
+    }
+    
+    Ok(folder)
 }
 
 #[tauri::command]
-pub fn update_folder_scan_depth(state: State<AppState>, path: String, scan_depth: usize) -> Result<(), String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    db.update_folder_scan_depth(&path, scan_depth).map_err(|e| e.to_string())
+pub async fn update_folder_scan_depth(state: State<'_, AppState>, path: String, scan_depth: usize) -> Result<(), String> {
+    let folder = run_db(&state, move |db| {
+        db.update_folder_scan_depth(&path, scan_depth)?;
+        db.get_mounted_folder(&path)
+    }).await?;
+    
+    if let Some(folder) = folder {
+        if let Err(e) = state.watcher.watch(&folder) {
+            log::warn!("{}", e);
+        }
+    }
+    
+    Ok(())
 }
 
+/// Change which files scans of `path` pick up. Takes effect on the next scan,
+/// which marks stored videos the new rules skip as excluded.
 #[tauri::command]
-pub fn get_mounted_folders(state: State<AppState>) -> Result<Vec<MountedFolder>, String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    db.get_mounted_folders().map_err(|e| e.to_string())
+pub async fn update_folder_scan_rules(state: State<'_, AppState>, path: String, scan_rules: ScanRules) -> Result<(), String> {
+    // Reject invalid globs here rather than failing every later scan
+    RuleSet::new(&path, &scan_rules)?;
+    
+    let folder = run_db(&state, move |db| {
+        db.update_folder_scan_rules(&path, &scan_rules)?;
+        db.get_mounted_folder(&path)
+    }).await?;
+    
+    if let Some(folder) = folder {
+        if let Err(e) = state.watcher.watch(&folder) {
+            log::warn!("{}", e);
+        }
+    }
+    
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_mounted_folders(state: State<'_, AppState>) -> Result<Vec<MountedFolder>, String> {
+    run_db(&state, move |db| db.get_mounted_folders()).await
 }
 
 #[tauri::command]
-pub fn remove_mounted_folder(state: State<AppState>, path: String) -> Result<(), String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    db.remove_mounted_folder(&path).map_err(|e| e.to_string())
+pub async fn remove_mounted_folder(state: State<'_, AppState>, path: String) -> Result<(), String> {
+    state.watcher.unwatch(&path);
+    run_db(&state, move |db| db.remove_mounted_folder(&path)).await
 }
 
 // ========== Scan Commands ==========
 
+/// Queue a scan of `folder_path`; progress and the final `ScanResult`
+/// arrive as `scan-progress` and `scan-finished` events
 #[tauri::command]
-pub fn scan_folder(state: State<AppState>, folder_path: String) -> Result<ScanResult, String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    
-    // Get scan depth for this folder
-    let scan_depth = db.get_mounted_folder(&folder_path)
-        .map_err(|e| e.to_string())?
-        .map(|f| f.scan_depth)
-        .unwrap_or(2);
-    
-    // Clear existing videos from this folder before re-scanning
-    db.clear_folder_videos(&folder_path).map_err(|e| e.to_string())?;
-    
-    drop(db); // Release lock before scanning
-    
-    let scan_result = scanner::scan_folder(&folder_path, scan_depth);
-    
-    // Save scanned videos to database
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    
-    for video in &scan_result.videos {
-        db.upsert_video(video).map_err(|e| e.to_string())?;
-    }
-    
-    Ok(ScanResult {
-        total_videos: scan_result.total_videos,
-        new_videos: scan_result.videos.len(),
-        folders: scan_result.folders,
-        videos: scan_result.videos,
-    })
+pub fn scan_folder(state: State<AppState>, folder_path: String) -> Result<ScanJob, String> {
+    Ok(state.scans.enqueue(&folder_path))
 }
 
 #[tauri::command]
-pub fn get_folder_tree(state: State<AppState>, folder_path: String) -> Result<FolderNode, String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    let scan_depth = db.get_mounted_folder(&folder_path)
-        .map_err(|e| e.to_string())?
-        .map(|f| f.scan_depth)
-        .unwrap_or(2);
-    drop(db);
-    
-    let scan_result = scanner::scan_folder(&folder_path, scan_depth);
-    scan_result.folders.into_iter().next().ok_or_else(|| "No folder found".to_string())
+pub fn get_scan_jobs(state: State<AppState>) -> Result<Vec<ScanJob>, String> {
+    Ok(state.scans.jobs())
+}
+
+#[tauri::command]
+pub fn cancel_scan(state: State<AppState>, job_id: String) -> Result<(), String> {
+    state.scans.cancel(&job_id)
+}
+
+/// Report of the most recent scan of `folder_path`, if it was ever scanned
+#[tauri::command]
+pub async fn get_last_scan_report(state: State<'_, AppState>, folder_path: String) -> Result<Option<ScanReport>, String> {
+    run_db(&state, move |db| db.get_last_scan_report(&folder_path)).await
+}
+
+/// Folder tree below `folder_path` from the library, `depth` levels deep.
+/// Nodes at the cut-off have `has_children` set; load them with another call.
+#[tauri::command]
+pub async fn get_folder_tree(state: State<'_, AppState>, folder_path: String, depth: Option<usize>) -> Result<FolderNode, String> {
+    run_db(&state, move |db| db.get_folder_tree(&folder_path, depth)).await
 }
 
 // ========== Video Commands ==========
 
+/// Rejection of `get_videos`. An invalid library query keeps its location so
+/// the UI can highlight it.
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum GetVideosError {
+    Query(QueryError),
+    Database(String),
+}
+
+impl From<rusqlite::Error> for GetVideosError {
+    fn from(e: rusqlite::Error) -> Self {
+        // `build_filter_query` passes parse errors through as conversion failures
+        match e {
+            rusqlite::Error::ToSqlConversionFailure(inner) => match inner.downcast::<QueryError>() {
+                Ok(query_error) => GetVideosError::Query(*query_error),
+                Err(inner) => GetVideosError::Database(rusqlite::Error::ToSqlConversionFailure(inner).to_string()),
+            },
+            e => GetVideosError::Database(e.to_string()),
+        }
+    }
+}
+
 #[tauri::command]
-pub fn get_videos(state: State<AppState>, filter: FilterOptions) -> Result<PaginatedVideos, String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    let videos = db.get_videos(&filter).map_err(|e| e.to_string())?;
-    let total = db.get_video_count(&filter).map_err(|e| e.to_string())?;
-    let has_more = filter.offset + videos.len() < total;
-    
-    Ok(PaginatedVideos {
-        videos,
-        total,
-        has_more,
+pub async fn get_videos(state: State<'_, AppState>, filter: FilterOptions) -> Result<PaginatedVideos, GetVideosError> {
+    let db = state.db.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let videos = db.get_videos(&filter)?;
+        let total = db.get_video_count(&filter)?;
+        let has_more = filter.offset + videos.len() < total;
+        
+        Ok(PaginatedVideos {
+            videos,
+            total,
+            has_more,
+        })
     })
+    .await
+    .map_err(|e| GetVideosError::Database(e.to_string()))?
 }
 
 #[tauri::command]
-pub fn get_video_with_metadata(state: State<AppState>, video_id: String) -> Result<VideoWithMetadata, String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    
-    // Get video by searching all videos (we need to find by id)
-    let filter = FilterOptions::default();
-    let videos = db.get_videos(&filter).map_err(|e| e.to_string())?;
-    let video = videos.into_iter().find(|v| v.id == video_id)
-        .ok_or_else(|| "Video not found".to_string())?;
-    
-    let tags = db.get_video_tags(&video_id).map_err(|e| e.to_string())?;
-    let participants = db.get_video_participants(&video_id).map_err(|e| e.to_string())?;
-    let languages = db.get_video_languages(&video_id).map_err(|e| e.to_string())?;
+pub async fn get_video_with_metadata(state: State<'_, AppState>, video_id: String) -> Result<VideoWithMetadata, String> {
+    run_db(&state, move |db| {
+        let video = db.get_video(&video_id)
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| "Video not found".to_string())?;
+        
+        with_metadata(db, video)
+    }).await
+}
+
+fn with_metadata(db: &Database, video: Video) -> Result<VideoWithMetadata, String> {
+    let tags = db.get_video_tags(&video.id).map_err(|e| e.to_string())?;
+    let participants = db.get_video_participants(&video.id).map_err(|e| e.to_string())?;
+    let languages = db.get_video_languages(&video.id).map_err(|e| e.to_string())?;
+    let media_info = db.get_media_info(&video.id).map_err(|e| e.to_string())?;
+    let notes = db.get_video_notes(&video.id).map_err(|e| e.to_string())?;
     
     Ok(VideoWithMetadata {
         video,
         tags,
         participants,
         languages,
+        media_info,
+        notes,
     })
 }
 
 #[tauri::command]
-pub fn delete_video(state: State<AppState>, video_id: String) -> Result<(), String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    db.delete_video(&video_id).map_err(|e| e.to_string())
+pub async fn set_video_notes(state: State<'_, AppState>, video_id: String, notes: Option<String>) -> Result<(), String> {
+    run_db(&state, move |db| db.set_video_notes(&video_id, notes.as_deref())).await
 }
 
 #[tauri::command]
-pub fn move_video_file(state: State<AppState>, old_path: String, new_folder: String) -> Result<Video, String> {
-    let old_path_obj = Path::new(&old_path);
-    let filename = old_path_obj.file_name()
-        .ok_or_else(|| "Invalid file path".to_string())?
-        .to_string_lossy()
-        .to_string();
-    
-    let new_path = Path::new(&new_folder).join(&filename);
-    let new_path_str = new_path.to_string_lossy().to_string();
+pub async fn search_suggestions(state: State<'_, AppState>, prefix: String, limit: Option<usize>) -> Result<Vec<SearchSuggestion>, String> {
+    run_db(&state, move |db| db.search_suggestions(&prefix, limit.unwrap_or(10))).await
+}
+
+/// Verify the database and clean up rows left behind by deleted videos
+#[tauri::command]
+pub async fn check_database(state: State<'_, AppState>) -> Result<DatabaseCheck, String> {
+    run_db(&state, move |db| db.check_database()).await
+}
+
+/// Check a library query so the UI can highlight errors before running it
+#[tauri::command]
+pub fn validate_query(query: String) -> Result<(), QueryError> {
+    query::parse(&query).map(|_| ())
+}
+
+#[tauri::command]
+pub async fn rebuild_search_index(state: State<'_, AppState>) -> Result<(), String> {
+    run_db(&state, move |db| db.rebuild_search_index()).await
+}
+
+/// Videos whose file is missing or on an offline volume
+#[tauri::command]
+pub async fn get_missing_videos(state: State<'_, AppState>) -> Result<Vec<Video>, String> {
+    run_db(&state, move |db| db.get_unavailable_videos()).await
+}
+
+/// Point a missing video at its file's new location, keeping its id and metadata
+#[tauri::command]
+pub async fn relink_video(state: State<'_, AppState>, video_id: String, new_path: String) -> Result<Video, String> {
+    let video = run_db(&state, move |db| {
+        let path = Path::new(&new_path);
+        
+        // Scans and the availability check only look inside mounted folders
+        let folder = db.get_mounted_folders().map_err(|e| e.to_string())?
+            .into_iter()
+            .filter(|f| path.starts_with(&f.path))
+            .max_by_key(|f| f.path.len())
+            .ok_or_else(|| format!("{} is not inside a mounted folder", new_path))?;
+        let rules = RuleSet::new(&folder.path, &folder.scan_rules)?;
+        
+        if !path.is_file() || !rules.is_video_file(path) {
+            return Err(format!("Not a video file: {}", new_path));
+        }
+        let file = scanner::create_video_from_path(path)
+            .ok_or_else(|| format!("Failed to read {}", new_path))?;
+        
+        // The next scan would exclude a file its folder's rules skip
+        if !rules.includes(path) {
+            return Err(format!("{} is excluded by the scan rules of {}", new_path, folder.path));
+        }
+        if let Some(existing) = db.get_video_by_path(&new_path).map_err(|e| e.to_string())? {
+            if existing.id != video_id {
+                return Err(format!("{} is already in the library", new_path));
+            }
+        }
+        
+        db.relink_video(&video_id, &file).map_err(|e| e.to_string())?;
+        db.get_video(&video_id)
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| "Video not found".to_string())
+    }).await?;
     
-    // Move the actual file
-    std::fs::rename(&old_path, &new_path).map_err(|e| format!("Failed to move file: {}", e))?;
+    state.probe.wake();
+    state.thumbnails.wake();
+    state.hashing.wake();
+    Ok(video)
+}
+
+#[tauri::command]
+pub async fn delete_video(state: State<'_, AppState>, video_id: String) -> Result<(), String> {
+    run_db(&state, move |db| db.delete_video(&video_id)).await
+}
+
+#[tauri::command]
+pub async fn move_video_file(state: State<'_, AppState>, old_path: String, new_folder: String) -> Result<Video, String> {
+    run_db(&state, move |db| {
+        let old_path_obj = Path::new(&old_path);
+        let filename = old_path_obj.file_name()
+            .ok_or_else(|| "Invalid file path".to_string())?
+            .to_string_lossy()
+            .to_string();
+        
+        let new_path = Path::new(&new_folder).join(&filename);
+        let new_path_str = new_path.to_string_lossy().to_string();
+        
+        // Move the actual file
+        std::fs::rename(&old_path, &new_path).map_err(|e| format!("Failed to move file: {}", e))?;
+        
+        // Update database
+        db.update_video_path(&old_path, &new_path_str, &new_folder, &filename)
+            .map_err(|e| e.to_string())?;
+        
+        // Return updated video
+        db.get_video_by_path(&new_path_str)
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| "Video not found after move".to_string())
+    }).await
+}
+
+// ========== Smart Collection Commands ==========
+
+/// Reject filters whose query would fail every time the collection is opened
+fn validate_collection_filter(filter: &FilterOptions) -> Result<(), String> {
+    match filter.query.as_deref().map(str::trim) {
+        Some(q) if !q.is_empty() => query::parse(q).map(|_| ()).map_err(|e| e.to_string()),
+        _ => Ok(()),
+    }
+}
+
+#[tauri::command]
+pub async fn create_smart_collection(state: State<'_, AppState>, name: String, filter: FilterOptions) -> Result<SmartCollection, String> {
+    validate_collection_filter(&filter)?;
+    run_db(&state, move |db| db.create_smart_collection(&name, &filter)).await
+}
+
+#[tauri::command]
+pub async fn get_smart_collections(state: State<'_, AppState>) -> Result<Vec<SmartCollection>, String> {
+    run_db(&state, move |db| db.get_smart_collections()).await
+}
+
+#[tauri::command]
+pub async fn get_smart_collection(state: State<'_, AppState>, id: String) -> Result<Option<SmartCollection>, String> {
+    run_db(&state, move |db| db.get_smart_collection(&id)).await
+}
+
+#[tauri::command]
+pub async fn update_smart_collection(state: State<'_, AppState>, id: String, name: String, filter: FilterOptions) -> Result<(), String> {
+    validate_collection_filter(&filter)?;
+    run_db(&state, move |db| db.update_smart_collection(&id, &name, &filter)).await
+}
+
+#[tauri::command]
+pub async fn delete_smart_collection(state: State<'_, AppState>, id: String) -> Result<(), String> {
+    run_db(&state, move |db| db.delete_smart_collection(&id)).await
+}
+
+#[tauri::command]
+pub async fn get_collection_videos(state: State<'_, AppState>, id: String, limit: usize, offset: usize) -> Result<PaginatedVideos, String> {
+    run_db(&state, move |db| {
+        db.get_collection_videos(&id, limit, offset)
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| "Collection not found".to_string())
+    }).await
+}
+
+/// Mounted folders and smart collections with their current video counts
+#[tauri::command]
+pub async fn get_sidebar(state: State<'_, AppState>) -> Result<SidebarData, String> {
+    run_db(&state, |db| db.get_sidebar()).await
+}
+
+// ========== Playlist Commands ==========
+
+#[tauri::command]
+pub async fn create_playlist(state: State<'_, AppState>, name: String) -> Result<Playlist, String> {
+    run_db(&state, move |db| db.create_playlist(&name)).await
+}
+
+#[tauri::command]
+pub async fn get_playlists(state: State<'_, AppState>) -> Result<Vec<Playlist>, String> {
+    run_db(&state, move |db| db.get_playlists()).await
+}
+
+#[tauri::command]
+pub async fn rename_playlist(state: State<'_, AppState>, id: String, name: String) -> Result<(), String> {
+    run_db(&state, move |db| db.rename_playlist(&id, &name)).await
+}
+
+#[tauri::command]
+pub async fn delete_playlist(state: State<'_, AppState>, id: String) -> Result<(), String> {
+    run_db(&state, move |db| db.delete_playlist(&id)).await
+}
+
+#[tauri::command]
+pub async fn get_playlist_items(state: State<'_, AppState>, playlist_id: String) -> Result<Vec<PlaylistItem>, String> {
+    run_db(&state, move |db| db.get_playlist_items(&playlist_id)).await
+}
+
+/// Insert videos before `position`, or append them when no position is given
+#[tauri::command]
+pub async fn add_to_playlist(state: State<'_, AppState>, playlist_id: String, video_ids: Vec<String>, position: Option<usize>) -> Result<(), String> {
+    run_db(&state, move |db| db.insert_playlist_items(&playlist_id, &video_ids, position)).await
+}
+
+#[tauri::command]
+pub async fn remove_from_playlist(state: State<'_, AppState>, playlist_id: String, item_ids: Vec<String>) -> Result<(), String> {
+    run_db(&state, move |db| db.remove_playlist_items(&playlist_id, &item_ids)).await
+}
+
+#[tauri::command]
+pub async fn reorder_playlist(state: State<'_, AppState>, playlist_id: String, item_ids: Vec<String>) -> Result<(), String> {
+    run_db(&state, move |db| db.reorder_playlist(&playlist_id, &item_ids)).await
+}
+
+#[tauri::command]
+pub async fn play_playlist(app: AppHandle, state: State<'_, AppState>, id: String, start_index: Option<usize>) -> Result<(), String> {
+    let playlist_id = id.clone();
+    let items = run_db(&state, move |db| db.get_playlist_items(&playlist_id)).await?;
     
-    // Update database
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    db.update_video_path(&old_path, &new_path_str, &new_folder, &filename)
-        .map_err(|e| e.to_string())?;
+    if items.is_empty() {
+        return Err("Playlist is empty".to_string());
+    }
+    let start_index = start_index.unwrap_or(0).min(items.len() - 1);
     
-    // Return updated video
-    let video = db.get_video_by_path(&new_path_str)
-        .map_err(|e| e.to_string())?
-        .ok_or_else(|| "Video not found after move".to_string())?;
+    let playlist_file = playlist::temp_playlist_path();
+    let videos: Vec<Video> = items.iter().map(|item| item.video.clone()).collect();
+    playlist::write_m3u(&playlist_file, None, &videos).map_err(|e| e.to_string())?;
     
-    Ok(video)
+    run_player(&app, move |_, state| {
+        state.player.queue.lock().map_err(|e| e.to_string())?.set_playing(false);
+        let mut player = state.player.player.lock().map_err(|e| e.to_string())?;
+        player.play_playlist(&playlist_file, start_index)?;
+        
+        *state.player.playlist.lock().map_err(|e| e.to_string())? = Some(ActivePlaylist {
+            playlist_id: id,
+            item_ids: items.into_iter().map(|item| item.id).collect(),
+        });
+        Ok(())
+    }).await
+}
+
+/// Which playlist item mpv is on, if a playlist is playing
+#[tauri::command]
+pub async fn get_playlist_playback(app: AppHandle) -> Result<Option<PlaylistPlayback>, String> {
+    run_player(&app, |_, state| {
+        let mut player = state.player.player.lock().map_err(|e| e.to_string())?;
+        let active = state.player.playlist.lock().map_err(|e| e.to_string())?;
+        let Some(active) = active.as_ref() else {
+            return Ok(None);
+        };
+        
+        let index = match player.ipc() {
+            Ok(ipc) => ipc.state().playlist_pos,
+            Err(_) => None,
+        };
+        
+        Ok(index.and_then(|index| {
+            active.item_ids.get(index).map(|item_id| PlaylistPlayback {
+                playlist_id: active.playlist_id.clone(),
+                index,
+                item_id: item_id.clone(),
+            })
+        }))
+    }).await
+}
+
+#[tauri::command]
+pub async fn export_playlist(state: State<'_, AppState>, id: String, path: String) -> Result<(), String> {
+    run_db(&state, move |db| {
+        let name = db.get_playlist_name(&id)
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| "Playlist not found".to_string())?;
+        let items = db.get_playlist_items(&id).map_err(|e| e.to_string())?;
+        
+        let videos: Vec<Video> = items.into_iter().map(|item| item.video).collect();
+        playlist::write_m3u(Path::new(&path), Some(&name), &videos).map_err(|e| e.to_string())
+    }).await
+}
+
+/// Create a playlist from an M3U/M3U8 file; entries not in the library are reported back
+#[tauri::command]
+pub async fn import_playlist(state: State<'_, AppState>, path: String) -> Result<PlaylistImport, String> {
+    run_db(&state, move |db| {
+        let m3u = playlist::read_m3u(Path::new(&path)).map_err(|e| e.to_string())?;
+        let name = m3u.name.clone().unwrap_or_else(|| {
+            Path::new(&path)
+                .file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_else(|| "Imported".to_string())
+        });
+        
+        let mut video_ids = Vec::new();
+        let mut missing = Vec::new();
+        for entry in m3u.paths {
+            match db.get_video_by_path(&entry).map_err(|e| e.to_string())? {
+                Some(video) => video_ids.push(video.id),
+                None => missing.push(entry),
+            }
+        }
+        
+        let mut playlist = db.create_playlist(&name).map_err(|e| e.to_string())?;
+        db.insert_playlist_items(&playlist.id, &video_ids, None).map_err(|e| e.to_string())?;
+        playlist.item_count = video_ids.len();
+        
+        Ok::<_, String>(PlaylistImport { playlist, missing })
+    }).await
 }
 
 // ========== Tag Commands ==========
 
 #[tauri::command]
-pub fn create_tag(state: State<AppState>, name: String, color: String) -> Result<Tag, String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    db.create_tag(&name, &color).map_err(|e| e.to_string())
+pub async fn create_tag(state: State<'_, AppState>, name: String, color: String) -> Result<Tag, String> {
+    run_db(&state, move |db| db.create_tag(&name, &color)).await
 }
 
 #[tauri::command]
-pub fn get_tags(state: State<AppState>) -> Result<Vec<Tag>, String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    db.get_tags().map_err(|e| e.to_string())
+pub async fn get_tags(state: State<'_, AppState>) -> Result<Vec<Tag>, String> {
+    run_db(&state, move |db| db.get_tags()).await
 }
 
 #[tauri::command]
-pub fn update_tag(state: State<AppState>, id: String, name: String, color: String) -> Result<(), String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    db.update_tag(&id, &name, &color).map_err(|e| e.to_string())
+pub async fn update_tag(state: State<'_, AppState>, id: String, name: String, color: String) -> Result<(), String> {
+    run_db(&state, move |db| db.update_tag(&id, &name, &color)).await
 }
 
 #[tauri::command]
-pub fn delete_tag(state: State<AppState>, id: String) -> Result<(), String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    db.delete_tag(&id).map_err(|e| e.to_string())
+pub async fn delete_tag(state: State<'_, AppState>, id: String) -> Result<(), String> {
+    run_db(&state, move |db| db.delete_tag(&id)).await
 }
 
 #[tauri::command]
-pub fn set_video_tags(state: State<AppState>, video_id: String, tag_ids: Vec<String>) -> Result<(), String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    db.set_video_tags(&video_id, &tag_ids).map_err(|e| e.to_string())
+pub async fn set_video_tags(state: State<'_, AppState>, video_id: String, tag_ids: Vec<String>) -> Result<(), String> {
+    run_db(&state, move |db| db.set_video_tags(&video_id, &tag_ids)).await
 }
 
 // ========== Participant Commands ==========
 
 #[tauri::command]
-pub fn create_participant(state: State<AppState>, name: String) -> Result<Participant, String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    db.create_participant(&name).map_err(|e| e.to_string())
+pub async fn create_participant(state: State<'_, AppState>, name: String) -> Result<Participant, String> {
+    run_db(&state, move |db| db.create_participant(&name)).await
 }
 
 #[tauri::command]
-pub fn get_participants(state: State<AppState>) -> Result<Vec<Participant>, String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    db.get_participants().map_err(|e| e.to_string())
+pub async fn get_participants(state: State<'_, AppState>) -> Result<Vec<Participant>, String> {
+    run_db(&state, move |db| db.get_participants()).await
 }
 
 #[tauri::command]
-pub fn update_participant(state: State<AppState>, id: String, name: String) -> Result<(), String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    db.update_participant(&id, &name).map_err(|e| e.to_string())
+pub async fn update_participant(state: State<'_, AppState>, id: String, name: String) -> Result<(), String> {
+    run_db(&state, move |db| db.update_participant(&id, &name)).await
 }
 
 #[tauri::command]
-pub fn delete_participant(state: State<AppState>, id: String) -> Result<(), String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    db.delete_participant(&id).map_err(|e| e.to_string())
+pub async fn delete_participant(state: State<'_, AppState>, id: String) -> Result<(), String> {
+    run_db(&state, move |db| db.delete_participant(&id)).await
 }
 
 #[tauri::command]
-pub fn set_video_participants(state: State<AppState>, video_id: String, participant_ids: Vec<String>) -> Result<(), String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    db.set_video_participants(&video_id, &participant_ids).map_err(|e| e.to_string())
+pub async fn set_video_participants(state: State<'_, AppState>, video_id: String, participant_ids: Vec<String>) -> Result<(), String> {
+    run_db(&state, move |db| db.set_video_participants(&video_id, &participant_ids)).await
 }
 
 // ========== Language Commands ==========
 
 #[tauri::command]
-pub fn create_language(state: State<AppState>, code: String, name: String) -> Result<Language, String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    db.create_language(&code, &name).map_err(|e| e.to_string())
+pub async fn create_language(state: State<'_, AppState>, code: String, name: String) -> Result<Language, String> {
+    run_db(&state, move |db| db.create_language(&code, &name)).await
 }
 
 #[tauri::command]
-pub fn get_languages(state: State<AppState>) -> Result<Vec<Language>, String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    db.get_languages().map_err(|e| e.to_string())
+pub async fn get_languages(state: State<'_, AppState>) -> Result<Vec<Language>, String> {
+    run_db(&state, move |db| db.get_languages()).await
 }
 
 #[tauri::command]
-pub fn update_language(state: State<AppState>, id: String, code: String, name: String) -> Result<(), String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    db.update_language(&id, &code, &name).map_err(|e| e.to_string())
+pub async fn update_language(state: State<'_, AppState>, id: String, code: String, name: String) -> Result<(), String> {
+    run_db(&state, move |db| db.update_language(&id, &code, &name)).await
 }
 
 #[tauri::command]
-pub fn delete_language(state: State<AppState>, id: String) -> Result<(), String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    db.delete_language(&id).map_err(|e| e.to_string())
+pub async fn delete_language(state: State<'_, AppState>, id: String) -> Result<(), String> {
+    run_db(&state, move |db| db.delete_language(&id)).await
 }
 
 #[tauri::command]
-pub fn set_video_languages(state: State<AppState>, video_id: String, language_ids: Vec<String>) -> Result<(), String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    db.set_video_languages(&video_id, &language_ids).map_err(|e| e.to_string())
+pub async fn set_video_languages(state: State<'_, AppState>, video_id: String, language_ids: Vec<String>) -> Result<(), String> {
+    run_db(&state, move |db| db.set_video_languages(&video_id, &language_ids)).await
 }
 
 // ========== Playback Commands ==========
 
 #[tauri::command]
-pub fn save_playback_position(state: State<AppState>, video_id: String, position: f64) -> Result<(), String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    db.save_playback_position(&video_id, position).map_err(|e| e.to_string())
+pub async fn save_playback_position(state: State<'_, AppState>, video_id: String, position: f64, duration: Option<f64>) -> Result<(), String> {
+    run_db(&state, move |db| {
+        // Same completion rule as mpv playback so resume behaves identically in both players
+        if playback::is_completed(position, duration) {
+            db.mark_playback_completed(&video_id)
+        } else {
+            db.save_playback_position(&video_id, position)
+        }
+    }).await
+}
+
+#[tauri::command]
+pub async fn get_playback_position(state: State<'_, AppState>, video_id: String) -> Result<Option<f64>, String> {
+    run_db(&state, move |db| db.get_playback_position(&video_id)).await
+}
+
+#[tauri::command]
+pub async fn mark_watched(state: State<'_, AppState>, video_id: String) -> Result<(), String> {
+    run_db(&state, move |db| db.mark_playback_completed(&video_id)).await
+}
+
+#[tauri::command]
+pub async fn mark_unwatched(state: State<'_, AppState>, video_id: String) -> Result<(), String> {
+    run_db(&state, move |db| db.mark_unwatched(&video_id)).await
+}
+
+#[tauri::command]
+pub async fn get_playback_history(state: State<'_, AppState>, video_id: String) -> Result<Option<PlaybackHistory>, String> {
+    run_db(&state, move |db| db.get_playback_history(&video_id)).await
+}
+
+#[tauri::command]
+pub async fn get_watch_history(state: State<'_, AppState>, range: Option<DateRange>, limit: Option<usize>) -> Result<Vec<WatchHistoryEntry>, String> {
+    run_db(&state, move |db| db.get_watch_history(&range.unwrap_or_default(), limit.unwrap_or(100))).await
+}
+
+#[tauri::command]
+pub async fn get_continue_watching(state: State<'_, AppState>, limit: Option<usize>) -> Result<Vec<VideoWithHistory>, String> {
+    run_db(&state, move |db| db.get_continue_watching(limit.unwrap_or(20))).await
+}
+
+/// Start a history session for the built-in player; mpv playback is logged by the tracker
+#[tauri::command]
+pub async fn begin_playback_session(state: State<'_, AppState>, video_id: String, position: f64) -> Result<String, String> {
+    run_db(&state, move |db| db.begin_playback_session(&video_id, position)).await
+}
+
+#[tauri::command]
+pub async fn end_playback_session(state: State<'_, AppState>, session_id: String, position: f64, watched_seconds: f64) -> Result<(), String> {
+    run_db(&state, move |db| db.update_playback_session(&session_id, position, watched_seconds)).await
+}
+
+// ========== Series Commands ==========
+
+#[tauri::command]
+pub async fn get_series(state: State<'_, AppState>) -> Result<Vec<Series>, String> {
+    run_db(&state, move |db| db.get_series()).await
+}
+
+#[tauri::command]
+pub async fn get_seasons(state: State<'_, AppState>, series_id: String) -> Result<Vec<Season>, String> {
+    run_db(&state, move |db| db.get_seasons(&series_id)).await
+}
+
+#[tauri::command]
+pub async fn get_episodes(state: State<'_, AppState>, series_id: String, season: Option<u32>) -> Result<Vec<Episode>, String> {
+    run_db(&state, move |db| db.get_episodes(&series_id, season)).await
+}
+
+#[tauri::command]
+pub async fn get_next_episode(state: State<'_, AppState>, series_id: String) -> Result<Option<Episode>, String> {
+    run_db(&state, move |db| db.get_next_episode(&series_id)).await
 }
 
+/// Play the next unwatched episode of a series in mpv, resuming it if it was started
 #[tauri::command]
-pub fn get_playback_position(state: State<AppState>, video_id: String) -> Result<Option<f64>, String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    db.get_playback_position(&video_id).map_err(|e| e.to_string())
+pub async fn play_next_episode(app: AppHandle, state: State<'_, AppState>, series_id: String) -> Result<Episode, String> {
+    let episode = run_db(&state, move |db| db.get_next_episode(&series_id)).await?
+        .ok_or("No unwatched episodes left")?;
+    
+    run_player(&app, move |app, state| {
+        state.player.queue.lock().map_err(|e| e.to_string())?.set_playing(false);
+        queue::play_video(app, &episode.video)?;
+        Ok(episode)
+    }).await
+}
+
+// ========== Duplicate Commands ==========
+
+#[tauri::command]
+pub async fn find_duplicates(state: State<'_, AppState>) -> Result<Vec<DuplicateGroup>, String> {
+    run_db(&state, |db| {
+        let groups = db.find_duplicates().map_err(|e| e.to_string())?;
+        
+        groups.into_iter()
+            .map(|(content_hash, videos)| {
+                Ok(DuplicateGroup {
+                    content_hash,
+                    size: videos.first().map(|v| v.size).unwrap_or(0),
+                    videos: videos.into_iter()
+                        .map(|v| with_metadata(db, v))
+                        .collect::<Result<Vec<_>, String>>()?,
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()
+    }).await
+}
+
+/// Make sure every video in `remove_ids` has the same confirmed content hash as `keep_id`
+fn check_duplicates(db: &Database, keep_id: &str, remove_ids: &[String]) -> Result<(), String> {
+    let keep_hash = db.get_content_hash(keep_id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "The video to keep has no confirmed duplicates".to_string())?;
+    
+    for id in remove_ids {
+        if db.get_content_hash(id).map_err(|e| e.to_string())?.as_deref() != Some(keep_hash.as_str()) {
+            return Err("Videos are not confirmed duplicates of each other".to_string());
+        }
+    }
+    Ok(())
+}
+
+/// Keep `keep_id` and fold the metadata of `remove_ids` into it. With
+/// `DuplicateAction::Trash` the other files are moved to the trash; a file
+/// that can't be trashed stays in the library and the error is returned.
+/// Only videos whose full content hashes match are resolved.
+#[tauri::command]
+pub async fn resolve_duplicates(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    keep_id: String,
+    remove_ids: Vec<String>,
+    action: DuplicateAction,
+) -> Result<(), String> {
+    let remove_ids: Vec<String> = remove_ids.into_iter().filter(|id| *id != keep_id).collect();
+    
+    if action == DuplicateAction::Merge {
+        return run_db(&state, move |db| {
+            check_duplicates(db, &keep_id, &remove_ids)?;
+            db.merge_duplicates(&keep_id, &remove_ids, false).map_err(|e| e.to_string())
+        }).await;
+    }
+    
+    let (keep, trashed, failure) = run_db(&state, move |db| {
+        check_duplicates(db, &keep_id, &remove_ids)?;
+        
+        let mut paths = Vec::with_capacity(remove_ids.len());
+        for id in &remove_ids {
+            let video = db.get_video(id)
+                .map_err(|e| e.to_string())?
+                .ok_or_else(|| "Video not found".to_string())?;
+            paths.push((video.id, video.path));
+        }
+        
+        let mut trashed = Vec::with_capacity(paths.len());
+        let mut failure = None;
+        for (id, path) in paths {
+            match trash::delete(&path) {
+                Ok(()) => trashed.push(id),
+                Err(e) => {
+                    failure = Some(format!("Failed to move {} to the trash: {}", path, e));
+                    break;
+                }
+            }
+        }
+        
+        db.merge_duplicates(&keep_id, &trashed, true).map_err(|e| e.to_string())?;
+        let keep = db.get_video(&keep_id).map_err(|e| e.to_string())?;
+        Ok::<_, String>((keep, trashed, failure))
+    }).await?;
+    
+    // The stored queue now points at the kept copy; bring the live one in line
+    if let Some(keep) = keep {
+        run_player(&app, move |app, state| {
+            let mut queue = state.player.queue.lock().map_err(|e| e.to_string())?;
+            if queue.replace_videos(&trashed, &keep) {
+                queue::publish(app, &queue);
+            }
+            Ok(())
+        }).await?;
+    }
+    
+    match failure {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
+// ========== Play Queue Commands ==========
+
+async fn videos_by_id(state: &State<'_, AppState>, video_ids: Vec<String>) -> Result<Vec<Video>, String> {
+    run_db(state, move |db| {
+        let mut videos = Vec::with_capacity(video_ids.len());
+        for video_id in &video_ids {
+            if let Some(video) = db.get_video(video_id)? {
+                videos.push(video);
+            }
+        }
+        Ok::<_, rusqlite::Error>(videos)
+    }).await
+}
+
+#[tauri::command]
+pub async fn get_queue(app: AppHandle) -> Result<QueueState, String> {
+    run_player(&app, |_, state| {
+        let queue = state.player.queue.lock().map_err(|e| e.to_string())?;
+        Ok(queue.snapshot())
+    }).await
+}
+
+#[tauri::command]
+pub async fn enqueue(app: AppHandle, state: State<'_, AppState>, video_ids: Vec<String>) -> Result<QueueState, String> {
+    let videos = videos_by_id(&state, video_ids).await?;
+    run_player(&app, move |app, state| {
+        let mut queue = state.player.queue.lock().map_err(|e| e.to_string())?;
+        queue.enqueue(videos);
+        queue::publish(app, &queue);
+        Ok(queue.snapshot())
+    }).await
+}
+
+#[tauri::command]
+pub async fn enqueue_next(app: AppHandle, state: State<'_, AppState>, video_ids: Vec<String>) -> Result<QueueState, String> {
+    let videos = videos_by_id(&state, video_ids).await?;
+    run_player(&app, move |app, state| {
+        let mut queue = state.player.queue.lock().map_err(|e| e.to_string())?;
+        queue.play_next(videos);
+        queue::publish(app, &queue);
+        Ok(queue.snapshot())
+    }).await
+}
+
+#[tauri::command]
+pub async fn remove_from_queue(app: AppHandle, index: usize) -> Result<QueueState, String> {
+    run_player(&app, move |app, state| {
+        let mut queue = state.player.queue.lock().map_err(|e| e.to_string())?;
+        queue.remove(index);
+        queue::publish(app, &queue);
+        Ok(queue.snapshot())
+    }).await
+}
+
+#[tauri::command]
+pub async fn move_in_queue(app: AppHandle, from: usize, to: usize) -> Result<QueueState, String> {
+    run_player(&app, move |app, state| {
+        let mut queue = state.player.queue.lock().map_err(|e| e.to_string())?;
+        queue.move_item(from, to);
+        queue::publish(app, &queue);
+        Ok(queue.snapshot())
+    }).await
+}
+
+#[tauri::command]
+pub async fn clear_queue(app: AppHandle) -> Result<QueueState, String> {
+    run_player(&app, move |app, state| {
+        let mut queue = state.player.queue.lock().map_err(|e| e.to_string())?;
+        queue.clear();
+        queue::publish(app, &queue);
+        Ok(queue.snapshot())
+    }).await
+}
+
+#[tauri::command]
+pub async fn shuffle_queue(app: AppHandle) -> Result<QueueState, String> {
+    run_player(&app, move |app, state| {
+        let mut queue = state.player.queue.lock().map_err(|e| e.to_string())?;
+        queue.shuffle();
+        queue::publish(app, &queue);
+        Ok(queue.snapshot())
+    }).await
+}
+
+#[tauri::command]
+pub async fn set_repeat_mode(app: AppHandle, mode: RepeatMode) -> Result<QueueState, String> {
+    run_player(&app, move |app, state| {
+        let mut queue = state.player.queue.lock().map_err(|e| e.to_string())?;
+        queue.set_repeat(mode);
+        queue::publish(app, &queue);
+        Ok(queue.snapshot())
+    }).await
+}
+
+/// Start playing the queue at `index`, or resume at its current item
+#[tauri::command]
+pub async fn play_queue(app: AppHandle, index: Option<usize>) -> Result<QueueState, String> {
+    run_player(&app, move |app, state| {
+        let mut queue = state.player.queue.lock().map_err(|e| e.to_string())?;
+        let index = index.or(queue.current()).unwrap_or(0);
+        let video = queue.jump(index).ok_or("Queue is empty")?;
+        
+        queue::play_video(app, &video)?;
+        queue.set_playing(true);
+        queue::publish(app, &queue);
+        Ok(queue.snapshot())
+    }).await
+}
+
+#[tauri::command]
+pub async fn queue_next(app: AppHandle) -> Result<QueueState, String> {
+    run_player(&app, |app, state| {
+        let mut queue = state.player.queue.lock().map_err(|e| e.to_string())?;
+        let video = queue.next().ok_or("No next item in the queue")?;
+        
+        queue::play_video(app, &video)?;
+        queue.set_playing(true);
+        queue::publish(app, &queue);
+        Ok(queue.snapshot())
+    }).await
+}
+
+#[tauri::command]
+pub async fn queue_previous(app: AppHandle) -> Result<QueueState, String> {
+    run_player(&app, |app, state| {
+        let mut queue = state.player.queue.lock().map_err(|e| e.to_string())?;
+        let video = queue.previous().ok_or("No previous item in the queue")?;
+        
+        queue::play_video(app, &video)?;
+        queue.set_playing(true);
+        queue::publish(app, &queue);
+        Ok(queue.snapshot())
+    }).await
 }
 
 // ========== Thumbnail Commands ==========
@@ -279,30 +935,115 @@ pub fn get_thumbnail_path(video_path: String) -> Result<Option<String>, String>
     Ok(scanner::find_thumbnail_for_video(path))
 }
 
+#[tauri::command]
+pub async fn get_thumbnail_offset(state: State<'_, AppState>) -> Result<f64, String> {
+    let offset = run_db(&state, |db| db.get_setting(thumbnails::OFFSET_SETTING)).await?
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(thumbnails::DEFAULT_OFFSET_PERCENT);
+    Ok(offset)
+}
+
+#[tauri::command]
+pub async fn set_thumbnail_offset(state: State<'_, AppState>, percent: f64) -> Result<(), String> {
+    if !(0.0..100.0).contains(&percent) {
+        return Err("Thumbnail offset must be between 0 and 100 percent".to_string());
+    }
+    run_db(&state, move |db| db.set_setting(thumbnails::OFFSET_SETTING, &percent.to_string())).await
+}
+
 // ========== MPV Player Commands ==========
 
 #[tauri::command]
-pub fn play_video_mpv(
-    state: State<AppState>,
+pub async fn play_video_mpv(
+    app: AppHandle,
     video_path: String,
     subtitle_path: Option<String>,
     start_position: Option<f64>,
 ) -> Result<(), String> {
-    let mut player = state.player.player.lock().map_err(|e| e.to_string())?;
-    player.play(&video_path, subtitle_path.as_deref(), start_position)
+    run_player(&app, move |_, state| {
+        state.player.queue.lock().map_err(|e| e.to_string())?.set_playing(false);
+        let mut player = state.player.player.lock().map_err(|e| e.to_string())?;
+        *state.player.playlist.lock().map_err(|e| e.to_string())? = None;
+        player.play(&video_path, subtitle_path.as_deref(), start_position)
+    }).await
 }
 
 #[tauri::command]
-pub fn stop_video_mpv(state: State<AppState>) -> Result<(), String> {
-    let mut player = state.player.player.lock().map_err(|e| e.to_string())?;
-    player.stop();
-    Ok(())
+pub async fn stop_video_mpv(app: AppHandle) -> Result<(), String> {
+    run_player(&app, |_, state| {
+        let mut player = state.player.player.lock().map_err(|e| e.to_string())?;
+        player.stop();
+        Ok(())
+    }).await
+}
+
+#[tauri::command]
+pub async fn is_mpv_running(app: AppHandle) -> Result<bool, String> {
+    run_player(&app, |_, state| {
+        let mut player = state.player.player.lock().map_err(|e| e.to_string())?;
+        Ok(player.is_running())
+    }).await
+}
+
+#[tauri::command]
+pub async fn mpv_get_state(app: AppHandle) -> Result<MpvState, String> {
+    run_player(&app, |_, state| {
+        let mut player = state.player.player.lock().map_err(|e| e.to_string())?;
+        match player.ipc() {
+            Ok(ipc) => Ok(ipc.state()),
+            Err(_) => Ok(MpvState {
+                running: player.is_running(),
+                ..Default::default()
+            }),
+        }
+    }).await
 }
 
 #[tauri::command]
-pub fn is_mpv_running(state: State<AppState>) -> Result<bool, String> {
-    let mut player = state.player.player.lock().map_err(|e| e.to_string())?;
-    Ok(player.is_running())
+pub async fn mpv_toggle_pause(app: AppHandle) -> Result<(), String> {
+    mpv_command(&app, json!(["cycle", "pause"])).await
+}
+
+#[tauri::command]
+pub async fn mpv_seek(app: AppHandle, position: f64, relative: Option<bool>) -> Result<(), String> {
+    let mode = if relative.unwrap_or(false) { "relative" } else { "absolute" };
+    mpv_command(&app, json!(["seek", position, mode])).await
+}
+
+#[tauri::command]
+pub async fn mpv_set_volume(app: AppHandle, volume: f64) -> Result<(), String> {
+    run_player(&app, move |_, state| {
+        let mut player = state.player.player.lock().map_err(|e| e.to_string())?;
+        player.ipc()?.set_property("volume", json!(volume.clamp(0.0, 130.0)))
+    }).await
+}
+
+#[tauri::command]
+pub async fn mpv_cycle_subtitle(app: AppHandle) -> Result<(), String> {
+    mpv_command(&app, json!(["cycle", "sub"])).await
+}
+
+#[tauri::command]
+pub async fn mpv_cycle_audio(app: AppHandle) -> Result<(), String> {
+    mpv_command(&app, json!(["cycle", "audio"])).await
+}
+
+#[tauri::command]
+pub async fn mpv_load_file(app: AppHandle, video_path: String) -> Result<(), String> {
+    run_player(&app, move |_, state| {
+        state.player.queue.lock().map_err(|e| e.to_string())?.set_playing(false);
+        let mut player = state.player.player.lock().map_err(|e| e.to_string())?;
+        *state.player.playlist.lock().map_err(|e| e.to_string())? = None;
+        player.ipc()?.command(json!(["loadfile", video_path, "replace"])).map(|_| ())
+    }).await
+}
+
+/// Send a command to the running mpv, ignoring its reply
+async fn mpv_command(app: &AppHandle, command: Value) -> Result<(), String> {
+    run_player(app, move |_, state| {
+        let mut player = state.player.player.lock().map_err(|e| e.to_string())?;
+        player.ipc()?.command(command).map(|_| ())
+    }).await
 }
 
 #[tauri::command]
@@ -1,10 +1,537 @@
-use rusqlite::{Connection, Result, params};
-use std::path::PathBuf;
-use std::sync::Mutex;
+use rusqlite::{Connection, Result, Row, params};
+use std::collections::{HashMap, HashSet};
+use std::path::{MAIN_SEPARATOR, Path, PathBuf};
+use crate::folder_tree::{self, FolderTotals};
+use crate::hashing;
+use crate::migrations;
 use crate::models::*;
+use crate::pool::{self, ConnectionPool};
+use crate::query;
+use crate::series;
 
+const VIDEO_COLUMNS: &str =
+    "v.id, v.path, v.filename, v.folder_path, v.size, v.duration, v.thumbnail_path, v.mtime, v.created_at, v.updated_at, v.availability";
+
+/// Number of columns in `VIDEO_COLUMNS`, i.e. the index of the first column selected after them
+const VIDEO_COLUMN_COUNT: usize = 11;
+
+/// Read-only connections kept open next to the writer
+const READER_COUNT: usize = 4;
+
+/// Library database. Read methods run on a pooled read-only connection and
+/// write methods on the single writer, so a long scan doesn't block browsing.
 pub struct Database {
-    conn: Mutex<Connection>,
+    pool: ConnectionPool,
+}
+
+/// Outcome of reconciling a folder scan with the rows already in the database
+#[derive(Debug, Default, Clone)]
+pub struct ScanChanges {
+    pub new_videos: usize,
+    pub updated_videos: usize,
+    pub removed_videos: usize,
+    pub relinked: Vec<RelinkedVideo>,
+}
+
+const HISTORY_COLUMNS: &str =
+    "ph.video_id, ph.position, ph.completed, ph.play_count, ph.total_watch_time, ph.first_played, ph.last_played";
+
+fn video_from_row(row: &Row) -> Result<Video> {
+    Ok(Video {
+        id: row.get(0)?,
+        path: row.get(1)?,
+        filename: row.get(2)?,
+        folder_path: row.get(3)?,
+        size: row.get(4)?,
+        duration: row.get(5)?,
+        thumbnail_path: row.get(6)?,
+        mtime: row.get(7)?,
+        created_at: row.get(8)?,
+        updated_at: row.get(9)?,
+        availability: Availability::parse(&row.get::<_, String>(10)?),
+    })
+}
+
+/// Read playback history columns selected with `HISTORY_COLUMNS`, starting at `offset`
+fn history_from_row(row: &Row, offset: usize) -> Result<PlaybackHistory> {
+    Ok(PlaybackHistory {
+        video_id: row.get(offset)?,
+        position: row.get(offset + 1)?,
+        completed: row.get(offset + 2)?,
+        play_count: row.get(offset + 3)?,
+        total_watch_time: row.get(offset + 4)?,
+        first_played: row.get(offset + 5)?,
+        last_played: row.get(offset + 6)?,
+    })
+}
+
+pub fn watch_state_condition(state: WatchState) -> &'static str {
+    match state {
+        WatchState::Watched =>
+            "EXISTS (SELECT 1 FROM playback_history ph WHERE ph.video_id = v.id AND ph.completed = 1)",
+        WatchState::Unwatched =>
+            "NOT EXISTS (SELECT 1 FROM playback_history ph WHERE ph.video_id = v.id AND ph.completed = 1)",
+        WatchState::InProgress =>
+            "EXISTS (SELECT 1 FROM playback_history ph WHERE ph.video_id = v.id AND ph.completed = 0 AND ph.position > 0)",
+    }
+}
+
+/// Weights for `bm25()`, one per `video_search` column: a hit in the filename
+/// counts for more than one in the path or notes
+const SEARCH_RANK: &str = "bm25(video_search, 10.0, 2.0, 5.0, 5.0, 3.0, 1.0)";
+
+/// SQL that rewrites the `video_search` rows of all videos matching `condition`
+/// (an expression over `videos v`), as the triggers created by migration step 4 do.
+///
+/// Index rows are keyed by `video_search_keys.id` rather than the rowid of
+/// `videos`: that table has a TEXT primary key, so VACUUM may renumber its rowids.
+fn refresh_search_sql(condition: &str) -> String {
+    format!(r#"
+        INSERT OR IGNORE INTO video_search_keys (video_id) SELECT v.id FROM videos v WHERE {condition};
+        DELETE FROM video_search WHERE rowid IN (
+            SELECT k.id FROM video_search_keys k INNER JOIN videos v ON v.id = k.video_id WHERE {condition});
+        INSERT INTO video_search (rowid, filename, path, tags, participants, languages, notes)
+        SELECT k.id, v.filename, v.path,
+            COALESCE((SELECT group_concat(t.name, ' ') FROM video_tags vt
+                      INNER JOIN tags t ON t.id = vt.tag_id WHERE vt.video_id = v.id), ''),
+            COALESCE((SELECT group_concat(p.name, ' ') FROM video_participants vp
+                      INNER JOIN participants p ON p.id = vp.participant_id WHERE vp.video_id = v.id), ''),
+            COALESCE((SELECT group_concat(l.name || ' ' || l.code, ' ') FROM video_languages vl
+                      INNER JOIN languages l ON l.id = vl.language_id WHERE vl.video_id = v.id), ''),
+            COALESCE(v.notes, '')
+        FROM videos v INNER JOIN video_search_keys k ON k.video_id = v.id WHERE {condition};
+    "#)
+}
+
+/// SQL that recreates every row of the full-text index
+fn rebuild_search_sql() -> String {
+    format!("DELETE FROM video_search; DELETE FROM video_search_keys; {}", refresh_search_sql("1"))
+}
+
+/// Translate a search box query into an FTS5 expression: every word must match
+/// as a prefix and "quoted text" as an exact phrase. Returns `None` when the
+/// query contains nothing searchable.
+pub fn fts_match_expression(query: &str) -> Option<String> {
+    let searchable = |text: &str| text.chars().any(char::is_alphanumeric);
+    let mut terms: Vec<String> = Vec::new();
+    let mut rest = query;
+    
+    loop {
+        rest = rest.trim_start();
+        if rest.is_empty() {
+            break;
+        }
+        
+        if let Some(quoted) = rest.strip_prefix('"') {
+            // An unterminated quote runs to the end of the query
+            let (phrase, remaining) = quoted.split_once('"').unwrap_or((quoted, ""));
+            if searchable(phrase) {
+                terms.push(format!("\"{}\"", phrase));
+            }
+            rest = remaining;
+        } else {
+            let end = rest.find(|c: char| c.is_whitespace() || c == '"').unwrap_or(rest.len());
+            let word = &rest[..end];
+            if searchable(word) {
+                terms.push(format!("\"{}\"*", word));
+            }
+            rest = &rest[end..];
+        }
+    }
+    
+    if terms.is_empty() {
+        None
+    } else {
+        Some(terms.join(" "))
+    }
+}
+
+fn collection_from_row(row: &Row) -> Result<SmartCollection> {
+    let filter_json: String = row.get(2)?;
+    let filter = serde_json::from_str(&filter_json).map_err(|e| {
+        rusqlite::Error::FromSqlConversionFailure(2, rusqlite::types::Type::Text, Box::new(e))
+    })?;
+    
+    Ok(SmartCollection {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        filter,
+        created_at: row.get(3)?,
+        updated_at: row.get(4)?,
+    })
+}
+
+fn mounted_folder_from_row(row: &Row) -> Result<MountedFolder> {
+    // Folders added before scan rules existed use the defaults
+    let scan_rules = match row.get::<_, Option<String>>(5)? {
+        Some(rules_json) => serde_json::from_str(&rules_json).map_err(|e| {
+            rusqlite::Error::FromSqlConversionFailure(5, rusqlite::types::Type::Text, Box::new(e))
+        })?,
+        None => ScanRules::default(),
+    };
+    
+    Ok(MountedFolder {
+        id: row.get(0)?,
+        path: row.get(1)?,
+        name: row.get(2)?,
+        scan_depth: row.get::<_, i64>(3)? as usize,
+        created_at: row.get(4)?,
+        scan_rules,
+    })
+}
+
+/// Close gaps in a playlist's positions after items were removed or moved
+fn renumber_playlist(conn: &Connection, playlist_id: &str) -> Result<()> {
+    let item_ids: Vec<String> = {
+        let mut stmt = conn.prepare(
+            "SELECT id FROM playlist_items WHERE playlist_id = ?1 ORDER BY position, rowid"
+        )?;
+        let rows = stmt.query_map(params![playlist_id], |row| row.get(0))?
+            .collect::<Result<Vec<_>>>()?;
+        rows
+    };
+    
+    let mut stmt = conn.prepare("UPDATE playlist_items SET position = ?1 WHERE id = ?2")?;
+    for (position, item_id) in item_ids.iter().enumerate() {
+        stmt.execute(params![position as i64, item_id])?;
+    }
+    
+    conn.execute(
+        "UPDATE playlists SET updated_at = ?1 WHERE id = ?2",
+        params![chrono::Utc::now().to_rfc3339(), playlist_id],
+    )?;
+    Ok(())
+}
+
+/// FROM and WHERE clauses selecting the videos that match a filter
+struct FilterQuery {
+    from_where: String,
+    params: Vec<Box<dyn rusqlite::ToSql>>,
+    /// Whether the full-text index is joined as `s`, exposing `s.search_rank`
+    searching: bool,
+}
+
+/// Condition on membership in one of the video junction tables: with
+/// `MatchMode::Any` a video needs one of `ids`, with `MatchMode::All` every one
+/// of them, and it must have none of `exclude_ids`
+fn membership_conditions(
+    table: &str,
+    column: &str,
+    ids: &[String],
+    mode: MatchMode,
+    exclude_ids: &[String],
+    conditions: &mut Vec<String>,
+    params_vec: &mut Vec<Box<dyn rusqlite::ToSql>>,
+) {
+    let placeholders = |ids: &[String], params_vec: &mut Vec<Box<dyn rusqlite::ToSql>>| {
+        let list: Vec<String> = ids.iter().enumerate()
+            .map(|(i, _)| format!("?{}", params_vec.len() + i + 1))
+            .collect();
+        for id in ids {
+            params_vec.push(Box::new(id.clone()));
+        }
+        list.join(",")
+    };
+    
+    // "All" compares a distinct count with the number of ids, so repeats would never match
+    let mut unique: Vec<String> = Vec::with_capacity(ids.len());
+    for id in ids {
+        if !unique.contains(id) {
+            unique.push(id.clone());
+        }
+    }
+    let ids = unique.as_slice();
+    
+    if !ids.is_empty() {
+        let list = placeholders(ids, params_vec);
+        conditions.push(match mode {
+            MatchMode::Any => format!(
+                "v.id IN (SELECT video_id FROM {} WHERE {} IN ({}))",
+                table, column, list
+            ),
+            MatchMode::All => format!(
+                "v.id IN (SELECT video_id FROM {0} WHERE {1} IN ({2}) GROUP BY video_id HAVING COUNT(DISTINCT {1}) = {3})",
+                table, column, list, ids.len()
+            ),
+        });
+    }
+    
+    if !exclude_ids.is_empty() {
+        let list = placeholders(exclude_ids, params_vec);
+        conditions.push(format!(
+            "NOT EXISTS (SELECT 1 FROM {} x WHERE x.video_id = v.id AND x.{} IN ({}))",
+            table, column, list
+        ));
+    }
+}
+
+/// Build the part of the video query that `get_videos` and `get_video_count`
+/// share, so a page of results and the total never disagree
+fn build_filter_query(filter: &FilterOptions) -> Result<FilterQuery> {
+    let mut from_where = String::from("FROM videos v");
+    // Videos the scan rules skip stay out of the library until the rules change
+    let mut conditions: Vec<String> = vec!["v.availability != 'excluded'".to_string()];
+    let mut params_vec: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
+    
+    // Full-text search
+    let search = filter.search_query.as_deref().and_then(fts_match_expression);
+    if let Some(ref expression) = search {
+        from_where.push_str(&format!(
+            " INNER JOIN (SELECT rowid AS search_rowid, {} AS search_rank FROM video_search WHERE video_search MATCH ?{}) s
+              INNER JOIN video_search_keys sk ON sk.id = s.search_rowid AND sk.video_id = v.id",
+            SEARCH_RANK, params_vec.len() + 1
+        ));
+        params_vec.push(Box::new(expression.clone()));
+    }
+    
+    // Folder filter
+    if let Some(ref folder) = filter.folder_path {
+        conditions.push(format!(
+            "(v.folder_path = ?{} OR v.folder_path LIKE ?{} ESCAPE '\\')",
+            params_vec.len() + 1, params_vec.len() + 2
+        ));
+        params_vec.push(Box::new(folder.clone()));
+        params_vec.push(Box::new(subfolder_pattern(folder)));
+    }
+    
+    // Tag, participant and language filters
+    membership_conditions(
+        "video_tags", "tag_id",
+        &filter.tag_ids, filter.tag_match, &filter.exclude_tag_ids,
+        &mut conditions, &mut params_vec,
+    );
+    membership_conditions(
+        "video_participants", "participant_id",
+        &filter.participant_ids, filter.participant_match, &filter.exclude_participant_ids,
+        &mut conditions, &mut params_vec,
+    );
+    membership_conditions(
+        "video_languages", "language_id",
+        &filter.language_ids, filter.language_match, &filter.exclude_language_ids,
+        &mut conditions, &mut params_vec,
+    );
+    
+    // Watched state
+    if let Some(watch_state) = filter.watch_state {
+        conditions.push(watch_state_condition(watch_state).to_string());
+    }
+    
+    // Query language
+    if let Some(ref query) = filter.query {
+        if !query.trim().is_empty() {
+            // Commands downcast this back to a `QueryError` for the UI
+            let expr = query::parse(query)
+                .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+            let (condition, values) = expr.to_sql(params_vec.len() + 1);
+            conditions.push(condition);
+            for value in values {
+                params_vec.push(Box::new(value));
+            }
+        }
+    }
+    
+    if !conditions.is_empty() {
+        from_where.push_str(" WHERE ");
+        from_where.push_str(&conditions.join(" AND "));
+    }
+    
+    Ok(FilterQuery {
+        from_where,
+        params: params_vec,
+        searching: search.is_some(),
+    })
+}
+
+/// Find the stored row of a file that was moved or renamed to `video.path`.
+///
+/// Candidates are rows of the same size whose file no longer exists, except
+/// those on an offline volume, which may well come back. They
+/// match on the same inode and mtime (a rename or move within one volume) or,
+/// failing that, on the same partial hash (a move across volumes). The new
+/// file is only hashed when a candidate of its size exists.
+fn find_moved_video(conn: &Connection, video: &Video, claimed: &HashSet<String>) -> Result<Option<Video>> {
+    let mut stmt = conn.prepare_cached(&format!(
+        r#"SELECT {}, v.inode, v.partial_hash FROM videos v
+           WHERE v.size = ?1 AND v.path != ?2 AND v.availability != 'offline_volume'"#,
+        VIDEO_COLUMNS
+    ))?;
+    let candidates = stmt.query_map(params![video.size, video.path], |row| {
+        Ok((
+            video_from_row(row)?,
+            row.get::<_, Option<i64>>(VIDEO_COLUMN_COUNT)?,
+            row.get::<_, Option<String>>(VIDEO_COLUMN_COUNT + 1)?,
+        ))
+    })?.collect::<Result<Vec<_>>>()?;
+    
+    let path = Path::new(&video.path);
+    let inode = hashing::inode(path);
+    let mut partial_hash: Option<Option<String>> = None;
+    
+    for (old, old_inode, old_hash) in candidates {
+        if claimed.contains(&old.id) || Path::new(&old.path).exists() {
+            continue;
+        }
+        if old_inode.is_some() && old_inode == inode && old.mtime == video.mtime {
+            return Ok(Some(old));
+        }
+        if let Some(old_hash) = old_hash {
+            let hash = partial_hash.get_or_insert_with(|| hashing::partial_hash(path).ok());
+            if hash.as_deref() == Some(old_hash.as_str()) {
+                return Ok(Some(old));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Point the row of `old` at the file `video` was found at. `video` takes over
+/// the old id, so its tags, notes and history carry over.
+fn relink_moved_video(conn: &Connection, video: &mut Video, old: Video, inode: Option<i64>) -> Result<RelinkedVideo> {
+    video.id = old.id;
+    video.created_at = old.created_at;
+    video.duration = old.duration;
+    // A sidecar image next to the old path moved away with the file
+    if video.thumbnail_path.is_none() && old.thumbnail_path.as_deref().is_some_and(|t| Path::new(t).exists()) {
+        video.thumbnail_path = old.thumbnail_path;
+    }
+    conn.execute(
+        r#"UPDATE videos SET path = ?1, filename = ?2, folder_path = ?3, mtime = ?4,
+               thumbnail_path = ?5, inode = ?6, availability = 'online', updated_at = ?7
+           WHERE id = ?8"#,
+        params![
+            video.path,
+            video.filename,
+            video.folder_path,
+            video.mtime,
+            video.thumbnail_path,
+            inode,
+            video.updated_at,
+            video.id,
+        ],
+    )?;
+    Ok(RelinkedVideo {
+        video_id: video.id.clone(),
+        old_path: old.path,
+        new_path: video.path.clone(),
+    })
+}
+
+fn insert_video(conn: &Connection, video: &Video, inode: Option<i64>) -> Result<()> {
+    conn.prepare_cached(
+        r#"INSERT INTO videos (id, path, filename, folder_path, size, duration, thumbnail_path, mtime, inode, created_at, updated_at)
+           VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)"#,
+    )?.execute(params![
+        video.id,
+        video.path,
+        video.filename,
+        video.folder_path,
+        video.size,
+        video.duration,
+        video.thumbnail_path,
+        video.mtime,
+        inode,
+        video.created_at,
+        video.updated_at,
+    ])?;
+    Ok(())
+}
+
+/// Parse the file names of `video_ids` again and replace their episode rows,
+/// creating series as needed. Series left without episodes are dropped.
+/// Returns the number of episode files found.
+fn update_episodes(conn: &Connection, video_ids: &[String]) -> Result<usize> {
+    let now = chrono::Utc::now().to_rfc3339();
+    let mut found = 0;
+    
+    for video_id in video_ids {
+        conn.prepare_cached("DELETE FROM episodes WHERE video_id = ?1")?.execute(params![video_id])?;
+        let mut rows = conn.prepare_cached("SELECT filename, folder_path FROM videos WHERE id = ?1")?
+            .query_map(params![video_id], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?
+            .collect::<Result<Vec<_>>>()?;
+        let Some((filename, folder_path)) = rows.pop() else {
+            continue;
+        };
+        let Some(parsed) = series::parse_filename(&filename) else {
+            continue;
+        };
+        // `Show/Season 1/S01E01.mkv` names the series in its folders
+        let (title, year) = if parsed.title.is_empty() {
+            match series::title_from_folder(&folder_path) {
+                Some(found) => found,
+                None => continue,
+            }
+        } else {
+            (parsed.title.clone(), parsed.year)
+        };
+        let key = series::match_key(&title, year);
+        if key.is_empty() {
+            continue;
+        }
+        
+        let existing: Option<String> = conn.prepare_cached("SELECT id FROM series WHERE match_key = ?1")?
+            .query_map(params![key], |row| row.get(0))?
+            .next()
+            .transpose()?;
+        let series_id = match existing {
+            Some(id) => id,
+            None => {
+                let id = uuid::Uuid::new_v4().to_string();
+                conn.prepare_cached(
+                    "INSERT INTO series (id, title, match_key, year, created_at) VALUES (?1, ?2, ?3, ?4, ?5)"
+                )?.execute(params![id, title, key, year, now])?;
+                id
+            }
+        };
+        conn.prepare_cached(
+            r#"INSERT INTO episodes (video_id, series_id, season, episode, episode_end, resolution, release_group)
+               VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)"#
+        )?.execute(params![
+            video_id,
+            series_id,
+            parsed.season,
+            parsed.episode,
+            parsed.episode_end,
+            parsed.resolution,
+            parsed.release_group,
+        ])?;
+        found += 1;
+    }
+    
+    conn.execute("DELETE FROM series WHERE id NOT IN (SELECT series_id FROM episodes)", [])?;
+    Ok(found)
+}
+
+/// Rows that point at a parent that no longer exists, as `(table, condition)`.
+/// Foreign keys were not enforced before schema version 9, so older databases
+/// can have plenty of these.
+const ORPHAN_CONDITIONS: &[(&str, &str)] = &[
+    ("video_tags", "video_id NOT IN (SELECT id FROM videos) OR tag_id NOT IN (SELECT id FROM tags)"),
+    ("video_participants", "video_id NOT IN (SELECT id FROM videos) OR participant_id NOT IN (SELECT id FROM participants)"),
+    ("video_languages", "video_id NOT IN (SELECT id FROM videos) OR language_id NOT IN (SELECT id FROM languages)"),
+    ("playback_history", "video_id NOT IN (SELECT id FROM videos)"),
+    ("playback_sessions", "video_id NOT IN (SELECT id FROM videos)"),
+    ("playlist_items", "video_id NOT IN (SELECT id FROM videos) OR playlist_id NOT IN (SELECT id FROM playlists)"),
+    ("play_queue", "video_id NOT IN (SELECT id FROM videos)"),
+];
+
+/// Delete every orphaned row; returns how many were removed
+fn delete_orphans(conn: &Connection) -> Result<usize> {
+    let mut removed = 0;
+    for (table, condition) in ORPHAN_CONDITIONS {
+        removed += conn.execute(&format!("DELETE FROM {} WHERE {}", table, condition), [])?;
+    }
+    Ok(removed)
+}
+
+/// LIKE pattern matching every folder strictly below `folder_path`.
+/// Use together with `folder_path = ?` so `/a/foo` never matches `/a/foobar`.
+fn subfolder_pattern(folder_path: &str) -> String {
+    let escaped = folder_path
+        .trim_end_matches(MAIN_SEPARATOR)
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("{}{}%", escaped, MAIN_SEPARATOR)
 }
 
 impl Database {
@@ -16,168 +543,74 @@ impl Database {
             std::fs::create_dir_all(parent).ok();
         }
         
-        let conn = Connection::open(&db_path)?;
-        let db = Database {
-            conn: Mutex::new(conn),
-        };
-        db.init_tables()?;
-        Ok(db)
+        Self::open(&db_path)
     }
     
-    fn get_db_path() -> PathBuf {
-        let data_dir = dirs::data_dir()
+    /// Open (creating and migrating as needed) the database at `db_path`
+    pub fn open(db_path: &Path) -> Result<Self> {
+        let mut writer = pool::open_connection(db_path)?;
+        migrations::migrate(&mut writer, db_path)?;
+        Ok(Database {
+            pool: ConnectionPool::new(writer, db_path, READER_COUNT)?,
+        })
+    }
+    
+    /// Directory holding the database and generated files such as thumbnails
+    pub fn get_data_dir() -> PathBuf {
+        dirs::data_dir()
             .unwrap_or_else(|| PathBuf::from("."))
-            .join("VideoPlayer");
-        data_dir.join("database.sqlite")
-    }
-    
-    fn init_tables(&self) -> Result<()> {
-        let conn = self.conn.lock().unwrap();
-        
-        conn.execute_batch(r#"
-            -- Mounted folders table
-            CREATE TABLE IF NOT EXISTS mounted_folders (
-                id TEXT PRIMARY KEY,
-                path TEXT NOT NULL UNIQUE,
-                name TEXT NOT NULL,
-                scan_depth INTEGER NOT NULL DEFAULT 2,
-                created_at TEXT NOT NULL
-            );
-            
-            -- Videos table
-            CREATE TABLE IF NOT EXISTS videos (
-                id TEXT PRIMARY KEY,
-                path TEXT NOT NULL UNIQUE,
-                filename TEXT NOT NULL,
-                folder_path TEXT NOT NULL,
-                size INTEGER NOT NULL DEFAULT 0,
-                duration REAL,
-                thumbnail_path TEXT,
-                created_at TEXT NOT NULL,
-                updated_at TEXT NOT NULL
-            );
-            
-            -- Tags table
-            CREATE TABLE IF NOT EXISTS tags (
-                id TEXT PRIMARY KEY,
-                name TEXT NOT NULL UNIQUE,
-                color TEXT NOT NULL DEFAULT '#6366f1'
-            );
-            
-            -- Video-Tags junction table
-            CREATE TABLE IF NOT EXISTS video_tags (
-                video_id TEXT NOT NULL,
-                tag_id TEXT NOT NULL,
-                PRIMARY KEY (video_id, tag_id),
-                FOREIGN KEY (video_id) REFERENCES videos(id) ON DELETE CASCADE,
-                FOREIGN KEY (tag_id) REFERENCES tags(id) ON DELETE CASCADE
-            );
-            
-            -- Participants table
-            CREATE TABLE IF NOT EXISTS participants (
-                id TEXT PRIMARY KEY,
-                name TEXT NOT NULL UNIQUE
-            );
-            
-            -- Video-Participants junction table
-            CREATE TABLE IF NOT EXISTS video_participants (
-                video_id TEXT NOT NULL,
-                participant_id TEXT NOT NULL,
-                PRIMARY KEY (video_id, participant_id),
-                FOREIGN KEY (video_id) REFERENCES videos(id) ON DELETE CASCADE,
-                FOREIGN KEY (participant_id) REFERENCES participants(id) ON DELETE CASCADE
-            );
-            
-            -- Languages table
-            CREATE TABLE IF NOT EXISTS languages (
-                id TEXT PRIMARY KEY,
-                code TEXT NOT NULL UNIQUE,
-                name TEXT NOT NULL
-            );
-            
-            -- Video-Languages junction table
-            CREATE TABLE IF NOT EXISTS video_languages (
-                video_id TEXT NOT NULL,
-                language_id TEXT NOT NULL,
-                PRIMARY KEY (video_id, language_id),
-                FOREIGN KEY (video_id) REFERENCES videos(id) ON DELETE CASCADE,
-                FOREIGN KEY (language_id) REFERENCES languages(id) ON DELETE CASCADE
-            );
-            
-            -- Playback history
-            CREATE TABLE IF NOT EXISTS playback_history (
-                video_id TEXT PRIMARY KEY,
-                position REAL NOT NULL DEFAULT 0,
-                last_played TEXT NOT NULL,
-                FOREIGN KEY (video_id) REFERENCES videos(id) ON DELETE CASCADE
-            );
-            
-            -- Create indexes for better performance
-            CREATE INDEX IF NOT EXISTS idx_videos_folder ON videos(folder_path);
-            CREATE INDEX IF NOT EXISTS idx_videos_filename ON videos(filename);
-        "#)?;
-        
-        Ok(())
+            .join("VideoPlayer")
+    }
+    
+    fn get_db_path() -> PathBuf {
+        Self::get_data_dir().join("database.sqlite")
     }
     
     // ========== Mounted Folders ==========
     
+    /// Add a folder to the library. Adding a path that is already mounted
+    /// updates its name and depth and keeps its id, scan rules and reports.
     pub fn add_mounted_folder(&self, path: &str, name: &str, scan_depth: usize) -> Result<MountedFolder> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.write();
         let id = uuid::Uuid::new_v4().to_string();
         let created_at = chrono::Utc::now().to_rfc3339();
         
         conn.execute(
-            "INSERT OR REPLACE INTO mounted_folders (id, path, name, scan_depth, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+            r#"INSERT INTO mounted_folders (id, path, name, scan_depth, created_at) VALUES (?1, ?2, ?3, ?4, ?5)
+               ON CONFLICT(path) DO UPDATE SET name = excluded.name, scan_depth = excluded.scan_depth"#,
             params![id, path, name, scan_depth as i64, created_at],
         )?;
         
-        Ok(MountedFolder {
-            id,
-            path: path.to_string(),
-            name: name.to_string(),
-            scan_depth,
-            created_at,
-        })
+        conn.query_row(
+            "SELECT id, path, name, scan_depth, created_at, scan_rules FROM mounted_folders WHERE path = ?1",
+            params![path],
+            mounted_folder_from_row,
+        )
     }
     
     pub fn get_mounted_folders(&self) -> Result<Vec<MountedFolder>> {
-        let conn = self.conn.lock().unwrap();
-        let mut stmt = conn.prepare("SELECT id, path, name, scan_depth, created_at FROM mounted_folders")?;
+        let conn = self.pool.read();
+        let mut stmt = conn.prepare("SELECT id, path, name, scan_depth, created_at, scan_rules FROM mounted_folders")?;
         
-        let folders = stmt.query_map([], |row| {
-            Ok(MountedFolder {
-                id: row.get(0)?,
-                path: row.get(1)?,
-                name: row.get(2)?,
-                scan_depth: row.get::<_, i64>(3)? as usize,
-                created_at: row.get(4)?,
-            })
-        })?.collect::<Result<Vec<_>>>()?;
+        let folders = stmt.query_map([], mounted_folder_from_row)?.collect::<Result<Vec<_>>>()?;
         
         Ok(folders)
     }
     
     pub fn get_mounted_folder(&self, path: &str) -> Result<Option<MountedFolder>> {
-        let conn = self.conn.lock().unwrap();
-        let mut stmt = conn.prepare("SELECT id, path, name, scan_depth, created_at FROM mounted_folders WHERE path = ?1")?;
+        let conn = self.pool.read();
+        let mut stmt = conn.prepare("SELECT id, path, name, scan_depth, created_at, scan_rules FROM mounted_folders WHERE path = ?1")?;
         
         let mut rows = stmt.query(params![path])?;
         if let Some(row) = rows.next()? {
-            Ok(Some(MountedFolder {
-                id: row.get(0)?,
-                path: row.get(1)?,
-                name: row.get(2)?,
-                scan_depth: row.get::<_, i64>(3)? as usize,
-                created_at: row.get(4)?,
-            }))
+            Ok(Some(mounted_folder_from_row(row)?))
         } else {
             Ok(None)
         }
     }
     
     pub fn update_folder_scan_depth(&self, path: &str, scan_depth: usize) -> Result<()> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.write();
         conn.execute(
             "UPDATE mounted_folders SET scan_depth = ?1 WHERE path = ?2",
             params![scan_depth as i64, path],
@@ -185,34 +618,163 @@ impl Database {
         Ok(())
     }
     
+    pub fn update_folder_scan_rules(&self, path: &str, scan_rules: &ScanRules) -> Result<()> {
+        let conn = self.pool.write();
+        let rules_json = serde_json::to_string(scan_rules)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+        conn.execute(
+            "UPDATE mounted_folders SET scan_rules = ?1 WHERE path = ?2",
+            params![rules_json, path],
+        )?;
+        Ok(())
+    }
+    
+    /// Device id the folder was on when it was last scanned
+    pub fn get_folder_device(&self, path: &str) -> Result<Option<u64>> {
+        let conn = self.pool.read();
+        let mut stmt = conn.prepare("SELECT device_id FROM mounted_folders WHERE path = ?1")?;
+        
+        let mut rows = stmt.query(params![path])?;
+        match rows.next()? {
+            Some(row) => Ok(row.get::<_, Option<i64>>(0)?.map(|id| id as u64)),
+            None => Ok(None),
+        }
+    }
+    
+    pub fn set_folder_device(&self, path: &str, device_id: u64) -> Result<()> {
+        let conn = self.pool.write();
+        conn.execute(
+            "UPDATE mounted_folders SET device_id = ?1 WHERE path = ?2",
+            params![device_id as i64, path],
+        )?;
+        Ok(())
+    }
+    
+    /// Whether any video is stored below `folder_path`
+    pub fn has_videos_at(&self, folder_path: &str) -> Result<bool> {
+        let conn = self.pool.read();
+        conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM videos WHERE folder_path = ?1 OR folder_path LIKE ?2 ESCAPE '\\')",
+            params![folder_path, subfolder_pattern(folder_path)],
+            |row| row.get(0),
+        )
+    }
+    
+    /// Replace the stored report of the folder's last scan. Reports for
+    /// folders that are no longer mounted are dropped.
+    pub fn save_scan_report(&self, report: &ScanReport) -> Result<()> {
+        let conn = self.pool.write();
+        let errors_json = serde_json::to_string(&report.errors)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+        conn.execute(
+            r#"INSERT OR REPLACE INTO scan_reports
+                   (folder_path, started_at, duration_ms, status, error, total_videos, new_videos,
+                    updated_videos, removed_videos, relinked_videos, errors)
+               SELECT ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11
+               WHERE EXISTS (SELECT 1 FROM mounted_folders WHERE path = ?1)"#,
+            params![
+                report.folder_path,
+                report.started_at,
+                report.duration_ms as i64,
+                report.status.as_str(),
+                report.error,
+                report.total_videos as i64,
+                report.new_videos as i64,
+                report.updated_videos as i64,
+                report.removed_videos as i64,
+                report.relinked_videos as i64,
+                errors_json,
+            ],
+        )?;
+        Ok(())
+    }
+    
+    pub fn get_last_scan_report(&self, folder_path: &str) -> Result<Option<ScanReport>> {
+        let conn = self.pool.read();
+        let mut stmt = conn.prepare(
+            r#"SELECT folder_path, started_at, duration_ms, status, error, total_videos, new_videos,
+                      updated_videos, removed_videos, relinked_videos, errors
+               FROM scan_reports WHERE folder_path = ?1"#
+        )?;
+        
+        let mut rows = stmt.query(params![folder_path])?;
+        if let Some(row) = rows.next()? {
+            let errors_json: String = row.get(10)?;
+            let errors = serde_json::from_str(&errors_json).map_err(|e| {
+                rusqlite::Error::FromSqlConversionFailure(10, rusqlite::types::Type::Text, Box::new(e))
+            })?;
+            Ok(Some(ScanReport {
+                folder_path: row.get(0)?,
+                started_at: row.get(1)?,
+                duration_ms: row.get::<_, i64>(2)? as u64,
+                status: ScanReportStatus::parse(&row.get::<_, String>(3)?),
+                error: row.get(4)?,
+                total_videos: row.get::<_, i64>(5)? as usize,
+                new_videos: row.get::<_, i64>(6)? as usize,
+                updated_videos: row.get::<_, i64>(7)? as usize,
+                removed_videos: row.get::<_, i64>(8)? as usize,
+                relinked_videos: row.get::<_, i64>(9)? as usize,
+                errors,
+            }))
+        } else {
+            Ok(None)
+        }
+    }
+    
     pub fn remove_mounted_folder(&self, path: &str) -> Result<()> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.write();
         conn.execute("DELETE FROM mounted_folders WHERE path = ?1", params![path])?;
         // Also remove videos from this folder
-        conn.execute("DELETE FROM videos WHERE folder_path LIKE ?1 || '%'", params![path])?;
+        conn.execute(
+            "DELETE FROM videos WHERE folder_path = ?1 OR folder_path LIKE ?2 ESCAPE '\\'",
+            params![path, subfolder_pattern(path)],
+        )?;
         Ok(())
     }
     
-    pub fn clear_folder_videos(&self, folder_path: &str) -> Result<()> {
-        let conn = self.conn.lock().unwrap();
-        // Remove all videos that belong to this folder or its subfolders
-        conn.execute("DELETE FROM videos WHERE folder_path LIKE ?1 || '%'", params![folder_path])?;
-        Ok(())
+    /// Folder tree of `root` built from the stored videos, `depth` levels deep
+    /// (`None` for the whole tree)
+    pub fn get_folder_tree(&self, root: &str, depth: Option<usize>) -> Result<FolderNode> {
+        let conn = self.pool.read();
+        let mut stmt = conn.prepare(
+            r#"SELECT folder_path, COUNT(*), COALESCE(SUM(size), 0), COALESCE(SUM(duration), 0)
+               FROM videos
+               WHERE (folder_path = ?1 OR folder_path LIKE ?2 ESCAPE '\') AND availability != 'excluded'
+               GROUP BY folder_path"#
+        )?;
+        let folders = stmt.query_map(params![root, subfolder_pattern(root)], |row| {
+            Ok((row.get::<_, String>(0)?, FolderTotals {
+                video_count: row.get::<_, i64>(1)? as usize,
+                total_size: row.get::<_, i64>(2)? as u64,
+                total_duration: row.get(3)?,
+            }))
+        })?.collect::<Result<Vec<_>>>()?;
+        
+        Ok(folder_tree::build(root, &folders, depth))
     }
     
     // ========== Videos ==========
     
     pub fn upsert_video(&self, video: &Video) -> Result<()> {
-        let conn = self.conn.lock().unwrap();
-        conn.execute(
-            r#"INSERT INTO videos (id, path, filename, folder_path, size, duration, thumbnail_path, created_at, updated_at)
-               VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+        let mut conn = self.pool.write();
+        let tx = conn.transaction()?;
+        tx.execute(
+            r#"INSERT INTO videos (id, path, filename, folder_path, size, duration, thumbnail_path, mtime, created_at, updated_at)
+               VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
                ON CONFLICT(path) DO UPDATE SET
                    filename = excluded.filename,
                    folder_path = excluded.folder_path,
                    size = excluded.size,
                    duration = excluded.duration,
                    thumbnail_path = excluded.thumbnail_path,
+                   mtime = excluded.mtime,
+                   probed_at = NULL,
+                   thumbnail_checked_at = NULL,
+                   partial_hash = NULL,
+                   content_hash = NULL,
+                   hashed_at = NULL,
+                   inode = NULL,
+                   availability = 'online',
                    updated_at = excluded.updated_at"#,
             params![
                 video.id,
@@ -222,108 +784,180 @@ impl Database {
                 video.size,
                 video.duration,
                 video.thumbnail_path,
+                video.mtime,
                 video.created_at,
                 video.updated_at,
             ],
         )?;
-        Ok(())
-    }
-    
-    pub fn get_video_by_path(&self, path: &str) -> Result<Option<Video>> {
-        let conn = self.conn.lock().unwrap();
-        let mut stmt = conn.prepare(
-            "SELECT id, path, filename, folder_path, size, duration, thumbnail_path, created_at, updated_at FROM videos WHERE path = ?1"
-        )?;
-        
-        let mut rows = stmt.query(params![path])?;
-        if let Some(row) = rows.next()? {
-            Ok(Some(Video {
-                id: row.get(0)?,
-                path: row.get(1)?,
-                filename: row.get(2)?,
-                folder_path: row.get(3)?,
-                size: row.get(4)?,
-                duration: row.get(5)?,
-                thumbnail_path: row.get(6)?,
-                created_at: row.get(7)?,
-                updated_at: row.get(8)?,
-            }))
-        } else {
-            Ok(None)
-        }
+        // A conflicting path keeps the id it had
+        let video_id: String = tx.query_row("SELECT id FROM videos WHERE path = ?1", params![video.path], |row| row.get(0))?;
+        update_episodes(&tx, &[video_id])?;
+        tx.commit()
     }
     
-    pub fn get_videos(&self, filter: &FilterOptions) -> Result<Vec<Video>> {
-        let conn = self.conn.lock().unwrap();
+    /// Reconcile a fresh scan of `folder_path` with the stored rows.
+    ///
+    /// Rows are matched by path so ids (and with them tags, participants,
+    /// languages and playback history) survive a rescan. Scanned videos that
+    /// match an existing row take over its id and `created_at`. New paths are
+    /// checked against rows whose file disappeared, so files moved or renamed
+    /// outside the app keep their id too. Remaining rows whose file was not
+    /// found by the scan are kept with their metadata, except below
+    /// `unreadable` directories the scan couldn't look into: marked missing
+    /// when the file is gone, or excluded when it is still there, i.e. the
+    /// scan rules skip it now. Everything is written in one transaction with cached statements.
+    pub fn apply_folder_scan(&self, folder_path: &str, scanned: &mut [Video], unreadable: &[String]) -> Result<ScanChanges> {
+        let mut conn = self.pool.write();
+        let tx = conn.transaction()?;
+        let mut changes = ScanChanges::default();
+        let mut unmatched: Vec<usize> = Vec::new();
         
-        let mut sql = String::from(
-            "SELECT DISTINCT v.id, v.path, v.filename, v.folder_path, v.size, v.duration, v.thumbnail_path, v.created_at, v.updated_at FROM videos v"
-        );
-        let mut conditions: Vec<String> = Vec::new();
-        let mut params_vec: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
-        
-        // Join tables if filtering by tags, participants, or languages
-        if !filter.tag_ids.is_empty() {
-            sql.push_str(" INNER JOIN video_tags vt ON v.id = vt.video_id");
-        }
-        if !filter.participant_ids.is_empty() {
-            sql.push_str(" INNER JOIN video_participants vp ON v.id = vp.video_id");
-        }
-        if !filter.language_ids.is_empty() {
-            sql.push_str(" INNER JOIN video_languages vl ON v.id = vl.video_id");
-        }
-        
-        // Folder filter
-        if let Some(ref folder) = filter.folder_path {
-            conditions.push(format!("v.folder_path LIKE ?{}", params_vec.len() + 1));
-            params_vec.push(Box::new(format!("{}%", folder)));
-        }
+        let mut existing: HashMap<String, Video> = {
+            let mut stmt = tx.prepare(&format!(
+                "SELECT {} FROM videos v WHERE v.folder_path = ?1 OR v.folder_path LIKE ?2 ESCAPE '\\'",
+                VIDEO_COLUMNS
+            ))?;
+            let rows = stmt.query_map(params![folder_path, subfolder_pattern(folder_path)], video_from_row)?
+                .collect::<Result<Vec<_>>>()?;
+            rows.into_iter().map(|v| (v.path.clone(), v)).collect()
+        };
         
-        // Tag filter
-        if !filter.tag_ids.is_empty() {
-            let placeholders: Vec<String> = filter.tag_ids.iter().enumerate()
-                .map(|(i, _)| format!("?{}", params_vec.len() + i + 1))
-                .collect();
-            conditions.push(format!("vt.tag_id IN ({})", placeholders.join(",")));
-            for tag_id in &filter.tag_ids {
-                params_vec.push(Box::new(tag_id.clone()));
+        for (index, video) in scanned.iter_mut().enumerate() {
+            match existing.remove(&video.path) {
+                Some(old) => {
+                    video.id = old.id;
+                    video.created_at = old.created_at;
+                    video.duration = old.duration;
+                    
+                    if old.size == video.size && old.mtime == video.mtime {
+                        // Same file: keep generated thumbnails unless a sidecar image appeared
+                        if video.thumbnail_path.is_none() {
+                            video.thumbnail_path = old.thumbnail_path.clone();
+                        }
+                        if video.thumbnail_path == old.thumbnail_path && old.availability == Availability::Online {
+                            video.updated_at = old.updated_at;
+                            continue;
+                        }
+                        tx.execute(
+                            "UPDATE videos SET thumbnail_path = ?1, availability = 'online', updated_at = ?2 WHERE id = ?3",
+                            params![video.thumbnail_path, video.updated_at, video.id],
+                        )?;
+                    } else {
+                        // Content changed: probe, thumbnail and hash again
+                        tx.execute(
+                            r#"UPDATE videos SET size = ?1, mtime = ?2, thumbnail_path = ?3,
+                                   probed_at = NULL, thumbnail_checked_at = NULL,
+                                   partial_hash = NULL, content_hash = NULL, hashed_at = NULL,
+                                   inode = ?4, availability = 'online', updated_at = ?5
+                               WHERE id = ?6"#,
+                            params![
+                                video.size,
+                                video.mtime,
+                                video.thumbnail_path,
+                                hashing::inode(Path::new(&video.path)),
+                                video.updated_at,
+                                video.id,
+                            ],
+                        )?;
+                    }
+                    changes.updated_videos += 1;
+                }
+                None => unmatched.push(index),
             }
         }
         
-        // Participant filter
-        if !filter.participant_ids.is_empty() {
-            let placeholders: Vec<String> = filter.participant_ids.iter().enumerate()
-                .map(|(i, _)| format!("?{}", params_vec.len() + i + 1))
-                .collect();
-            conditions.push(format!("vp.participant_id IN ({})", placeholders.join(",")));
-            for p_id in &filter.participant_ids {
-                params_vec.push(Box::new(p_id.clone()));
+        let mut claimed: HashSet<String> = HashSet::new();
+        // New and renamed videos, whose names may make them episodes
+        let mut renamed: Vec<String> = Vec::new();
+        for index in unmatched {
+            let video = &mut scanned[index];
+            let inode = hashing::inode(Path::new(&video.path));
+            
+            if let Some(old) = find_moved_video(&tx, video, &claimed)? {
+                existing.remove(&old.path);
+                claimed.insert(old.id.clone());
+                changes.relinked.push(relink_moved_video(&tx, video, old, inode)?);
+                renamed.push(video.id.clone());
+                continue;
             }
+            
+            insert_video(&tx, video, inode)?;
+            renamed.push(video.id.clone());
+            changes.new_videos += 1;
         }
+        update_episodes(&tx, &renamed)?;
         
-        // Language filter
-        if !filter.language_ids.is_empty() {
-            let placeholders: Vec<String> = filter.language_ids.iter().enumerate()
-                .map(|(i, _)| format!("?{}", params_vec.len() + i + 1))
-                .collect();
-            conditions.push(format!("vl.language_id IN ({})", placeholders.join(",")));
-            for l_id in &filter.language_ids {
-                params_vec.push(Box::new(l_id.clone()));
+        // Anything left was not seen by the scan
+        for vanished in existing.values() {
+            if unreadable.iter().any(|dir| Path::new(&vanished.path).starts_with(dir)) {
+                continue;
+            }
+            let availability = if Path::new(&vanished.path).exists() {
+                Availability::Excluded
+            } else {
+                Availability::Missing
+            };
+            if vanished.availability != availability {
+                tx.prepare_cached("UPDATE videos SET availability = ?1 WHERE id = ?2")?
+                    .execute(params![availability.as_str(), vanished.id])?;
+                changes.removed_videos += 1;
             }
         }
         
-        // Search query
-        if let Some(ref query) = filter.search_query {
-            if !query.is_empty() {
-                conditions.push(format!("v.filename LIKE ?{}", params_vec.len() + 1));
-                params_vec.push(Box::new(format!("%{}%", query)));
+        tx.commit()?;
+        Ok(changes)
+    }
+    
+    /// Store a video found at a path the library doesn't know yet. When it is
+    /// a stored video that was moved or renamed (see `find_moved_video`), that
+    /// row is relinked instead and keeps its id.
+    pub fn add_video(&self, video: &mut Video) -> Result<Option<RelinkedVideo>> {
+        let mut conn = self.pool.write();
+        let tx = conn.transaction()?;
+        let inode = hashing::inode(Path::new(&video.path));
+        
+        let relinked = match find_moved_video(&tx, video, &HashSet::new())? {
+            Some(old) => Some(relink_moved_video(&tx, video, old, inode)?),
+            None => {
+                insert_video(&tx, video, inode)?;
+                None
             }
+        };
+        update_episodes(&tx, std::slice::from_ref(&video.id))?;
+        
+        tx.commit()?;
+        Ok(relinked)
+    }
+    
+    pub fn get_video_by_path(&self, path: &str) -> Result<Option<Video>> {
+        let conn = self.pool.read();
+        let mut stmt = conn.prepare(&format!("SELECT {} FROM videos v WHERE v.path = ?1", VIDEO_COLUMNS))?;
+        
+        let mut rows = stmt.query(params![path])?;
+        if let Some(row) = rows.next()? {
+            Ok(Some(video_from_row(row)?))
+        } else {
+            Ok(None)
         }
+    }
+    
+    pub fn get_video(&self, video_id: &str) -> Result<Option<Video>> {
+        let conn = self.pool.read();
+        let mut stmt = conn.prepare(&format!("SELECT {} FROM videos v WHERE v.id = ?1", VIDEO_COLUMNS))?;
         
-        if !conditions.is_empty() {
-            sql.push_str(" WHERE ");
-            sql.push_str(&conditions.join(" AND "));
+        let mut rows = stmt.query(params![video_id])?;
+        if let Some(row) = rows.next()? {
+            Ok(Some(video_from_row(row)?))
+        } else {
+            Ok(None)
         }
+    }
+    
+    pub fn get_videos(&self, filter: &FilterOptions) -> Result<Vec<Video>> {
+        let conn = self.pool.read();
+        let query = build_filter_query(filter)?;
+        
+        let mut sql = format!("SELECT {} {}", VIDEO_COLUMNS, query.from_where);
         
         // Sorting
         let order = if filter.sort_order == "desc" { "DESC" } else { "ASC" };
@@ -333,126 +967,432 @@ impl Database {
             "updated_at" => "v.updated_at",
             _ => "v.filename",
         };
-        sql.push_str(&format!(" ORDER BY {} {}", sort_column, order));
+        if filter.sort_by == "relevance" && query.searching {
+            // bm25 scores are lower for better matches
+            sql.push_str(" ORDER BY s.search_rank ASC, v.filename ASC");
+        } else {
+            sql.push_str(&format!(" ORDER BY {} {}", sort_column, order));
+        }
         
         // Add LIMIT and OFFSET for pagination
         sql.push_str(&format!(" LIMIT {} OFFSET {}", filter.limit, filter.offset));
         
         let mut stmt = conn.prepare(&sql)?;
-        let params_refs: Vec<&dyn rusqlite::ToSql> = params_vec.iter().map(|p| p.as_ref()).collect();
+        let params_refs: Vec<&dyn rusqlite::ToSql> = query.params.iter().map(|p| p.as_ref()).collect();
         
-        let videos = stmt.query_map(params_refs.as_slice(), |row| {
-            Ok(Video {
-                id: row.get(0)?,
-                path: row.get(1)?,
-                filename: row.get(2)?,
-                folder_path: row.get(3)?,
-                size: row.get(4)?,
-                duration: row.get(5)?,
-                thumbnail_path: row.get(6)?,
-                created_at: row.get(7)?,
-                updated_at: row.get(8)?,
-            })
-        })?.collect::<Result<Vec<_>>>()?;
+        let videos = stmt.query_map(params_refs.as_slice(), video_from_row)?
+            .collect::<Result<Vec<_>>>()?;
         
         Ok(videos)
     }
     
     pub fn get_video_count(&self, filter: &FilterOptions) -> Result<usize> {
-        let conn = self.conn.lock().unwrap();
-        
-        let mut sql = String::from("SELECT COUNT(DISTINCT v.id) FROM videos v");
-        let mut conditions: Vec<String> = Vec::new();
-        let mut params_vec: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
-        
-        // Join tables if filtering
-        if !filter.tag_ids.is_empty() {
-            sql.push_str(" INNER JOIN video_tags vt ON v.id = vt.video_id");
-        }
-        if !filter.participant_ids.is_empty() {
-            sql.push_str(" INNER JOIN video_participants vp ON v.id = vp.video_id");
-        }
-        if !filter.language_ids.is_empty() {
-            sql.push_str(" INNER JOIN video_languages vl ON v.id = vl.video_id");
-        }
-        
-        // Folder filter
-        if let Some(ref folder) = filter.folder_path {
-            conditions.push(format!("v.folder_path LIKE ?{}", params_vec.len() + 1));
-            params_vec.push(Box::new(format!("{}%", folder)));
-        }
-        
-        // Tag filter
-        if !filter.tag_ids.is_empty() {
-            let placeholders: Vec<String> = filter.tag_ids.iter().enumerate()
-                .map(|(i, _)| format!("?{}", params_vec.len() + i + 1))
-                .collect();
-            conditions.push(format!("vt.tag_id IN ({})", placeholders.join(",")));
-            for tag_id in &filter.tag_ids {
-                params_vec.push(Box::new(tag_id.clone()));
-            }
-        }
-        
-        // Participant filter
-        if !filter.participant_ids.is_empty() {
-            let placeholders: Vec<String> = filter.participant_ids.iter().enumerate()
-                .map(|(i, _)| format!("?{}", params_vec.len() + i + 1))
-                .collect();
-            conditions.push(format!("vp.participant_id IN ({})", placeholders.join(",")));
-            for p_id in &filter.participant_ids {
-                params_vec.push(Box::new(p_id.clone()));
-            }
-        }
-        
-        // Language filter
-        if !filter.language_ids.is_empty() {
-            let placeholders: Vec<String> = filter.language_ids.iter().enumerate()
-                .map(|(i, _)| format!("?{}", params_vec.len() + i + 1))
-                .collect();
-            conditions.push(format!("vl.language_id IN ({})", placeholders.join(",")));
-            for l_id in &filter.language_ids {
-                params_vec.push(Box::new(l_id.clone()));
-            }
-        }
-        
-        // Search query
-        if let Some(ref query) = filter.search_query {
-            if !query.is_empty() {
-                conditions.push(format!("v.filename LIKE ?{}", params_vec.len() + 1));
-                params_vec.push(Box::new(format!("%{}%", query)));
-            }
-        }
-        
-        if !conditions.is_empty() {
-            sql.push_str(" WHERE ");
-            sql.push_str(&conditions.join(" AND "));
-        }
+        let conn = self.pool.read();
+        let query = build_filter_query(filter)?;
         
-        let params_refs: Vec<&dyn rusqlite::ToSql> = params_vec.iter().map(|p| p.as_ref()).collect();
+        let sql = format!("SELECT COUNT(*) {}", query.from_where);
+        let params_refs: Vec<&dyn rusqlite::ToSql> = query.params.iter().map(|p| p.as_ref()).collect();
         let count: usize = conn.query_row(&sql, params_refs.as_slice(), |row| row.get(0))?;
         Ok(count)
     }
     
     pub fn delete_video(&self, video_id: &str) -> Result<()> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.write();
         conn.execute("DELETE FROM videos WHERE id = ?1", params![video_id])?;
         Ok(())
     }
     
-    pub fn update_video_path(&self, old_path: &str, new_path: &str, new_folder: &str, new_filename: &str) -> Result<()> {
-        let conn = self.conn.lock().unwrap();
-        let updated_at = chrono::Utc::now().to_rfc3339();
-        conn.execute(
-            "UPDATE videos SET path = ?1, folder_path = ?2, filename = ?3, updated_at = ?4 WHERE path = ?5",
+    /// Mark the video stored at `path`, or every video below it when `path` was
+    /// a directory, with `availability`. Excluded videos only change when they
+    /// come back online. Returns the number of rows that changed.
+    pub fn mark_videos_at(&self, path: &str, availability: Availability) -> Result<usize> {
+        let conn = self.pool.write();
+        let changed = conn.execute(
+            r#"UPDATE videos SET availability = ?1
+               WHERE (path = ?2 OR folder_path = ?2 OR folder_path LIKE ?3 ESCAPE '\') AND availability != ?1
+                   AND (availability != 'excluded' OR ?1 = 'online')"#,
+            params![availability.as_str(), path, subfolder_pattern(path)],
+        )?;
+        Ok(changed)
+    }
+    
+    /// `(id, path, availability)` of every video below `folder_path`
+    pub fn get_folder_availability(&self, folder_path: &str) -> Result<Vec<(String, String, Availability)>> {
+        let conn = self.pool.read();
+        let mut stmt = conn.prepare(
+            "SELECT id, path, availability FROM videos WHERE folder_path = ?1 OR folder_path LIKE ?2 ESCAPE '\\'"
+        )?;
+        
+        let videos = stmt.query_map(params![folder_path, subfolder_pattern(folder_path)], |row| {
+            Ok((row.get(0)?, row.get(1)?, Availability::parse(&row.get::<_, String>(2)?)))
+        })?.collect::<Result<Vec<_>>>()?;
+        
+        Ok(videos)
+    }
+    
+    pub fn set_availability(&self, changes: &[(String, Availability)]) -> Result<()> {
+        let mut conn = self.pool.write();
+        let tx = conn.transaction()?;
+        for (video_id, availability) in changes {
+            tx.execute(
+                "UPDATE videos SET availability = ?1 WHERE id = ?2",
+                params![availability.as_str(), video_id],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+    
+    /// Missing videos and videos on offline volumes
+    pub fn get_unavailable_videos(&self) -> Result<Vec<Video>> {
+        let conn = self.pool.read();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM videos v WHERE v.availability IN ('missing', 'offline_volume') ORDER BY v.availability, v.path",
+            VIDEO_COLUMNS
+        ))?;
+        
+        let videos = stmt.query_map([], video_from_row)?.collect::<Result<Vec<_>>>()?;
+        Ok(videos)
+    }
+    
+    /// Point a video at a file chosen by the user. `file` is the freshly read
+    /// state of the new path; media info, thumbnail and hashes are redone when
+    /// the size differs from the old file.
+    pub fn relink_video(&self, video_id: &str, file: &Video) -> Result<()> {
+        let mut conn = self.pool.write();
+        let tx = conn.transaction()?;
+        tx.execute(
+            r#"UPDATE videos SET
+                   probed_at = CASE WHEN size = ?4 THEN probed_at END,
+                   thumbnail_checked_at = CASE WHEN size = ?4 THEN thumbnail_checked_at END,
+                   thumbnail_path = CASE WHEN size = ?4 THEN COALESCE(?6, thumbnail_path) ELSE ?6 END,
+                   partial_hash = NULL, content_hash = NULL, hashed_at = NULL,
+                   path = ?1, filename = ?2, folder_path = ?3, size = ?4, mtime = ?5,
+                   inode = ?7, availability = 'online', updated_at = ?8
+               WHERE id = ?9"#,
+            params![
+                file.path,
+                file.filename,
+                file.folder_path,
+                file.size,
+                file.mtime,
+                file.thumbnail_path,
+                hashing::inode(Path::new(&file.path)),
+                file.updated_at,
+                video_id,
+            ],
+        )?;
+        update_episodes(&tx, &[video_id.to_string()])?;
+        tx.commit()
+    }
+    
+    pub fn update_video_path(&self, old_path: &str, new_path: &str, new_folder: &str, new_filename: &str) -> Result<()> {
+        let mut conn = self.pool.write();
+        let tx = conn.transaction()?;
+        let updated_at = chrono::Utc::now().to_rfc3339();
+        tx.execute(
+            "UPDATE videos SET path = ?1, folder_path = ?2, filename = ?3, updated_at = ?4 WHERE path = ?5",
             params![new_path, new_folder, new_filename, updated_at, old_path],
         )?;
+        // Episodes named only by their folder may belong to another series now
+        let video_ids: Vec<String> = tx.prepare("SELECT id FROM videos WHERE path = ?1")?
+            .query_map(params![new_path], |row| row.get(0))?
+            .collect::<Result<_>>()?;
+        update_episodes(&tx, &video_ids)?;
+        tx.commit()
+    }
+    
+    // ========== Media Info ==========
+    
+    /// Online videos whose media info has not been probed yet, as `(id, path)` pairs
+    pub fn get_unprobed_videos(&self, limit: usize) -> Result<Vec<(String, String)>> {
+        let conn = self.pool.read();
+        let mut stmt = conn.prepare("SELECT id, path FROM videos WHERE probed_at IS NULL AND availability = 'online' LIMIT ?1")?;
+        
+        let videos = stmt.query_map(params![limit as i64], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<Vec<_>>>()?;
+        
+        Ok(videos)
+    }
+    
+    /// Store probe results. `None` marks the video as probed without usable info
+    /// so it isn't retried on every pass, keeping whatever was stored before.
+    pub fn save_media_info(&self, video_id: &str, info: Option<&MediaInfo>) -> Result<()> {
+        let conn = self.pool.write();
+        let probed_at = chrono::Utc::now().to_rfc3339();
+        
+        let Some(info) = info else {
+            conn.execute(
+                "UPDATE videos SET probed_at = ?1 WHERE id = ?2",
+                params![probed_at, video_id],
+            )?;
+            return Ok(());
+        };
+        conn.execute(
+            r#"UPDATE videos SET
+                   duration = ?1, width = ?2, height = ?3, video_codec = ?4, audio_codec = ?5,
+                   bitrate = ?6, frame_rate = ?7, audio_tracks = ?8, probed_at = ?9
+               WHERE id = ?10"#,
+            params![
+                info.duration,
+                info.width,
+                info.height,
+                info.video_codec,
+                info.audio_codec,
+                info.bitrate,
+                info.frame_rate,
+                info.audio_tracks,
+                probed_at,
+                video_id,
+            ],
+        )?;
+        Ok(())
+    }
+    
+    pub fn get_media_info(&self, video_id: &str) -> Result<Option<MediaInfo>> {
+        let conn = self.pool.read();
+        let mut stmt = conn.prepare(
+            r#"SELECT duration, width, height, video_codec, audio_codec, bitrate, frame_rate, audio_tracks
+               FROM videos WHERE id = ?1 AND probed_at IS NOT NULL"#
+        )?;
+        
+        let mut rows = stmt.query(params![video_id])?;
+        if let Some(row) = rows.next()? {
+            Ok(Some(MediaInfo {
+                duration: row.get(0)?,
+                width: row.get(1)?,
+                height: row.get(2)?,
+                video_codec: row.get(3)?,
+                audio_codec: row.get(4)?,
+                bitrate: row.get(5)?,
+                frame_rate: row.get(6)?,
+                audio_tracks: row.get::<_, Option<u32>>(7)?.unwrap_or(0),
+            }))
+        } else {
+            Ok(None)
+        }
+    }
+    
+    // ========== Thumbnails ==========
+    
+    /// Online videos without any thumbnail that haven't been tried yet, as `(id, path, mtime)`
+    pub fn get_videos_missing_thumbnails(&self, limit: usize) -> Result<Vec<(String, String, Option<i64>)>> {
+        let conn = self.pool.read();
+        let mut stmt = conn.prepare(
+            "SELECT id, path, mtime FROM videos WHERE thumbnail_path IS NULL AND thumbnail_checked_at IS NULL AND availability = 'online' LIMIT ?1"
+        )?;
+        
+        let videos = stmt.query_map(params![limit as i64], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+            .collect::<Result<Vec<_>>>()?;
+        
+        Ok(videos)
+    }
+    
+    pub fn count_videos_missing_thumbnails(&self) -> Result<usize> {
+        let conn = self.pool.read();
+        conn.query_row(
+            "SELECT COUNT(*) FROM videos WHERE thumbnail_path IS NULL AND thumbnail_checked_at IS NULL AND availability = 'online'",
+            [],
+            |row| row.get(0),
+        )
+    }
+    
+    /// Record a generation attempt; `None` means no frame could be extracted
+    pub fn set_generated_thumbnail(&self, video_id: &str, thumbnail_path: Option<&str>) -> Result<()> {
+        let conn = self.pool.write();
+        let checked_at = chrono::Utc::now().to_rfc3339();
+        conn.execute(
+            "UPDATE videos SET thumbnail_path = COALESCE(?1, thumbnail_path), thumbnail_checked_at = ?2 WHERE id = ?3",
+            params![thumbnail_path, checked_at, video_id],
+        )?;
+        Ok(())
+    }
+    
+    // ========== Duplicates ==========
+    
+    /// Online videos without a partial hash that haven't been tried yet, as `(id, path)` pairs
+    pub fn get_unhashed_videos(&self, limit: usize) -> Result<Vec<(String, String)>> {
+        let conn = self.pool.read();
+        let mut stmt = conn.prepare("SELECT id, path FROM videos WHERE hashed_at IS NULL AND availability = 'online' LIMIT ?1")?;
+        
+        let videos = stmt.query_map(params![limit as i64], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<Vec<_>>>()?;
+        
+        Ok(videos)
+    }
+    
+    /// Record a partial hash; `None` means the file could not be read.
+    /// Rows from before inodes were tracked pick up their inode here.
+    pub fn save_partial_hash(&self, video_id: &str, partial_hash: Option<&str>, inode: Option<i64>) -> Result<()> {
+        let conn = self.pool.write();
+        let hashed_at = chrono::Utc::now().to_rfc3339();
+        conn.execute(
+            r#"UPDATE videos SET partial_hash = ?1, content_hash = NULL, hashed_at = ?2, inode = COALESCE(inode, ?3)
+               WHERE id = ?4"#,
+            params![partial_hash, hashed_at, inode, video_id],
+        )?;
+        Ok(())
+    }
+    
+    /// Videos sharing their partial hash with another video but not fully hashed yet
+    pub fn get_unconfirmed_duplicates(&self, limit: usize) -> Result<Vec<(String, String)>> {
+        let conn = self.pool.read();
+        let mut stmt = conn.prepare(
+            r#"SELECT id, path FROM videos
+               WHERE content_hash IS NULL AND availability = 'online' AND partial_hash IN (
+                   SELECT partial_hash FROM videos WHERE partial_hash IS NOT NULL
+                   GROUP BY partial_hash HAVING COUNT(*) > 1
+               )
+               LIMIT ?1"#
+        )?;
+        
+        let videos = stmt.query_map(params![limit as i64], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<Vec<_>>>()?;
+        
+        Ok(videos)
+    }
+    
+    /// Record a full content hash. `None` (unreadable file) also drops the partial
+    /// hash so the video leaves duplicate detection until its file changes.
+    pub fn save_content_hash(&self, video_id: &str, content_hash: Option<&str>) -> Result<()> {
+        let conn = self.pool.write();
+        match content_hash {
+            Some(hash) => conn.execute(
+                "UPDATE videos SET content_hash = ?1 WHERE id = ?2",
+                params![hash, video_id],
+            )?,
+            None => conn.execute(
+                "UPDATE videos SET partial_hash = NULL, content_hash = NULL WHERE id = ?1",
+                params![video_id],
+            )?,
+        };
+        Ok(())
+    }
+    
+    /// Full content hash of a video; `None` until it has been confirmed as a duplicate
+    pub fn get_content_hash(&self, video_id: &str) -> Result<Option<String>> {
+        let conn = self.pool.read();
+        let mut stmt = conn.prepare("SELECT content_hash FROM videos WHERE id = ?1")?;
+        
+        let mut rows = stmt.query(params![video_id])?;
+        match rows.next()? {
+            Some(row) => row.get(0),
+            None => Ok(None),
+        }
+    }
+    
+    /// Videos with identical content, grouped by content hash, largest files first
+    pub fn find_duplicates(&self) -> Result<Vec<(String, Vec<Video>)>> {
+        let conn = self.pool.read();
+        let mut stmt = conn.prepare(&format!(
+            r#"SELECT {}, v.content_hash FROM videos v
+               WHERE v.availability = 'online' AND v.content_hash IN (
+                   SELECT content_hash FROM videos WHERE content_hash IS NOT NULL AND availability = 'online'
+                   GROUP BY content_hash HAVING COUNT(*) > 1
+               )
+               ORDER BY v.size DESC, v.content_hash, v.path"#,
+            VIDEO_COLUMNS
+        ))?;
+        
+        let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(VIDEO_COLUMN_COUNT)?, video_from_row(row)?)))?
+            .collect::<Result<Vec<_>>>()?;
+        
+        let mut groups: Vec<(String, Vec<Video>)> = Vec::new();
+        for (hash, video) in rows {
+            match groups.last_mut() {
+                Some((last_hash, videos)) if *last_hash == hash => videos.push(video),
+                _ => groups.push((hash, vec![video])),
+            }
+        }
+        
+        Ok(groups)
+    }
+    
+    /// Move tags, participants, languages, notes and playback history of
+    /// `other_ids` onto `keep_id`. With `remove_others` the other rows are
+    /// deleted as well and their playlist and queue entries point at `keep_id`.
+    pub fn merge_duplicates(&self, keep_id: &str, other_ids: &[String], remove_others: bool) -> Result<()> {
+        let mut conn = self.pool.write();
+        let tx = conn.transaction()?;
+        
+        for other_id in other_ids.iter().filter(|id| *id != keep_id) {
+            for (table, column) in [
+                ("video_tags", "tag_id"),
+                ("video_participants", "participant_id"),
+                ("video_languages", "language_id"),
+            ] {
+                tx.execute(
+                    &format!(
+                        "INSERT OR IGNORE INTO {table} (video_id, {column}) SELECT ?1, {column} FROM {table} WHERE video_id = ?2"
+                    ),
+                    params![keep_id, other_id],
+                )?;
+            }
+            
+            tx.execute(
+                r#"UPDATE videos SET notes = (SELECT notes FROM videos WHERE id = ?2)
+                   WHERE id = ?1 AND COALESCE(notes, '') = ''"#,
+                params![keep_id, other_id],
+            )?;
+            
+            // Add up the counters; position follows whichever copy was played last
+            tx.execute(
+                r#"INSERT INTO playback_history (video_id, position, last_played, completed, play_count, total_watch_time, first_played)
+                   SELECT ?1, position, last_played, completed, play_count, total_watch_time, first_played
+                   FROM playback_history WHERE video_id = ?2
+                   ON CONFLICT(video_id) DO UPDATE SET
+                       position = CASE WHEN excluded.last_played > last_played THEN excluded.position ELSE position END,
+                       last_played = MAX(last_played, excluded.last_played),
+                       completed = MAX(completed, excluded.completed),
+                       play_count = play_count + excluded.play_count,
+                       total_watch_time = total_watch_time + excluded.total_watch_time,
+                       first_played = COALESCE(MIN(first_played, excluded.first_played), first_played, excluded.first_played)"#,
+                params![keep_id, other_id],
+            )?;
+            tx.execute("DELETE FROM playback_history WHERE video_id = ?1", params![other_id])?;
+            tx.execute(
+                "UPDATE playback_sessions SET video_id = ?1 WHERE video_id = ?2",
+                params![keep_id, other_id],
+            )?;
+            
+            if remove_others {
+                tx.execute("UPDATE playlist_items SET video_id = ?1 WHERE video_id = ?2", params![keep_id, other_id])?;
+                tx.execute("UPDATE play_queue SET video_id = ?1 WHERE video_id = ?2", params![keep_id, other_id])?;
+                tx.execute("DELETE FROM videos WHERE id = ?1", params![other_id])?;
+            }
+        }
+        
+        tx.commit()?;
+        Ok(())
+    }
+    
+    // ========== Settings ==========
+    
+    pub fn get_setting(&self, key: &str) -> Result<Option<String>> {
+        let conn = self.pool.read();
+        let mut stmt = conn.prepare("SELECT value FROM settings WHERE key = ?1")?;
+        
+        let mut rows = stmt.query(params![key])?;
+        if let Some(row) = rows.next()? {
+            Ok(Some(row.get(0)?))
+        } else {
+            Ok(None)
+        }
+    }
+    
+    pub fn set_setting(&self, key: &str, value: &str) -> Result<()> {
+        let conn = self.pool.write();
+        conn.execute(
+            "INSERT OR REPLACE INTO settings (key, value) VALUES (?1, ?2)",
+            params![key, value],
+        )?;
         Ok(())
     }
     
     // ========== Tags ==========
     
     pub fn create_tag(&self, name: &str, color: &str) -> Result<Tag> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.write();
         let id = uuid::Uuid::new_v4().to_string();
         
         conn.execute(
@@ -468,7 +1408,7 @@ impl Database {
     }
     
     pub fn get_tags(&self) -> Result<Vec<Tag>> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.read();
         let mut stmt = conn.prepare("SELECT id, name, color FROM tags ORDER BY name")?;
         
         let tags = stmt.query_map([], |row| {
@@ -483,7 +1423,7 @@ impl Database {
     }
     
     pub fn update_tag(&self, id: &str, name: &str, color: &str) -> Result<()> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.write();
         conn.execute(
             "UPDATE tags SET name = ?1, color = ?2 WHERE id = ?3",
             params![name, color, id],
@@ -492,13 +1432,13 @@ impl Database {
     }
     
     pub fn delete_tag(&self, id: &str) -> Result<()> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.write();
         conn.execute("DELETE FROM tags WHERE id = ?1", params![id])?;
         Ok(())
     }
     
     pub fn get_video_tags(&self, video_id: &str) -> Result<Vec<Tag>> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.read();
         let mut stmt = conn.prepare(
             "SELECT t.id, t.name, t.color FROM tags t 
              INNER JOIN video_tags vt ON t.id = vt.tag_id 
@@ -517,7 +1457,7 @@ impl Database {
     }
     
     pub fn set_video_tags(&self, video_id: &str, tag_ids: &[String]) -> Result<()> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.write();
         conn.execute("DELETE FROM video_tags WHERE video_id = ?1", params![video_id])?;
         
         for tag_id in tag_ids {
@@ -532,7 +1472,7 @@ impl Database {
     // ========== Participants ==========
     
     pub fn create_participant(&self, name: &str) -> Result<Participant> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.write();
         let id = uuid::Uuid::new_v4().to_string();
         
         conn.execute(
@@ -547,7 +1487,7 @@ impl Database {
     }
     
     pub fn get_participants(&self) -> Result<Vec<Participant>> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.read();
         let mut stmt = conn.prepare("SELECT id, name FROM participants ORDER BY name")?;
         
         let participants = stmt.query_map([], |row| {
@@ -561,19 +1501,19 @@ impl Database {
     }
     
     pub fn update_participant(&self, id: &str, name: &str) -> Result<()> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.write();
         conn.execute("UPDATE participants SET name = ?1 WHERE id = ?2", params![name, id])?;
         Ok(())
     }
     
     pub fn delete_participant(&self, id: &str) -> Result<()> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.write();
         conn.execute("DELETE FROM participants WHERE id = ?1", params![id])?;
         Ok(())
     }
     
     pub fn get_video_participants(&self, video_id: &str) -> Result<Vec<Participant>> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.read();
         let mut stmt = conn.prepare(
             "SELECT p.id, p.name FROM participants p 
              INNER JOIN video_participants vp ON p.id = vp.participant_id 
@@ -591,7 +1531,7 @@ impl Database {
     }
     
     pub fn set_video_participants(&self, video_id: &str, participant_ids: &[String]) -> Result<()> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.write();
         conn.execute("DELETE FROM video_participants WHERE video_id = ?1", params![video_id])?;
         
         for p_id in participant_ids {
@@ -606,7 +1546,7 @@ impl Database {
     // ========== Languages ==========
     
     pub fn create_language(&self, code: &str, name: &str) -> Result<Language> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.write();
         let id = uuid::Uuid::new_v4().to_string();
         
         conn.execute(
@@ -622,7 +1562,7 @@ impl Database {
     }
     
     pub fn get_languages(&self) -> Result<Vec<Language>> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.read();
         let mut stmt = conn.prepare("SELECT id, code, name FROM languages ORDER BY name")?;
         
         let languages = stmt.query_map([], |row| {
@@ -637,7 +1577,7 @@ impl Database {
     }
     
     pub fn update_language(&self, id: &str, code: &str, name: &str) -> Result<()> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.write();
         conn.execute(
             "UPDATE languages SET code = ?1, name = ?2 WHERE id = ?3",
             params![code, name, id],
@@ -646,13 +1586,13 @@ impl Database {
     }
     
     pub fn delete_language(&self, id: &str) -> Result<()> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.write();
         conn.execute("DELETE FROM languages WHERE id = ?1", params![id])?;
         Ok(())
     }
     
     pub fn get_video_languages(&self, video_id: &str) -> Result<Vec<Language>> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.read();
         let mut stmt = conn.prepare(
             "SELECT l.id, l.code, l.name FROM languages l 
              INNER JOIN video_languages vl ON l.id = vl.language_id 
@@ -671,7 +1611,7 @@ impl Database {
     }
     
     pub fn set_video_languages(&self, video_id: &str, language_ids: &[String]) -> Result<()> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.write();
         conn.execute("DELETE FROM video_languages WHERE video_id = ?1", params![video_id])?;
         
         for l_id in language_ids {
@@ -686,18 +1626,37 @@ impl Database {
     // ========== Playback History ==========
     
     pub fn save_playback_position(&self, video_id: &str, position: f64) -> Result<()> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.write();
         let last_played = chrono::Utc::now().to_rfc3339();
         
         conn.execute(
-            "INSERT OR REPLACE INTO playback_history (video_id, position, last_played) VALUES (?1, ?2, ?3)",
+            r#"INSERT INTO playback_history (video_id, position, last_played, first_played) VALUES (?1, ?2, ?3, ?3)
+               ON CONFLICT(video_id) DO UPDATE SET
+                   position = excluded.position,
+                   last_played = excluded.last_played"#,
             params![video_id, position, last_played],
         )?;
         Ok(())
     }
     
+    /// Mark a video as watched to the end; the next playback starts from the beginning
+    pub fn mark_playback_completed(&self, video_id: &str) -> Result<()> {
+        let conn = self.pool.write();
+        let last_played = chrono::Utc::now().to_rfc3339();
+        
+        conn.execute(
+            r#"INSERT INTO playback_history (video_id, position, last_played, first_played, completed) VALUES (?1, 0, ?2, ?2, 1)
+               ON CONFLICT(video_id) DO UPDATE SET
+                   position = 0,
+                   last_played = excluded.last_played,
+                   completed = 1"#,
+            params![video_id, last_played],
+        )?;
+        Ok(())
+    }
+    
     pub fn get_playback_position(&self, video_id: &str) -> Result<Option<f64>> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.pool.read();
         let mut stmt = conn.prepare("SELECT position FROM playback_history WHERE video_id = ?1")?;
         
         let mut rows = stmt.query(params![video_id])?;
@@ -707,5 +1666,1448 @@ impl Database {
             Ok(None)
         }
     }
-}
-
+    
+    pub fn mark_unwatched(&self, video_id: &str) -> Result<()> {
+        let conn = self.pool.write();
+        conn.execute(
+            "UPDATE playback_history SET completed = 0, position = 0 WHERE video_id = ?1",
+            params![video_id],
+        )?;
+        Ok(())
+    }
+    
+    pub fn get_playback_history(&self, video_id: &str) -> Result<Option<PlaybackHistory>> {
+        let conn = self.pool.read();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM playback_history ph WHERE ph.video_id = ?1",
+            HISTORY_COLUMNS
+        ))?;
+        
+        let mut rows = stmt.query(params![video_id])?;
+        if let Some(row) = rows.next()? {
+            Ok(Some(history_from_row(row, 0)?))
+        } else {
+            Ok(None)
+        }
+    }
+    
+    /// Open a viewing session and count it as a play. Returns the session id.
+    pub fn begin_playback_session(&self, video_id: &str, start_position: f64) -> Result<String> {
+        let mut conn = self.pool.write();
+        let tx = conn.transaction()?;
+        let id = uuid::Uuid::new_v4().to_string();
+        let now = chrono::Utc::now().to_rfc3339();
+        
+        tx.execute(
+            "INSERT INTO playback_sessions (id, video_id, started_at, start_position) VALUES (?1, ?2, ?3, ?4)",
+            params![id, video_id, now, start_position],
+        )?;
+        tx.execute(
+            r#"INSERT INTO playback_history (video_id, position, last_played, first_played, play_count)
+               VALUES (?1, ?2, ?3, ?3, 1)
+               ON CONFLICT(video_id) DO UPDATE SET
+                   play_count = play_count + 1,
+                   last_played = excluded.last_played,
+                   first_played = COALESCE(first_played, excluded.first_played)"#,
+            params![video_id, start_position, now],
+        )?;
+        
+        tx.commit()?;
+        Ok(id)
+    }
+    
+    /// Record how far a session got. `watched_seconds` is the playing time since
+    /// the previous update and is added to the video's total watch time.
+    pub fn update_playback_session(&self, session_id: &str, end_position: f64, watched_seconds: f64) -> Result<()> {
+        let mut conn = self.pool.write();
+        let tx = conn.transaction()?;
+        let now = chrono::Utc::now().to_rfc3339();
+        
+        tx.execute(
+            "UPDATE playback_sessions SET ended_at = ?1, end_position = ?2 WHERE id = ?3",
+            params![now, end_position, session_id],
+        )?;
+        tx.execute(
+            r#"UPDATE playback_history SET total_watch_time = total_watch_time + ?1
+               WHERE video_id = (SELECT video_id FROM playback_sessions WHERE id = ?2)"#,
+            params![watched_seconds.max(0.0), session_id],
+        )?;
+        
+        tx.commit()?;
+        Ok(())
+    }
+    
+    /// Sessions started within `range`, newest first
+    pub fn get_watch_history(&self, range: &DateRange, limit: usize) -> Result<Vec<WatchHistoryEntry>> {
+        let conn = self.pool.read();
+        let mut stmt = conn.prepare(&format!(
+            r#"SELECT {}, s.id, s.video_id, s.started_at, s.ended_at, s.start_position, s.end_position
+               FROM playback_sessions s
+               INNER JOIN videos v ON v.id = s.video_id
+               WHERE (?1 IS NULL OR s.started_at >= ?1) AND (?2 IS NULL OR s.started_at <= ?2)
+               ORDER BY s.started_at DESC
+               LIMIT ?3"#,
+            VIDEO_COLUMNS
+        ))?;
+        
+        let entries = stmt.query_map(params![range.from, range.to, limit as i64], |row| {
+            let session = PlaybackSession {
+                id: row.get(VIDEO_COLUMN_COUNT)?,
+                video_id: row.get(VIDEO_COLUMN_COUNT + 1)?,
+                started_at: row.get(VIDEO_COLUMN_COUNT + 2)?,
+                ended_at: row.get(VIDEO_COLUMN_COUNT + 3)?,
+                start_position: row.get(VIDEO_COLUMN_COUNT + 4)?,
+                end_position: row.get(VIDEO_COLUMN_COUNT + 5)?,
+            };
+            Ok(WatchHistoryEntry { session, video: video_from_row(row)? })
+        })?.collect::<Result<Vec<_>>>()?;
+        
+        Ok(entries)
+    }
+    
+    /// Partially watched videos, most recently played first
+    pub fn get_continue_watching(&self, limit: usize) -> Result<Vec<VideoWithHistory>> {
+        let conn = self.pool.read();
+        let mut stmt = conn.prepare(&format!(
+            r#"SELECT {}, {} FROM playback_history ph
+               INNER JOIN videos v ON v.id = ph.video_id
+               WHERE ph.completed = 0 AND ph.position > 0
+               ORDER BY ph.last_played DESC
+               LIMIT ?1"#,
+            VIDEO_COLUMNS, HISTORY_COLUMNS
+        ))?;
+        
+        let items = stmt.query_map(params![limit as i64], |row| {
+            Ok(VideoWithHistory {
+                video: video_from_row(row)?,
+                history: history_from_row(row, VIDEO_COLUMN_COUNT)?,
+            })
+        })?.collect::<Result<Vec<_>>>()?;
+        
+        Ok(items)
+    }
+    
+    // ========== Series ==========
+    
+    /// Parse every video's file name and rebuild the episode list. Series keep
+    /// their ids from run to run; series left without episodes are dropped.
+    /// Scans and moves update the episodes of the videos they touch, so this
+    /// is only needed on startup. Returns the number of episode files found.
+    pub fn sync_episodes(&self) -> Result<usize> {
+        let mut conn = self.pool.write();
+        let tx = conn.transaction()?;
+        
+        let video_ids: Vec<String> = tx.prepare("SELECT id FROM videos")?
+            .query_map([], |row| row.get(0))?
+            .collect::<Result<_>>()?;
+        tx.execute("DELETE FROM episodes", [])?;
+        let found = update_episodes(&tx, &video_ids)?;
+        
+        tx.commit()?;
+        Ok(found)
+    }
+    
+    /// Every series with its watched progress, by title. Like the library,
+    /// these leave out videos the scan rules exclude.
+    pub fn get_series(&self) -> Result<Vec<Series>> {
+        let conn = self.pool.read();
+        let mut stmt = conn.prepare(
+            r#"SELECT s.id, s.title, s.year, COUNT(DISTINCT e.season), COUNT(*),
+                   COALESCE(SUM(ph.completed), 0), MAX(ph.last_played)
+               FROM series s
+               INNER JOIN episodes e ON e.series_id = s.id
+               INNER JOIN videos v ON v.id = e.video_id
+               LEFT JOIN playback_history ph ON ph.video_id = e.video_id
+               WHERE v.availability != 'excluded'
+               GROUP BY s.id
+               ORDER BY s.title COLLATE NOCASE"#
+        )?;
+    
+        let series = stmt.query_map([], |row| {
+            Ok(Series {
+                id: row.get(0)?,
+                title: row.get(1)?,
+                year: row.get(2)?,
+                season_count: row.get::<_, i64>(3)? as usize,
+                episode_count: row.get::<_, i64>(4)? as usize,
+                watched_count: row.get::<_, i64>(5)? as usize,
+                last_played: row.get(6)?,
+            })
+        })?.collect::<Result<Vec<_>>>()?;
+    
+        Ok(series)
+    }
+    
+    pub fn get_seasons(&self, series_id: &str) -> Result<Vec<Season>> {
+        let conn = self.pool.read();
+        let mut stmt = conn.prepare(
+            r#"SELECT e.season, COUNT(*), COALESCE(SUM(ph.completed), 0)
+               FROM episodes e
+               INNER JOIN videos v ON v.id = e.video_id
+               LEFT JOIN playback_history ph ON ph.video_id = e.video_id
+               WHERE e.series_id = ?1 AND v.availability != 'excluded'
+               GROUP BY e.season
+               ORDER BY e.season"#
+        )?;
+    
+        let seasons = stmt.query_map(params![series_id], |row| {
+            Ok(Season {
+                season: row.get(0)?,
+                episode_count: row.get::<_, i64>(1)? as usize,
+                watched_count: row.get::<_, i64>(2)? as usize,
+            })
+        })?.collect::<Result<Vec<_>>>()?;
+    
+        Ok(seasons)
+    }
+    
+    /// Episodes of a series in viewing order, optionally of one season only
+    pub fn get_episodes(&self, series_id: &str, season: Option<u32>) -> Result<Vec<Episode>> {
+        let conn = self.pool.read();
+        let mut stmt = conn.prepare(&format!(
+            r#"SELECT {}, e.series_id, e.season, e.episode, e.episode_end, e.resolution, e.release_group, {}
+               FROM episodes e
+               INNER JOIN videos v ON v.id = e.video_id
+               LEFT JOIN playback_history ph ON ph.video_id = e.video_id
+               WHERE e.series_id = ?1 AND (?2 IS NULL OR e.season = ?2) AND v.availability != 'excluded'
+               ORDER BY e.season, e.episode, COALESCE(e.episode_end, e.episode), v.filename"#,
+            VIDEO_COLUMNS, HISTORY_COLUMNS
+        ))?;
+    
+        let history_offset = VIDEO_COLUMN_COUNT + 6;
+        let episodes = stmt.query_map(params![series_id, season], |row| {
+            let history = match row.get::<_, Option<String>>(history_offset)? {
+                Some(_) => Some(history_from_row(row, history_offset)?),
+                None => None,
+            };
+            let video = video_from_row(row)?;
+            Ok(Episode {
+                availability: video.availability,
+                video,
+                series_id: row.get(VIDEO_COLUMN_COUNT)?,
+                season: row.get(VIDEO_COLUMN_COUNT + 1)?,
+                episode: row.get(VIDEO_COLUMN_COUNT + 2)?,
+                episode_end: row.get(VIDEO_COLUMN_COUNT + 3)?,
+                resolution: row.get(VIDEO_COLUMN_COUNT + 4)?,
+                release_group: row.get(VIDEO_COLUMN_COUNT + 5)?,
+                history,
+            })
+        })?.collect::<Result<Vec<_>>>()?;
+    
+        Ok(episodes)
+    }
+    
+    /// The episode to play next: the last one played if it wasn't finished,
+    /// otherwise the first unwatched one after it, wrapping around to earlier
+    /// ones left unwatched. Only episodes whose files are online count.
+    pub fn get_next_episode(&self, series_id: &str) -> Result<Option<Episode>> {
+        let episodes = self.get_episodes(series_id, None)?;
+        let playable = |episode: &Episode| {
+            episode.availability == Availability::Online
+                && !episode.history.as_ref().is_some_and(|h| h.completed)
+        };
+    
+        let last_played = episodes.iter()
+            .enumerate()
+            .filter_map(|(index, episode)| Some((index, episode.history.as_ref()?.last_played.as_str())))
+            .max_by_key(|(_, last_played)| *last_played)
+            .map(|(index, _)| index);
+        let start = match last_played {
+            Some(index) if playable(&episodes[index]) => index,
+            Some(index) => index + 1,
+            None => 0,
+        };
+    
+        let next = episodes.iter().skip(start)
+            .chain(episodes.iter().take(start))
+            .position(playable)
+            .map(|offset| (start + offset) % episodes.len());
+        Ok(next.map(|index| episodes[index].clone()))
+    }
+    
+    // ========== Smart Collections ==========
+    
+    pub fn create_smart_collection(&self, name: &str, filter: &FilterOptions) -> Result<SmartCollection> {
+        let conn = self.pool.write();
+        let id = uuid::Uuid::new_v4().to_string();
+        let now = chrono::Utc::now().to_rfc3339();
+        let filter_json = serde_json::to_string(filter)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+        
+        conn.execute(
+            "INSERT INTO smart_collections (id, name, filter, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?4)",
+            params![id, name, filter_json, now],
+        )?;
+        
+        Ok(SmartCollection {
+            id,
+            name: name.to_string(),
+            filter: filter.clone(),
+            created_at: now.clone(),
+            updated_at: now,
+        })
+    }
+    
+    pub fn get_smart_collections(&self) -> Result<Vec<SmartCollection>> {
+        let conn = self.pool.read();
+        let mut stmt = conn.prepare(
+            "SELECT id, name, filter, created_at, updated_at FROM smart_collections ORDER BY name COLLATE NOCASE"
+        )?;
+        
+        let collections = stmt.query_map([], collection_from_row)?
+            .collect::<Result<Vec<_>>>()?;
+        
+        Ok(collections)
+    }
+    
+    pub fn get_smart_collection(&self, id: &str) -> Result<Option<SmartCollection>> {
+        let conn = self.pool.read();
+        let mut stmt = conn.prepare(
+            "SELECT id, name, filter, created_at, updated_at FROM smart_collections WHERE id = ?1"
+        )?;
+        
+        let mut rows = stmt.query(params![id])?;
+        if let Some(row) = rows.next()? {
+            Ok(Some(collection_from_row(row)?))
+        } else {
+            Ok(None)
+        }
+    }
+    
+    pub fn update_smart_collection(&self, id: &str, name: &str, filter: &FilterOptions) -> Result<()> {
+        let conn = self.pool.write();
+        let filter_json = serde_json::to_string(filter)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+        
+        conn.execute(
+            "UPDATE smart_collections SET name = ?1, filter = ?2, updated_at = ?3 WHERE id = ?4",
+            params![name, filter_json, chrono::Utc::now().to_rfc3339(), id],
+        )?;
+        Ok(())
+    }
+    
+    pub fn delete_smart_collection(&self, id: &str) -> Result<()> {
+        let conn = self.pool.write();
+        conn.execute("DELETE FROM smart_collections WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+    
+    /// A page of the videos a collection's filter matches right now; `None`
+    /// if there is no such collection
+    pub fn get_collection_videos(&self, id: &str, limit: usize, offset: usize) -> Result<Option<PaginatedVideos>> {
+        let Some(collection) = self.get_smart_collection(id)? else {
+            return Ok(None);
+        };
+        
+        let filter = FilterOptions {
+            limit,
+            offset,
+            ..collection.filter
+        };
+        let videos = self.get_videos(&filter)?;
+        let total = self.get_video_count(&filter)?;
+        let has_more = filter.offset + videos.len() < total;
+        
+        Ok(Some(PaginatedVideos {
+            videos,
+            total,
+            has_more,
+        }))
+    }
+    
+    /// Mounted folders and smart collections with their current video counts
+    pub fn get_sidebar(&self) -> Result<SidebarData> {
+        let folders = self.get_mounted_folders()?;
+        
+        let collections = self.get_smart_collections()?
+            .into_iter()
+            .map(|c| SidebarCollection {
+                // Show a collection that can no longer be evaluated as empty instead of failing the sidebar
+                video_count: self.get_video_count(&c.filter).unwrap_or(0),
+                id: c.id,
+                name: c.name,
+            })
+            .collect();
+        
+        Ok(SidebarData { folders, collections })
+    }
+    
+    // ========== Playlists ==========
+    
+    pub fn create_playlist(&self, name: &str) -> Result<Playlist> {
+        let conn = self.pool.write();
+        let id = uuid::Uuid::new_v4().to_string();
+        let now = chrono::Utc::now().to_rfc3339();
+        
+        conn.execute(
+            "INSERT INTO playlists (id, name, created_at, updated_at) VALUES (?1, ?2, ?3, ?3)",
+            params![id, name, now],
+        )?;
+        
+        Ok(Playlist {
+            id,
+            name: name.to_string(),
+            item_count: 0,
+            created_at: now.clone(),
+            updated_at: now,
+        })
+    }
+    
+    pub fn get_playlists(&self) -> Result<Vec<Playlist>> {
+        let conn = self.pool.read();
+        let mut stmt = conn.prepare(
+            r#"SELECT p.id, p.name, p.created_at, p.updated_at,
+                   (SELECT COUNT(*) FROM playlist_items pi
+                    INNER JOIN videos v ON v.id = pi.video_id WHERE pi.playlist_id = p.id)
+               FROM playlists p ORDER BY p.name COLLATE NOCASE"#
+        )?;
+        
+        let playlists = stmt.query_map([], |row| {
+            Ok(Playlist {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                created_at: row.get(2)?,
+                updated_at: row.get(3)?,
+                item_count: row.get::<_, i64>(4)? as usize,
+            })
+        })?.collect::<Result<Vec<_>>>()?;
+        
+        Ok(playlists)
+    }
+    
+    pub fn get_playlist_name(&self, id: &str) -> Result<Option<String>> {
+        let conn = self.pool.read();
+        let mut stmt = conn.prepare("SELECT name FROM playlists WHERE id = ?1")?;
+        
+        let mut rows = stmt.query(params![id])?;
+        if let Some(row) = rows.next()? {
+            Ok(Some(row.get(0)?))
+        } else {
+            Ok(None)
+        }
+    }
+    
+    pub fn rename_playlist(&self, id: &str, name: &str) -> Result<()> {
+        let conn = self.pool.write();
+        conn.execute(
+            "UPDATE playlists SET name = ?1, updated_at = ?2 WHERE id = ?3",
+            params![name, chrono::Utc::now().to_rfc3339(), id],
+        )?;
+        Ok(())
+    }
+    
+    pub fn delete_playlist(&self, id: &str) -> Result<()> {
+        let mut conn = self.pool.write();
+        let tx = conn.transaction()?;
+        tx.execute("DELETE FROM playlist_items WHERE playlist_id = ?1", params![id])?;
+        tx.execute("DELETE FROM playlists WHERE id = ?1", params![id])?;
+        tx.commit()
+    }
+    
+    /// Items of a playlist in play order; entries whose video was removed are skipped
+    pub fn get_playlist_items(&self, playlist_id: &str) -> Result<Vec<PlaylistItem>> {
+        let conn = self.pool.read();
+        let mut stmt = conn.prepare(&format!(
+            r#"SELECT {}, pi.id, pi.playlist_id FROM playlist_items pi
+               INNER JOIN videos v ON v.id = pi.video_id
+               WHERE pi.playlist_id = ?1
+               ORDER BY pi.position, pi.rowid"#,
+            VIDEO_COLUMNS
+        ))?;
+        
+        let rows = stmt.query_map(params![playlist_id], |row| {
+            Ok((video_from_row(row)?, row.get::<_, String>(VIDEO_COLUMN_COUNT)?, row.get::<_, String>(VIDEO_COLUMN_COUNT + 1)?))
+        })?.collect::<Result<Vec<_>>>()?;
+        
+        let items = rows.into_iter().enumerate()
+            .map(|(position, (video, id, playlist_id))| PlaylistItem {
+                id,
+                playlist_id,
+                position,
+                video,
+            })
+            .collect();
+        
+        Ok(items)
+    }
+    
+    /// Insert videos before `position`, or append them when it is `None`
+    pub fn insert_playlist_items(&self, playlist_id: &str, video_ids: &[String], position: Option<usize>) -> Result<()> {
+        let mut conn = self.pool.write();
+        let tx = conn.transaction()?;
+        renumber_playlist(&tx, playlist_id)?;
+        
+        let count: i64 = tx.query_row(
+            "SELECT COUNT(*) FROM playlist_items WHERE playlist_id = ?1",
+            params![playlist_id],
+            |row| row.get(0),
+        )?;
+        let start = position.map(|p| (p as i64).min(count)).unwrap_or(count);
+        
+        tx.execute(
+            "UPDATE playlist_items SET position = position + ?1 WHERE playlist_id = ?2 AND position >= ?3",
+            params![video_ids.len() as i64, playlist_id, start],
+        )?;
+        for (offset, video_id) in video_ids.iter().enumerate() {
+            tx.execute(
+                "INSERT INTO playlist_items (id, playlist_id, video_id, position) VALUES (?1, ?2, ?3, ?4)",
+                params![uuid::Uuid::new_v4().to_string(), playlist_id, video_id, start + offset as i64],
+            )?;
+        }
+        
+        renumber_playlist(&tx, playlist_id)?;
+        tx.commit()
+    }
+    
+    pub fn remove_playlist_items(&self, playlist_id: &str, item_ids: &[String]) -> Result<()> {
+        let mut conn = self.pool.write();
+        let tx = conn.transaction()?;
+        for item_id in item_ids {
+            tx.execute(
+                "DELETE FROM playlist_items WHERE id = ?1 AND playlist_id = ?2",
+                params![item_id, playlist_id],
+            )?;
+        }
+        renumber_playlist(&tx, playlist_id)?;
+        tx.commit()
+    }
+    
+    /// Put the listed items first, in the given order; items not listed keep
+    /// their relative order after them
+    pub fn reorder_playlist(&self, playlist_id: &str, item_ids: &[String]) -> Result<()> {
+        let mut conn = self.pool.write();
+        let tx = conn.transaction()?;
+        
+        tx.execute(
+            "UPDATE playlist_items SET position = position + ?1 WHERE playlist_id = ?2",
+            params![item_ids.len() as i64, playlist_id],
+        )?;
+        for (position, item_id) in item_ids.iter().enumerate() {
+            tx.execute(
+                "UPDATE playlist_items SET position = ?1 WHERE id = ?2 AND playlist_id = ?3",
+                params![position as i64, item_id, playlist_id],
+            )?;
+        }
+        
+        renumber_playlist(&tx, playlist_id)?;
+        tx.commit()
+    }
+    
+    // ========== Play Queue ==========
+    
+    pub fn save_play_queue(&self, video_ids: &[String], current: Option<usize>, repeat: RepeatMode) -> Result<()> {
+        let mut conn = self.pool.write();
+        let tx = conn.transaction()?;
+        
+        tx.execute("DELETE FROM play_queue", [])?;
+        for (position, video_id) in video_ids.iter().enumerate() {
+            // Skip videos deleted while they were queued
+            tx.execute(
+                "INSERT INTO play_queue (position, video_id) SELECT ?1, id FROM videos WHERE id = ?2",
+                params![position as i64, video_id],
+            )?;
+        }
+        
+        let repeat = serde_json::to_string(&repeat)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+        tx.execute(
+            "INSERT OR REPLACE INTO settings (key, value) VALUES ('queue_current', ?1)",
+            params![current.map(|c| c.to_string()).unwrap_or_default()],
+        )?;
+        tx.execute(
+            "INSERT OR REPLACE INTO settings (key, value) VALUES ('queue_repeat', ?1)",
+            params![repeat],
+        )?;
+        
+        tx.commit()
+    }
+    
+    /// Queue saved by `save_play_queue`, minus videos removed from the library since
+    pub fn load_play_queue(&self) -> Result<(Vec<Video>, Option<usize>, RepeatMode)> {
+        let saved_current: Option<i64> = self.get_setting("queue_current")?
+            .and_then(|c| c.parse().ok());
+        let repeat = self.get_setting("queue_repeat")?
+            .and_then(|r| serde_json::from_str(&r).ok())
+            .unwrap_or_default();
+        
+        let conn = self.pool.read();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {}, q.position FROM play_queue q INNER JOIN videos v ON v.id = q.video_id ORDER BY q.position",
+            VIDEO_COLUMNS
+        ))?;
+        let rows = stmt.query_map([], |row| Ok((video_from_row(row)?, row.get::<_, i64>(VIDEO_COLUMN_COUNT)?)))?
+            .collect::<Result<Vec<_>>>()?;
+        
+        let current = rows.iter().position(|(_, position)| Some(*position) == saved_current);
+        let items = rows.into_iter().map(|(video, _)| video).collect();
+        
+        Ok((items, current, repeat))
+    }
+    
+    // ========== Search ==========
+    
+    /// Autocomplete candidates for `prefix`: matching tag, participant and
+    /// language names first, then words from the full-text index
+    pub fn search_suggestions(&self, prefix: &str, limit: usize) -> Result<Vec<SearchSuggestion>> {
+        let conn = self.pool.read();
+        let prefix = prefix.trim();
+        if prefix.is_empty() {
+            return Ok(Vec::new());
+        }
+        
+        let pattern = format!(
+            "{}%",
+            prefix.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+        );
+        let mut stmt = conn.prepare(
+            r#"SELECT name, kind FROM (
+                   SELECT name, 'tag' AS kind FROM tags WHERE name LIKE ?1 ESCAPE '\'
+                   UNION ALL
+                   SELECT name, 'participant' FROM participants WHERE name LIKE ?1 ESCAPE '\'
+                   UNION ALL
+                   SELECT name, 'language' FROM languages WHERE name LIKE ?1 ESCAPE '\' OR code LIKE ?1 ESCAPE '\'
+               )
+               ORDER BY name COLLATE NOCASE
+               LIMIT ?2"#
+        )?;
+        let mut suggestions = stmt.query_map(params![pattern, limit as i64], |row| {
+            Ok(SearchSuggestion {
+                text: row.get(0)?,
+                kind: row.get(1)?,
+            })
+        })?.collect::<Result<Vec<_>>>()?;
+        
+        // Indexed terms are lowercase; take the most widespread ones first
+        let term_prefix = prefix.to_lowercase();
+        let mut stmt = conn.prepare(
+            "SELECT term FROM video_search_vocab WHERE term >= ?1 AND term < ?2 ORDER BY doc DESC, term LIMIT ?3"
+        )?;
+        let terms = stmt.query_map(
+            params![term_prefix, format!("{}\u{10FFFF}", term_prefix), limit as i64],
+            |row| row.get::<_, String>(0),
+        )?.collect::<Result<Vec<_>>>()?;
+        
+        for term in terms {
+            if suggestions.len() >= limit {
+                break;
+            }
+            if !suggestions.iter().any(|s| s.text.to_lowercase() == term) {
+                suggestions.push(SearchSuggestion {
+                    text: term,
+                    kind: "term".to_string(),
+                });
+            }
+        }
+        
+        Ok(suggestions)
+    }
+    
+    /// Recreate every row of the full-text index from the library tables
+    pub fn rebuild_search_index(&self) -> Result<()> {
+        let conn = self.pool.write();
+        conn.execute_batch(&rebuild_search_sql())
+    }
+    
+    // ========== Maintenance ==========
+    
+    /// Run SQLite's integrity and foreign key checks, delete orphaned rows
+    /// and rebuild the search index
+    pub fn check_database(&self) -> Result<DatabaseCheck> {
+        let mut conn = self.pool.write();
+        
+        let mut problems: Vec<String> = {
+            let mut stmt = conn.prepare("PRAGMA integrity_check")?;
+            let rows = stmt.query_map([], |row| row.get::<_, String>(0))?
+                .collect::<Result<Vec<_>>>()?;
+            rows.into_iter().filter(|message| message != "ok").collect()
+        };
+        
+        let tx = conn.transaction()?;
+        let orphans_removed = delete_orphans(&tx)?;
+        tx.execute_batch(&rebuild_search_sql())?;
+        
+        // Anything left here points at a table the cleanup doesn't know about
+        {
+            let mut stmt = tx.prepare("PRAGMA foreign_key_check")?;
+            let violations = stmt.query_map([], |row| {
+                Ok(format!("{} row {} references missing {}", row.get::<_, String>(0)?, row.get::<_, i64>(1)?, row.get::<_, String>(2)?))
+            })?.collect::<Result<Vec<_>>>()?;
+            problems.extend(violations);
+        }
+        tx.commit()?;
+        
+        Ok(DatabaseCheck {
+            problems,
+            orphans_removed,
+        })
+    }
+    
+    pub fn get_video_notes(&self, video_id: &str) -> Result<Option<String>> {
+        let conn = self.pool.read();
+        let mut stmt = conn.prepare("SELECT notes FROM videos WHERE id = ?1")?;
+        
+        let mut rows = stmt.query(params![video_id])?;
+        if let Some(row) = rows.next()? {
+            Ok(row.get(0)?)
+        } else {
+            Ok(None)
+        }
+    }
+    
+    pub fn set_video_notes(&self, video_id: &str, notes: Option<&str>) -> Result<()> {
+        let conn = self.pool.write();
+        let notes = notes.map(str::trim).filter(|n| !n.is_empty());
+        conn.execute(
+            "UPDATE videos SET notes = ?1 WHERE id = ?2",
+            params![notes, video_id],
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use crate::scan_rules::RuleSet;
+
+    /// A database in a directory of its own. Fields drop in order, so the
+    /// database is closed before its directory is removed.
+    struct TempDb {
+        db: Database,
+        _dir: TempDir,
+    }
+
+    struct TempDir(PathBuf);
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    impl std::ops::Deref for TempDb {
+        type Target = Database;
+
+        fn deref(&self) -> &Database {
+            &self.db
+        }
+    }
+
+    fn temp_db(name: &str) -> TempDb {
+        let dir = std::env::temp_dir().join(format!("videoplayer-database-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        TempDb {
+            db: Database::open(&dir.join("database.sqlite")).unwrap(),
+            _dir: TempDir(dir),
+        }
+    }
+
+    fn video(id: &str, path: &str) -> Video {
+        let path = Path::new(path);
+        Video {
+            id: id.to_string(),
+            path: path.to_string_lossy().to_string(),
+            filename: path.file_name().unwrap().to_string_lossy().to_string(),
+            folder_path: path.parent().unwrap().to_string_lossy().to_string(),
+            size: 1000,
+            duration: None,
+            thumbnail_path: None,
+            mtime: None,
+            created_at: "2024-01-01T00:00:00Z".to_string(),
+            updated_at: "2024-01-01T00:00:00Z".to_string(),
+            availability: Availability::Online,
+        }
+    }
+
+    fn count(db: &Database, table: &str) -> i64 {
+        let conn = db.pool.write();
+        conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| row.get(0)).unwrap()
+    }
+
+    /// A video with a row in every table that references videos
+    fn video_with_dependents(db: &Database, id: &str, path: &str) {
+        db.upsert_video(&video(id, path)).unwrap();
+        let tag = db.create_tag(&format!("tag-{}", id), "#ff0000").unwrap();
+        db.set_video_tags(id, &[tag.id]).unwrap();
+        let participant = db.create_participant(&format!("participant-{}", id)).unwrap();
+        db.set_video_participants(id, &[participant.id]).unwrap();
+        let language = db.create_language(&format!("l{}", id), "Language").unwrap();
+        db.set_video_languages(id, &[language.id]).unwrap();
+        db.save_playback_position(id, 12.0).unwrap();
+        db.begin_playback_session(id, 0.0).unwrap();
+        let playlist = db.create_playlist(&format!("playlist-{}", id)).unwrap();
+        db.insert_playlist_items(&playlist.id, &[id.to_string()], None).unwrap();
+        db.save_play_queue(&[id.to_string()], Some(0), RepeatMode::Off).unwrap();
+    }
+
+    const DEPENDENT_TABLES: &[&str] = &[
+        "video_tags",
+        "video_participants",
+        "video_languages",
+        "playback_history",
+        "playback_sessions",
+        "playlist_items",
+        "play_queue",
+    ];
+
+    #[test]
+    fn connection_pragmas_are_set() {
+        let db = temp_db("pragmas");
+        let conn = db.pool.write();
+        let foreign_keys: i64 = conn.query_row("PRAGMA foreign_keys", [], |row| row.get(0)).unwrap();
+        let journal_mode: String = conn.query_row("PRAGMA journal_mode", [], |row| row.get(0)).unwrap();
+        assert_eq!(foreign_keys, 1);
+        assert_eq!(journal_mode, "wal");
+    }
+
+    #[test]
+    fn reads_do_not_wait_for_the_writer() {
+        let db = temp_db("pool");
+        db.upsert_video(&video("video-1", "/videos/one.mp4")).unwrap();
+
+        // Keep a write transaction open, as a long scan would
+        let mut writer = db.pool.write();
+        let tx = writer.transaction().unwrap();
+        tx.execute("DELETE FROM videos", []).unwrap();
+
+        let db = &db;
+        std::thread::scope(|scope| {
+            let reader = scope.spawn(|| db.get_video("video-1").unwrap());
+            // Readers see the last committed state
+            assert!(reader.join().unwrap().is_some());
+        });
+        tx.commit().unwrap();
+        drop(writer);
+        assert!(db.get_video("video-1").unwrap().is_none());
+    }
+
+    #[test]
+    fn readers_are_read_only() {
+        let db = temp_db("read-only");
+        let reader = db.pool.read();
+        assert!(reader.execute("DELETE FROM videos", []).is_err());
+    }
+
+    #[test]
+    fn deleting_a_video_cascades() {
+        let db = temp_db("delete-video");
+        video_with_dependents(&db, "video-1", "/videos/one.mp4");
+        for table in DEPENDENT_TABLES {
+            assert_eq!(count(&db, table), 1, "{}", table);
+        }
+
+        db.delete_video("video-1").unwrap();
+        for table in DEPENDENT_TABLES {
+            assert_eq!(count(&db, table), 0, "{}", table);
+        }
+        // The referenced rows themselves stay
+        assert_eq!(count(&db, "tags"), 1);
+        assert_eq!(count(&db, "playlists"), 1);
+    }
+
+    #[test]
+    fn deleting_a_tag_cascades() {
+        let db = temp_db("delete-tag");
+        video_with_dependents(&db, "video-1", "/videos/one.mp4");
+        let tag_id = db.get_video_tags("video-1").unwrap()[0].id.clone();
+
+        db.delete_tag(&tag_id).unwrap();
+        assert_eq!(count(&db, "video_tags"), 0);
+        assert_eq!(count(&db, "videos"), 1);
+    }
+
+    #[test]
+    fn removing_a_mounted_folder_cascades() {
+        let db = temp_db("remove-folder");
+        db.add_mounted_folder("/videos", "videos", 1).unwrap();
+        video_with_dependents(&db, "video-1", "/videos/one.mp4");
+        video_with_dependents(&db, "video-2", "/other/two.mp4");
+
+        db.remove_mounted_folder("/videos").unwrap();
+        assert_eq!(count(&db, "videos"), 1);
+        assert_eq!(count(&db, "video_tags"), 1);
+        assert_eq!(count(&db, "playback_sessions"), 1);
+        assert_eq!(count(&db, "playlist_items"), 1);
+    }
+
+    #[test]
+    fn re_adding_a_folder_keeps_its_settings() {
+        let db = temp_db("re-add-folder");
+        let folder = db.add_mounted_folder("/videos", "videos", 2).unwrap();
+        let rules = ScanRules {
+            exclude_patterns: vec!["samples".to_string()],
+            ..ScanRules::default()
+        };
+        db.update_folder_scan_rules("/videos", &rules).unwrap();
+        db.set_folder_device("/videos", 42).unwrap();
+        {
+            let conn = db.pool.write();
+            conn.execute(
+                "INSERT INTO scan_reports (folder_path, started_at, duration_ms, status) VALUES ('/videos', '2024-01-01T00:00:00Z', 10, 'completed')",
+                [],
+            ).unwrap();
+        }
+
+        let again = db.add_mounted_folder("/videos", "Videos", 4).unwrap();
+        assert_eq!(again.id, folder.id);
+        assert_eq!(again.created_at, folder.created_at);
+        assert_eq!((again.name.as_str(), again.scan_depth), ("Videos", 4));
+        assert_eq!(again.scan_rules.exclude_patterns, ["samples"]);
+        assert_eq!(db.get_folder_device("/videos").unwrap(), Some(42));
+        assert_eq!(count(&db, "scan_reports"), 1);
+        assert_eq!(count(&db, "mounted_folders"), 1);
+    }
+
+    #[test]
+    fn check_database_removes_orphans() {
+        let db = temp_db("orphans");
+        video_with_dependents(&db, "video-1", "/videos/one.mp4");
+        video_with_dependents(&db, "video-2", "/videos/two.mp4");
+        {
+            // What an older release could leave behind
+            let conn = db.pool.write();
+            conn.execute_batch("PRAGMA foreign_keys = OFF; DELETE FROM videos WHERE id = 'video-1'; PRAGMA foreign_keys = ON;").unwrap();
+        }
+
+        let check = db.check_database().unwrap();
+        assert!(check.problems.is_empty(), "{:?}", check.problems);
+        // One row per dependent table, except the queue only ever held video-2
+        assert_eq!(check.orphans_removed, DEPENDENT_TABLES.len() - 1);
+        for table in DEPENDENT_TABLES {
+            assert_eq!(count(&db, table), 1, "{}", table);
+        }
+    }
+
+    #[test]
+    fn scans_keep_ids_and_mark_vanished_files() {
+        let db = temp_db("scan-diff");
+        let mut scanned = vec![
+            video("same", "/videos/same.mp4"),
+            video("changed", "/videos/changed.mp4"),
+            video("gone", "/videos/gone.mp4"),
+        ];
+        let changes = db.apply_folder_scan("/videos", &mut scanned, &[]).unwrap();
+        assert_eq!(changes.new_videos, 3);
+        let tag = db.create_tag("keep", "#ff0000").unwrap();
+        db.set_video_tags("gone", &[tag.id]).unwrap();
+
+        // Fresh ids from the scanner give way to the stored ones
+        let mut changed = video("changed-2", "/videos/changed.mp4");
+        changed.size = 2000;
+        let mut rescanned = vec![
+            video("same-2", "/videos/same.mp4"),
+            changed,
+            video("new", "/videos/new.mp4"),
+        ];
+        let changes = db.apply_folder_scan("/videos", &mut rescanned, &[]).unwrap();
+        assert_eq!((changes.new_videos, changes.updated_videos, changes.removed_videos), (1, 1, 1));
+        let ids: Vec<&str> = rescanned.iter().map(|v| v.id.as_str()).collect();
+        assert_eq!(ids, ["same", "changed", "new"]);
+        assert_eq!(db.get_video("changed").unwrap().unwrap().size, 2000);
+
+        // A vanished file keeps its row and metadata until it's relinked or removed
+        let gone = db.get_video("gone").unwrap().unwrap();
+        assert_eq!(gone.availability, Availability::Missing);
+        assert_eq!(db.get_video_tags("gone").unwrap().len(), 1);
+
+        let changes = db.apply_folder_scan("/videos", &mut rescanned, &[]).unwrap();
+        assert_eq!((changes.new_videos, changes.updated_videos, changes.removed_videos), (0, 0, 0));
+    }
+
+    #[test]
+    fn failed_probes_keep_stored_media_info() {
+        let db = temp_db("probe");
+        db.upsert_video(&video("video-1", "/videos/one.mp4")).unwrap();
+        let info = MediaInfo {
+            duration: Some(90.0),
+            width: Some(1920),
+            height: Some(1080),
+            video_codec: Some("h264".to_string()),
+            audio_tracks: 1,
+            ..Default::default()
+        };
+        db.save_media_info("video-1", Some(&info)).unwrap();
+        db.save_media_info("video-1", None).unwrap();
+
+        let stored = db.get_media_info("video-1").unwrap().unwrap();
+        assert_eq!((stored.duration, stored.width, stored.video_codec.as_deref()), (Some(90.0), Some(1920), Some("h264")));
+        assert!(db.get_unprobed_videos(10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn workers_only_pick_up_online_videos() {
+        let db = temp_db("online-work");
+        for id in ["online", "missing", "offline"] {
+            db.upsert_video(&video(id, &format!("/videos/{}.mp4", id))).unwrap();
+            db.save_partial_hash(id, Some("same"), None).unwrap();
+        }
+        db.set_availability(&[
+            ("missing".to_string(), Availability::Missing),
+            ("offline".to_string(), Availability::OfflineVolume),
+        ]).unwrap();
+
+        let ids = |videos: Vec<(String, String)>| videos.into_iter().map(|(id, _)| id).collect::<Vec<_>>();
+        assert_eq!(ids(db.get_unprobed_videos(10).unwrap()), ["online"]);
+        assert_eq!(ids(db.get_unconfirmed_duplicates(10).unwrap()), ["online"]);
+        let thumbnails = db.get_videos_missing_thumbnails(10).unwrap();
+        assert_eq!(thumbnails.into_iter().map(|(id, _, _)| id).collect::<Vec<_>>(), ["online"]);
+        assert_eq!(db.count_videos_missing_thumbnails().unwrap(), 1);
+
+        db.upsert_video(&video("unhashed", "/videos/unhashed.mp4")).unwrap();
+        db.set_availability(&[("unhashed".to_string(), Availability::OfflineVolume)]).unwrap();
+        assert!(db.get_unhashed_videos(10).unwrap().is_empty());
+
+        // A volume that comes back is worked on again
+        db.set_availability(&[("unhashed".to_string(), Availability::Online)]).unwrap();
+        assert_eq!(ids(db.get_unhashed_videos(10).unwrap()), ["unhashed"]);
+    }
+
+    #[test]
+    fn confirmed_duplicates_merge_into_the_kept_video() {
+        let db = temp_db("duplicates");
+        for id in ["keep", "copy", "edited", "other"] {
+            db.upsert_video(&video(id, &format!("/videos/{}.mp4", id))).unwrap();
+        }
+        for (id, partial) in [("keep", "p"), ("copy", "p"), ("edited", "p"), ("other", "q")] {
+            db.save_partial_hash(id, Some(partial), None).unwrap();
+        }
+
+        // Only partial hash collisions get hashed in full
+        let mut unconfirmed: Vec<String> = db.get_unconfirmed_duplicates(10).unwrap()
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect();
+        unconfirmed.sort();
+        assert_eq!(unconfirmed, ["copy", "edited", "keep"]);
+        for (id, full) in [("keep", "x"), ("copy", "x"), ("edited", "y")] {
+            db.save_content_hash(id, Some(full)).unwrap();
+        }
+        assert!(db.get_unconfirmed_duplicates(10).unwrap().is_empty());
+
+        let groups = db.find_duplicates().unwrap();
+        assert_eq!(groups.len(), 1);
+        let mut ids: Vec<&str> = groups[0].1.iter().map(|v| v.id.as_str()).collect();
+        ids.sort();
+        assert_eq!((groups[0].0.as_str(), ids), ("x", vec!["copy", "keep"]));
+
+        let tag = db.create_tag("favorite", "#ff0000").unwrap();
+        db.set_video_tags("copy", &[tag.id]).unwrap();
+        db.save_playback_position("copy", 42.0).unwrap();
+        db.set_video_notes("copy", Some("director's cut")).unwrap();
+
+        db.merge_duplicates("keep", &["copy".to_string()], true).unwrap();
+        assert!(db.get_video("copy").unwrap().is_none());
+        let tags: Vec<String> = db.get_video_tags("keep").unwrap().into_iter().map(|t| t.name).collect();
+        assert_eq!(tags, ["favorite"]);
+        assert_eq!(db.get_playback_history("keep").unwrap().unwrap().position, 42.0);
+        assert_eq!(db.get_video_notes("keep").unwrap().as_deref(), Some("director's cut"));
+        assert!(db.find_duplicates().unwrap().is_empty());
+    }
+
+    #[test]
+    fn collections_follow_their_saved_filter() {
+        let db = temp_db("collections");
+        let tag = db.create_tag("favorite", "#ff0000").unwrap();
+        for (id, path) in [("a", "/videos/a.mp4"), ("b", "/videos/b.mkv"), ("c", "/other/c.mp4")] {
+            db.upsert_video(&video(id, path)).unwrap();
+            db.set_video_tags(id, std::slice::from_ref(&tag.id)).unwrap();
+        }
+        db.add_mounted_folder("/videos", "videos", 2).unwrap();
+
+        let filter = FilterOptions {
+            tag_ids: vec![tag.id.clone()],
+            query: Some("ext:mp4".to_string()),
+            ..FilterOptions::default()
+        };
+        let collection = db.create_smart_collection("Favorite mp4s", &filter).unwrap();
+        assert_eq!(db.get_smart_collection(&collection.id).unwrap().unwrap().filter.query, filter.query);
+
+        let ids = |videos: &[Video]| videos.iter().map(|v| v.id.clone()).collect::<Vec<_>>();
+        let page = db.get_collection_videos(&collection.id, 1, 0).unwrap().unwrap();
+        let expected = db.get_videos(&FilterOptions { limit: 1, ..filter.clone() }).unwrap();
+        assert_eq!(ids(&page.videos), ids(&expected));
+        assert_eq!(page.total, db.get_video_count(&filter).unwrap());
+        assert_eq!(page.total, 2);
+        assert!(page.has_more);
+
+        // Counts are live: they follow the library and the saved filter
+        db.upsert_video(&video("d", "/videos/d.mp4")).unwrap();
+        db.set_video_tags("d", std::slice::from_ref(&tag.id)).unwrap();
+        let sidebar = db.get_sidebar().unwrap();
+        assert_eq!(sidebar.folders.len(), 1);
+        assert_eq!((sidebar.collections[0].name.as_str(), sidebar.collections[0].video_count), ("Favorite mp4s", 3));
+
+        let narrowed = FilterOptions {
+            folder_path: Some("/other".to_string()),
+            ..filter
+        };
+        db.update_smart_collection(&collection.id, "Other mp4s", &narrowed).unwrap();
+        let page = db.get_collection_videos(&collection.id, 10, 0).unwrap().unwrap();
+        assert_eq!(ids(&page.videos), ["c"]);
+        assert_eq!(db.get_sidebar().unwrap().collections[0].video_count, 1);
+
+        db.delete_smart_collection(&collection.id).unwrap();
+        assert!(db.get_collection_videos(&collection.id, 10, 0).unwrap().is_none());
+        assert!(db.get_sidebar().unwrap().collections.is_empty());
+    }
+
+    #[test]
+    fn search_survives_vacuum() {
+        let db = temp_db("search-vacuum");
+        for (id, path) in [("a", "/videos/alpha.mp4"), ("b", "/videos/beach.mp4"), ("c", "/videos/cabin.mp4")] {
+            db.upsert_video(&video(id, path)).unwrap();
+        }
+        db.delete_video("a").unwrap();
+        db.pool.write().execute_batch("VACUUM").unwrap();
+
+        let search = |text: &str| -> Vec<String> {
+            let filter = FilterOptions { search_query: Some(text.to_string()), ..Default::default() };
+            let query = FilterOptions { query: Some(text.to_string()), ..Default::default() };
+            let mut ids: Vec<String> = db.get_videos(&filter).unwrap().into_iter().map(|v| v.id).collect();
+            let query_ids: Vec<String> = db.get_videos(&query).unwrap().into_iter().map(|v| v.id).collect();
+            assert_eq!(ids, query_ids);
+            ids.sort();
+            ids
+        };
+        assert_eq!(search("beach"), ["b"]);
+        assert_eq!(search("cabin"), ["c"]);
+        assert!(search("alpha").is_empty());
+
+        db.rebuild_search_index().unwrap();
+        assert_eq!(search("beach"), ["b"]);
+    }
+
+    #[test]
+    fn query_errors_and_values_survive_the_database() {
+        let db = temp_db("query");
+        db.upsert_video(&video("video-1", "/videos/one.mp4")).unwrap();
+
+        let filter = |query: &str| FilterOptions { query: Some(query.to_string()), ..Default::default() };
+        let found = db.get_videos(&filter("name:\"'; DROP TABLE videos; --\"")).unwrap();
+        assert!(found.is_empty());
+        assert_eq!(count(&db, "videos"), 1);
+
+        let Err(rusqlite::Error::ToSqlConversionFailure(e)) = db.get_videos(&filter("size:huge")) else {
+            panic!("invalid query was accepted");
+        };
+        let e = e.downcast::<query::QueryError>().unwrap();
+        assert_eq!((e.start, e.end), (5, 9));
+    }
+
+    #[test]
+    fn repeated_ids_still_match_all() {
+        let db = temp_db("match-all");
+        db.upsert_video(&video("video-1", "/videos/one.mp4")).unwrap();
+        let a = db.create_tag("a", "#ff0000").unwrap();
+        let b = db.create_tag("b", "#00ff00").unwrap();
+        db.set_video_tags("video-1", &[a.id.clone(), b.id.clone()]).unwrap();
+
+        let filter = FilterOptions {
+            tag_ids: vec![a.id.clone(), b.id, a.id],
+            tag_match: MatchMode::All,
+            ..Default::default()
+        };
+        assert_eq!(db.get_videos(&filter).unwrap().len(), 1);
+        assert_eq!(db.get_video_count(&filter).unwrap(), 1);
+    }
+
+    #[test]
+    fn renamed_files_keep_their_ids() {
+        let db = temp_db("rename");
+        let root = std::env::temp_dir().join(format!("videoplayer-rename-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("sub")).unwrap();
+        std::fs::write(root.join("before.mp4"), vec![1u8; 4096]).unwrap();
+
+        let root_str = root.to_string_lossy().to_string();
+        let rules = Arc::new(RuleSet::new(&root_str, &ScanRules::default()).unwrap());
+        let scan = || crate::scanner::scan_folder(&root_str, 5, &rules).videos;
+
+        let mut scanned = scan();
+        db.apply_folder_scan(&root_str, &mut scanned, &[]).unwrap();
+        let id = scanned[0].id.clone();
+        let tag = db.create_tag("keep", "#ff0000").unwrap();
+        db.set_video_tags(&id, &[tag.id]).unwrap();
+
+        std::fs::rename(root.join("before.mp4"), root.join("sub").join("after.mp4")).unwrap();
+        let mut rescanned = scan();
+        let changes = db.apply_folder_scan(&root_str, &mut rescanned, &[]).unwrap();
+        assert_eq!((changes.new_videos, changes.removed_videos, changes.relinked.len()), (0, 0, 1));
+
+        let moved = db.get_video(&id).unwrap().unwrap();
+        assert!(moved.path.ends_with("after.mp4"));
+        assert_eq!(moved.availability, Availability::Online);
+        assert_eq!(db.get_video_tags(&id).unwrap().len(), 1);
+        assert_eq!(count(&db, "videos"), 1);
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn excluded_files_keep_their_rows() {
+        let db = temp_db("excluded");
+        let root = std::env::temp_dir().join(format!("videoplayer-excluded-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("samples")).unwrap();
+        std::fs::write(root.join("movie.mp4"), vec![1u8; 4096]).unwrap();
+        std::fs::write(root.join("samples").join("sample.mp4"), vec![2u8; 4096]).unwrap();
+
+        let root_str = root.to_string_lossy().to_string();
+        let scan = |rules: &ScanRules| {
+            let rules = Arc::new(RuleSet::new(&root_str, rules).unwrap());
+            crate::scanner::scan_folder(&root_str, 5, &rules).videos
+        };
+        let mut scanned = scan(&ScanRules::default());
+        db.apply_folder_scan(&root_str, &mut scanned, &[]).unwrap();
+        let sample = db.get_video_by_path(&root.join("samples").join("sample.mp4").to_string_lossy())
+            .unwrap().unwrap();
+        let tag = db.create_tag("keep", "#ff0000").unwrap();
+        db.set_video_tags(&sample.id, &[tag.id]).unwrap();
+
+        let skip_samples = ScanRules {
+            exclude_patterns: vec!["samples".to_string()],
+            ..ScanRules::default()
+        };
+        let mut rescanned = scan(&skip_samples);
+        let changes = db.apply_folder_scan(&root_str, &mut rescanned, &[]).unwrap();
+        assert_eq!(changes.removed_videos, 1);
+        assert_eq!(db.get_video(&sample.id).unwrap().unwrap().availability, Availability::Excluded);
+        assert_eq!(db.get_video_tags(&sample.id).unwrap().len(), 1);
+
+        // Out of the library and the missing list, and kept when the volume goes away
+        assert_eq!(db.get_videos(&FilterOptions::default()).unwrap().len(), 1);
+        assert_eq!(db.get_folder_tree(&root_str, None).unwrap().video_count, 1);
+        assert!(db.get_unavailable_videos().unwrap().is_empty());
+        db.mark_videos_at(&root_str, Availability::OfflineVolume).unwrap();
+        assert_eq!(db.get_video(&sample.id).unwrap().unwrap().availability, Availability::Excluded);
+
+        let mut restored = scan(&ScanRules::default());
+        db.apply_folder_scan(&root_str, &mut restored, &[]).unwrap();
+        assert_eq!(db.get_video(&sample.id).unwrap().unwrap().availability, Availability::Online);
+        assert_eq!(db.get_video_tags(&sample.id).unwrap().len(), 1);
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn unreadable_directories_keep_their_videos() {
+        let db = temp_db("unreadable");
+        let mut scanned = vec![video("a", "/videos/a.mp4"), video("b", "/videos/slow/b.mp4")];
+        db.apply_folder_scan("/videos", &mut scanned, &[]).unwrap();
+
+        // `/videos/slow` timed out on the next scan
+        let mut rescanned = vec![video("a2", "/videos/a.mp4")];
+        let changes = db.apply_folder_scan("/videos", &mut rescanned, &["/videos/slow".to_string()]).unwrap();
+        assert_eq!(changes.removed_videos, 0);
+        assert_eq!(db.get_video("b").unwrap().unwrap().availability, Availability::Online);
+
+        let changes = db.apply_folder_scan("/videos", &mut rescanned, &[]).unwrap();
+        assert_eq!(changes.removed_videos, 1);
+        assert_eq!(db.get_video("b").unwrap().unwrap().availability, Availability::Missing);
+    }
+
+    /// A first scan of thousands of videos, then an unchanged rescan
+    #[test]
+    fn applies_large_scans_in_one_transaction() {
+        let db = temp_db("large-scan");
+        let scan = || -> Vec<Video> {
+            (0..5000)
+                .map(|i| {
+                    let mut v = video(&format!("video-{}", i), &format!("/videos/{:02}/{}.mkv", i % 50, i));
+                    v.size = 1000 + i as u64;
+                    v
+                })
+                .collect()
+        };
+
+        let changes = db.apply_folder_scan("/videos", &mut scan(), &[]).unwrap();
+        assert_eq!(changes.new_videos, 5000);
+        assert_eq!(count(&db, "videos"), 5000);
+
+        let changes = db.apply_folder_scan("/videos", &mut scan(), &[]).unwrap();
+        assert_eq!(changes.new_videos + changes.updated_videos + changes.removed_videos, 0);
+    }
+
+    #[test]
+    fn last_scan_report_is_kept_per_folder() {
+        let db = temp_db("scan-report");
+        db.add_mounted_folder("/videos", "videos", 2).unwrap();
+        let report = |status, new_videos| ScanReport {
+            folder_path: "/videos".to_string(),
+            started_at: "2024-01-01T00:00:00Z".to_string(),
+            duration_ms: 1500,
+            status,
+            error: None,
+            total_videos: 3,
+            new_videos,
+            updated_videos: 0,
+            removed_videos: 0,
+            relinked_videos: 0,
+            errors: vec![ScanError {
+                path: "/videos/locked".to_string(),
+                kind: ScanErrorKind::ReadDir,
+                message: "Permission denied (os error 13)".to_string(),
+                os_error: Some(13),
+            }],
+        };
+
+        assert!(db.get_last_scan_report("/videos").unwrap().is_none());
+        db.save_scan_report(&report(ScanReportStatus::Completed, 3)).unwrap();
+        db.save_scan_report(&report(ScanReportStatus::Cancelled, 1)).unwrap();
+
+        let stored = db.get_last_scan_report("/videos").unwrap().unwrap();
+        assert_eq!(stored.status, ScanReportStatus::Cancelled);
+        assert_eq!(stored.new_videos, 1);
+        assert_eq!(stored.duration_ms, 1500);
+        assert_eq!(stored.errors.len(), 1);
+        assert_eq!(stored.errors[0].os_error, Some(13));
+
+        // Folders that aren't mounted don't get one, and unmounting drops it
+        let mut other = report(ScanReportStatus::Completed, 3);
+        other.folder_path = "/elsewhere".to_string();
+        db.save_scan_report(&other).unwrap();
+        assert!(db.get_last_scan_report("/elsewhere").unwrap().is_none());
+        db.remove_mounted_folder("/videos").unwrap();
+        assert_eq!(count(&db, "scan_reports"), 0);
+    }
+
+    #[test]
+    fn groups_episodes_into_series() {
+        let db = temp_db("series");
+        for (id, path) in [
+            ("e3", "/tv/Show.Name.S01E03.720p-GRP.mkv"),
+            ("e1", "/tv/Show.Name.S01E01E02.1080p-GRP.mkv"),
+            ("e5", "/tv/Show Name - 2x01.mkv"),
+            ("f1", "/tv/Other Show/Season 1/S01E01.mkv"),
+            ("m1", "/movies/Movie.2010.1080p.mkv"),
+        ] {
+            db.upsert_video(&video(id, path)).unwrap();
+        }
+        assert_eq!(db.sync_episodes().unwrap(), 4);
+
+        let series = db.get_series().unwrap();
+        let titles: Vec<&str> = series.iter().map(|s| s.title.as_str()).collect();
+        assert_eq!(titles, ["Other Show", "Show Name"]);
+        let show = &series[1];
+        assert_eq!((show.season_count, show.episode_count, show.watched_count), (2, 3, 0));
+
+        let episodes = db.get_episodes(&show.id, None).unwrap();
+        let ids: Vec<&str> = episodes.iter().map(|e| e.video.id.as_str()).collect();
+        assert_eq!(ids, ["e1", "e3", "e5"]);
+        assert_eq!(episodes[0].episode_end, Some(2));
+        assert_eq!(episodes[0].resolution.as_deref(), Some("1080p"));
+        assert_eq!(episodes[0].release_group.as_deref(), Some("GRP"));
+        assert_eq!(db.get_episodes(&show.id, Some(2)).unwrap().len(), 1);
+
+        let seasons: Vec<(u32, usize)> = db.get_seasons(&show.id).unwrap()
+            .iter()
+            .map(|s| (s.season, s.episode_count))
+            .collect();
+        assert_eq!(seasons, [(1, 2), (2, 1)]);
+
+        // Series keep their ids and go away with their last episode
+        db.delete_video("f1").unwrap();
+        db.sync_episodes().unwrap();
+        let series = db.get_series().unwrap();
+        assert_eq!(series.len(), 1);
+        assert_eq!(series[0].id, show.id);
+        assert_eq!(count(&db, "series"), 1);
+    }
+
+    #[test]
+    fn excluded_episodes_are_left_out() {
+        let db = temp_db("series-availability");
+        for (id, path) in [
+            ("e1", "/tv/Show.S01E01.mkv"),
+            ("e2", "/tv/Show.S01E02.mkv"),
+            ("e3", "/tv/Show.S02E01.mkv"),
+        ] {
+            db.upsert_video(&video(id, path)).unwrap();
+        }
+        db.sync_episodes().unwrap();
+        db.set_availability(&[
+            ("e2".to_string(), Availability::Missing),
+            ("e3".to_string(), Availability::Excluded),
+        ]).unwrap();
+
+        let series = db.get_series().unwrap();
+        assert_eq!((series[0].season_count, series[0].episode_count), (1, 2));
+        let seasons: Vec<(u32, usize)> = db.get_seasons(&series[0].id).unwrap()
+            .iter()
+            .map(|s| (s.season, s.episode_count))
+            .collect();
+        assert_eq!(seasons, [(1, 2)]);
+
+        // Missing episodes are listed so they can be shown as such
+        let episodes: Vec<(String, Availability)> = db.get_episodes(&series[0].id, None).unwrap()
+            .into_iter()
+            .map(|e| (e.video.id, e.availability))
+            .collect();
+        assert_eq!(episodes, [("e1".to_string(), Availability::Online), ("e2".to_string(), Availability::Missing)]);
+    }
+
+    #[test]
+    fn episodes_follow_the_videos_that_changed() {
+        let db = temp_db("series-incremental");
+        let mut scanned = vec![
+            video("old", "/tv/Doctor.Who.1963.S01E01.mkv"),
+            video("new", "/tv/Doctor.Who.2005.S01E01.mkv"),
+        ];
+        db.apply_folder_scan("/tv", &mut scanned, &[]).unwrap();
+
+        // Same title, different years: two series, without a full rebuild
+        let series = db.get_series().unwrap();
+        let years: Vec<Option<u32>> = series.iter().map(|s| s.year).collect();
+        assert_eq!(years.len(), 2);
+        assert!(years.contains(&Some(1963)) && years.contains(&Some(2005)));
+
+        // A rename moves the episode and drops the series it leaves empty
+        db.relink_video("old", &video("old", "/tv/Doctor.Who.2005.S01E02.mkv")).unwrap();
+        let series = db.get_series().unwrap();
+        assert_eq!(series.len(), 1);
+        assert_eq!((series[0].year, series[0].episode_count), (Some(2005), 2));
+
+        db.update_video_path("/tv/Doctor.Who.2005.S01E02.mkv", "/tv/extras/trailer.mkv", "/tv/extras", "trailer.mkv").unwrap();
+        assert_eq!(db.get_series().unwrap()[0].episode_count, 1);
+        assert_eq!(count(&db, "episodes"), 1);
+    }
+
+    #[test]
+    fn next_episode_follows_watch_progress() {
+        let db = temp_db("next-episode");
+        for episode in 1..=4 {
+            let id = format!("ep{}", episode);
+            db.upsert_video(&video(&id, &format!("/tv/Show.S01E0{}.mkv", episode))).unwrap();
+        }
+        db.sync_episodes().unwrap();
+        let series_id = db.get_series().unwrap()[0].id.clone();
+        let played = |id: &str, completed: bool, last_played: &str| {
+            let conn = db.pool.write();
+            conn.execute(
+                r#"INSERT OR REPLACE INTO playback_history (video_id, position, completed, last_played, first_played)
+                   VALUES (?1, 10, ?2, ?3, ?3)"#,
+                params![id, completed, last_played],
+            ).unwrap();
+        };
+        let next = || db.get_next_episode(&series_id).unwrap().map(|e| e.video.id);
+
+        assert_eq!(next().as_deref(), Some("ep1"));
+
+        // A started episode is resumed, a finished one moves on
+        played("ep2", false, "2024-01-02T00:00:00Z");
+        assert_eq!(next().as_deref(), Some("ep2"));
+        played("ep2", true, "2024-01-03T00:00:00Z");
+        assert_eq!(next().as_deref(), Some("ep3"));
+
+        // Unavailable files are skipped, then earlier unwatched episodes come up
+        db.set_availability(&[("ep3".to_string(), Availability::Missing)]).unwrap();
+        played("ep4", true, "2024-01-04T00:00:00Z");
+        assert_eq!(next().as_deref(), Some("ep1"));
+        assert_eq!(db.get_series().unwrap()[0].watched_count, 2);
+
+        played("ep1", true, "2024-01-05T00:00:00Z");
+        assert_eq!(next(), None);
+    }
+}
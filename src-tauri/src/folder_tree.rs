@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+use std::path::{Component, Path};
+use crate::models::FolderNode;
+
+/// Videos stored directly in one folder, or summed over a subtree
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FolderTotals {
+    pub video_count: usize,
+    pub total_size: u64,
+    /// Seconds; videos that haven't been probed yet count as zero
+    pub total_duration: f64,
+}
+
+impl FolderTotals {
+    fn add(&mut self, other: &FolderTotals) {
+        self.video_count += other.video_count;
+        self.total_size += other.total_size;
+        self.total_duration += other.total_duration;
+    }
+}
+
+#[derive(Default)]
+struct Branch {
+    totals: FolderTotals,
+    children: HashMap<String, Branch>,
+}
+
+/// Build the folder tree below `root` from the totals of every folder that
+/// directly holds videos. Each node's totals include all folders below it.
+/// `depth` limits how many levels of children are returned (`None` for all);
+/// nodes cut off there report `has_children` so their subtree can be loaded
+/// later.
+pub fn build(root: &str, folders: &[(String, FolderTotals)], depth: Option<usize>) -> FolderNode {
+    let root_path = Path::new(root);
+    let mut tree = Branch::default();
+
+    for (folder, totals) in folders {
+        // Compare whole components so `/a/foo` never takes in `/a/foobar`
+        let Ok(relative) = Path::new(folder).strip_prefix(root_path) else {
+            continue;
+        };
+        let mut branch = &mut tree;
+        branch.totals.add(totals);
+        for component in relative.components() {
+            if let Component::Normal(name) = component {
+                branch = branch.children.entry(name.to_string_lossy().to_string()).or_default();
+                branch.totals.add(totals);
+            }
+        }
+    }
+
+    let name = root_path.file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| root.to_string());
+    into_node(root.to_string(), name, tree, depth)
+}
+
+fn into_node(path: String, name: String, branch: Branch, depth: Option<usize>) -> FolderNode {
+    let has_children = !branch.children.is_empty();
+
+    let mut children: Vec<FolderNode> = match depth {
+        Some(0) => Vec::new(),
+        _ => branch.children
+            .into_iter()
+            .map(|(child_name, child)| {
+                let child_path = Path::new(&path).join(&child_name).to_string_lossy().to_string();
+                into_node(child_path, child_name, child, depth.map(|d| d - 1))
+            })
+            .collect(),
+    };
+    children.sort_by_key(|c| c.name.to_lowercase());
+
+    FolderNode {
+        path,
+        name,
+        children,
+        video_count: branch.totals.video_count,
+        total_size: branch.totals.total_size,
+        total_duration: branch.totals.total_duration,
+        has_children,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn totals(video_count: usize, total_size: u64) -> FolderTotals {
+        FolderTotals {
+            video_count,
+            total_size,
+            total_duration: video_count as f64 * 60.0,
+        }
+    }
+
+    fn child<'a>(node: &'a FolderNode, name: &str) -> &'a FolderNode {
+        node.children.iter()
+            .find(|c| c.name == name)
+            .unwrap_or_else(|| panic!("{} has no child {}", node.path, name))
+    }
+
+    #[test]
+    fn prefixes_match_whole_components() {
+        let folders = vec![
+            ("/a/foo".to_string(), totals(1, 100)),
+            ("/a/foobar".to_string(), totals(2, 200)),
+        ];
+        let tree = build("/a/foo", &folders, None);
+        assert_eq!((tree.video_count, tree.total_size), (1, 100));
+        assert!(tree.children.is_empty());
+        assert!(!tree.has_children);
+
+        let tree = build("/a", &folders, None);
+        let names: Vec<&str> = tree.children.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["foo", "foobar"]);
+    }
+
+    #[test]
+    fn totals_include_every_folder_below() {
+        let folders = vec![
+            ("/lib".to_string(), totals(1, 10)),
+            ("/lib/tv/Show/Season 1".to_string(), totals(3, 300)),
+            ("/lib/tv/Show/Season 2".to_string(), totals(2, 200)),
+            ("/lib/movies".to_string(), totals(4, 4000)),
+        ];
+        let tree = build("/lib", &folders, None);
+        assert_eq!((tree.video_count, tree.total_size), (10, 4510));
+        assert_eq!(tree.total_duration, 600.0);
+
+        let show = child(child(&tree, "tv"), "Show");
+        assert_eq!((show.video_count, show.total_size), (5, 500));
+        assert!(Path::new(&show.path) == Path::new("/lib/tv/Show"));
+        assert_eq!(child(show, "Season 2").video_count, 2);
+        // Sorted by name
+        assert_eq!(tree.children[0].name, "movies");
+    }
+
+    #[test]
+    fn depth_cuts_off_children_but_keeps_totals() {
+        let folders = vec![("/lib/tv/Show/Season 1".to_string(), totals(3, 300))];
+
+        let tree = build("/lib", &folders, Some(0));
+        assert!(tree.children.is_empty());
+        assert!(tree.has_children);
+        assert_eq!(tree.video_count, 3);
+
+        let tree = build("/lib", &folders, Some(1));
+        let tv = child(&tree, "tv");
+        assert!(tv.children.is_empty());
+        assert!(tv.has_children);
+        assert_eq!(tv.video_count, 3);
+
+        let tree = build("/lib", &folders, None);
+        let season = child(child(child(&tree, "tv"), "Show"), "Season 1");
+        assert!(!season.has_children);
+    }
+
+    #[test]
+    fn builds_from_the_filesystem_root() {
+        let folders = vec![
+            ("/".to_string(), totals(1, 10)),
+            ("/videos".to_string(), totals(2, 20)),
+        ];
+        let tree = build("/", &folders, None);
+        assert_eq!(tree.name, "/");
+        assert_eq!(tree.video_count, 3);
+        let videos = child(&tree, "videos");
+        assert!(Path::new(&videos.path) == Path::new("/videos"));
+        assert_eq!(videos.video_count, 2);
+    }
+}
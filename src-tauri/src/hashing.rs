@@ -0,0 +1,171 @@
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Mutex;
+use std::thread;
+use tauri::{AppHandle, Emitter, Manager};
+use crate::commands::AppState;
+
+/// Bytes read from each end of a file for its partial hash
+const PARTIAL_CHUNK: u64 = 4 * 1024 * 1024;
+
+/// Number of videos fetched from the database per hashing pass
+const BATCH_SIZE: usize = 50;
+
+/// Background queue that finds duplicate files.
+///
+/// Every video gets a cheap partial hash (size plus the first and last few MB).
+/// Only videos whose partial hash collides with another one are hashed in
+/// full, so a large library isn't read end to end. Like the probe queue, work
+/// is tracked in the database (`videos.hashed_at IS NULL`).
+pub struct HashQueue {
+    sender: Sender<()>,
+    receiver: Mutex<Option<Receiver<()>>>,
+}
+
+impl HashQueue {
+    pub fn new() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        HashQueue {
+            sender,
+            receiver: Mutex::new(Some(receiver)),
+        }
+    }
+
+    /// Start the worker thread and process anything left over from previous runs
+    pub fn start(&self, app: AppHandle) {
+        if let Some(receiver) = self.receiver.lock().unwrap().take() {
+            thread::spawn(move || run_worker(app, receiver));
+            self.wake();
+        }
+    }
+
+    /// Ask the worker to look for unhashed videos
+    pub fn wake(&self) {
+        let _ = self.sender.send(());
+    }
+}
+
+fn run_worker(app: AppHandle, receiver: Receiver<()>) {
+    while receiver.recv().is_ok() {
+        // Collapse wake-ups that arrived while we were busy
+        while receiver.try_recv().is_ok() {}
+
+        let state = app.state::<AppState>();
+        let mut confirmed = 0;
+
+        // Partial hashes first, so every collision is known before full hashing starts.
+        // A failed save would hand out the same batch again, so the pass stops
+        // until the next wake-up instead.
+        'partial: loop {
+            let batch = state.db.get_unhashed_videos(BATCH_SIZE).unwrap_or_default();
+            if batch.is_empty() {
+                break;
+            }
+            for (video_id, path) in batch {
+                let hash = partial_hash(Path::new(&path)).ok();
+                let file_inode = inode(Path::new(&path));
+                if let Err(e) = state.db.save_partial_hash(&video_id, hash.as_deref(), file_inode) {
+                    log::error!("Failed to save hash: {}", e);
+                    break 'partial;
+                }
+            }
+        }
+
+        'full: loop {
+            let batch = state.db.get_unconfirmed_duplicates(BATCH_SIZE).unwrap_or_default();
+            if batch.is_empty() {
+                break;
+            }
+            for (video_id, path) in batch {
+                let hash = full_hash(Path::new(&path)).ok();
+                match state.db.save_content_hash(&video_id, hash.as_deref()) {
+                    Ok(()) if hash.is_some() => confirmed += 1,
+                    Ok(()) => {}
+                    Err(e) => {
+                        log::error!("Failed to save hash: {}", e);
+                        break 'full;
+                    }
+                }
+            }
+        }
+
+        if confirmed > 0 {
+            let _ = app.emit("duplicates-changed", confirmed);
+        }
+    }
+}
+
+/// BLAKE3 of the file size and its first and last `PARTIAL_CHUNK` bytes
+pub fn partial_hash(path: &Path) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let size = file.metadata()?.len();
+
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(&size.to_le_bytes());
+    io::copy(&mut (&mut file).take(PARTIAL_CHUNK), &mut hasher)?;
+    if size > PARTIAL_CHUNK {
+        // Don't hash bytes of a short file twice
+        file.seek(SeekFrom::Start(PARTIAL_CHUNK.max(size - PARTIAL_CHUNK)))?;
+        io::copy(&mut file, &mut hasher)?;
+    }
+
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+/// Inode number of a file, used to recognize it after a rename. Windows has no
+/// stable equivalent, so moves there are matched by partial hash alone.
+pub fn inode(path: &Path) -> Option<i64> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        std::fs::metadata(path).ok().map(|m| m.ino() as i64)
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+        None
+    }
+}
+
+/// BLAKE3 of the whole file
+pub fn full_hash(path: &Path) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = blake3::Hasher::new();
+    io::copy(&mut file, &mut hasher)?;
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn partial_hashes_only_cover_the_ends() {
+        let dir = std::env::temp_dir().join(format!("videoplayer-hashing-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        // Same size and ends, different middle
+        let size = (2 * PARTIAL_CHUNK + 1024 * 1024) as usize;
+        let original = vec![7u8; size];
+        let mut edited = original.clone();
+        edited[size / 2] = 8;
+        let (a, b, c) = (dir.join("a.mkv"), dir.join("b.mkv"), dir.join("c.mkv"));
+        fs::write(&a, &original).unwrap();
+        fs::write(&b, &edited).unwrap();
+        fs::write(&c, &original).unwrap();
+
+        assert_eq!(partial_hash(&a).unwrap(), partial_hash(&b).unwrap());
+        assert_ne!(full_hash(&a).unwrap(), full_hash(&b).unwrap());
+        assert_eq!(full_hash(&a).unwrap(), full_hash(&c).unwrap());
+
+        // The size is part of the partial hash even when the bytes read match
+        fs::write(&c, &original[..size - 1]).unwrap();
+        assert_ne!(partial_hash(&a).unwrap(), partial_hash(&c).unwrap());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -1,13 +1,36 @@
+mod availability;
 mod commands;
 mod database;
+mod folder_tree;
+mod hashing;
+mod migrations;
 mod models;
+mod mpv_ipc;
+mod playback;
 mod player;
+mod playlist;
+mod pool;
+mod probe;
+mod query;
+mod queue;
+mod scan_queue;
+mod scan_rules;
 mod scanner;
+mod series;
+mod thumbnails;
+mod watcher;
 
 use commands::AppState;
 use database::Database;
 use player::PlayerState;
-use std::sync::Mutex;
+use hashing::HashQueue;
+use probe::ProbeQueue;
+use queue::PlayQueue;
+use scan_queue::ScanQueue;
+use std::sync::Arc;
+use tauri::Manager;
+use thumbnails::ThumbnailQueue;
+use watcher::FolderWatcher;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
@@ -18,9 +41,41 @@ pub fn run() {
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_shell::init())
+        .plugin(tauri_plugin_log::Builder::new().level(log::LevelFilter::Info).build())
         .manage(AppState {
-            db: Mutex::new(db),
+            db: Arc::new(db),
             player: PlayerState::new(),
+            watcher: FolderWatcher::new(),
+            probe: ProbeQueue::new(),
+            thumbnails: ThumbnailQueue::new(),
+            hashing: HashQueue::new(),
+            scans: ScanQueue::new(),
+        })
+        .setup(|app| {
+            let state = app.state::<AppState>();
+            state.watcher.start(app.handle().clone());
+            state.probe.start(app.handle().clone());
+            state.thumbnails.start(app.handle().clone());
+            state.hashing.start(app.handle().clone());
+            state.scans.start(app.handle().clone());
+            playback::spawn_tracker(app.handle().clone());
+            availability::spawn_checker(app.handle().clone());
+            
+            let (items, current, repeat) = state.db.load_play_queue()?;
+            *state.player.queue.lock().unwrap() = PlayQueue::restore(items, current, repeat);
+            
+            // Picks up videos stored before series detection existed
+            if let Err(e) = state.db.sync_episodes() {
+                log::error!("Failed to update episodes: {}", e);
+            }
+            
+            let folders = state.db.get_mounted_folders()?;
+            for folder in &folders {
+                if let Err(e) = state.watcher.watch(folder) {
+                    log::warn!("{}", e);
+                }
+            }
+            Ok(())
         })
         .invoke_handler(tauri::generate_handler![
             // Folder commands
@@ -28,14 +83,61 @@ pub fn run() {
             commands::get_mounted_folders,
             commands::remove_mounted_folder,
             commands::update_folder_scan_depth,
+            commands::update_folder_scan_rules,
             // Scan commands
             commands::scan_folder,
+            commands::get_scan_jobs,
+            commands::cancel_scan,
+            commands::get_last_scan_report,
             commands::get_folder_tree,
             // Video commands
             commands::get_videos,
             commands::get_video_with_metadata,
+            commands::set_video_notes,
+            commands::search_suggestions,
+            commands::rebuild_search_index,
+            commands::check_database,
+            commands::validate_query,
             commands::delete_video,
+            commands::get_missing_videos,
+            commands::relink_video,
             commands::move_video_file,
+            // Smart collection commands
+            commands::create_smart_collection,
+            commands::get_smart_collections,
+            commands::get_smart_collection,
+            commands::update_smart_collection,
+            commands::delete_smart_collection,
+            commands::get_collection_videos,
+            commands::get_sidebar,
+            // Playlist commands
+            commands::create_playlist,
+            commands::get_playlists,
+            commands::rename_playlist,
+            commands::delete_playlist,
+            commands::get_playlist_items,
+            commands::add_to_playlist,
+            commands::remove_from_playlist,
+            commands::reorder_playlist,
+            commands::play_playlist,
+            commands::get_playlist_playback,
+            commands::export_playlist,
+            commands::import_playlist,
+            // Duplicate commands
+            commands::find_duplicates,
+            commands::resolve_duplicates,
+            // Play queue commands
+            commands::get_queue,
+            commands::enqueue,
+            commands::enqueue_next,
+            commands::remove_from_queue,
+            commands::move_in_queue,
+            commands::clear_queue,
+            commands::shuffle_queue,
+            commands::set_repeat_mode,
+            commands::play_queue,
+            commands::queue_next,
+            commands::queue_previous,
             // Tag commands
             commands::create_tag,
             commands::get_tags,
@@ -57,12 +159,34 @@ pub fn run() {
             // Playback commands
             commands::save_playback_position,
             commands::get_playback_position,
+            commands::mark_watched,
+            commands::mark_unwatched,
+            commands::get_playback_history,
+            commands::get_watch_history,
+            commands::get_continue_watching,
+            commands::begin_playback_session,
+            commands::end_playback_session,
+            // Series commands
+            commands::get_series,
+            commands::get_seasons,
+            commands::get_episodes,
+            commands::get_next_episode,
+            commands::play_next_episode,
             // Thumbnail commands
             commands::get_thumbnail_path,
+            commands::get_thumbnail_offset,
+            commands::set_thumbnail_offset,
             // MPV commands
             commands::play_video_mpv,
             commands::stop_video_mpv,
             commands::is_mpv_running,
+            commands::mpv_get_state,
+            commands::mpv_toggle_pause,
+            commands::mpv_seek,
+            commands::mpv_set_volume,
+            commands::mpv_cycle_subtitle,
+            commands::mpv_cycle_audio,
+            commands::mpv_load_file,
             commands::check_mpv_installed,
             commands::find_subtitle_for_video,
         ])
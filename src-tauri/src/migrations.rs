@@ -0,0 +1,585 @@
+use std::path::{Path, PathBuf};
+use rusqlite::{ffi, Connection, DatabaseName, Error, Result, Transaction};
+
+/// One step of the schema history. Steps are applied in order, each in its
+/// own transaction, and `PRAGMA user_version` holds the number of steps
+/// applied so far. Never edit a released step; append a new one instead.
+struct Migration {
+    description: &'static str,
+    apply: fn(&Transaction) -> Result<()>,
+}
+
+const MIGRATIONS: &[Migration] = &[
+    Migration { description: "initial schema", apply: initial_schema },
+    Migration { description: "file modification times, media info and settings", apply: media_info },
+    Migration { description: "watch history", apply: watch_history },
+    Migration { description: "notes and full-text search", apply: search },
+    Migration { description: "smart collections", apply: smart_collections },
+    Migration { description: "playlists and play queue", apply: playlists },
+    Migration { description: "content fingerprints", apply: fingerprints },
+    Migration { description: "file availability", apply: availability },
+    Migration { description: "remove rows orphaned while foreign keys were off", apply: foreign_keys },
+    Migration { description: "per-folder scan rules", apply: scan_rules },
+    Migration { description: "last scan report per folder", apply: scan_reports },
+    Migration { description: "tv series and episodes", apply: series },
+];
+
+/// Schema version of a database with every migration applied
+pub fn latest_version() -> u32 {
+    MIGRATIONS.len() as u32
+}
+
+/// Bring the database at `db_path` up to the latest schema. An existing
+/// database is copied next to it before the first pending step runs.
+pub fn migrate(conn: &mut Connection, db_path: &Path) -> Result<()> {
+    let version: u32 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    if version > latest_version() {
+        return Err(Error::SqliteFailure(
+            ffi::Error::new(ffi::SQLITE_ERROR),
+            Some(format!(
+                "Database schema version {} is newer than this app supports ({})",
+                version,
+                latest_version()
+            )),
+        ));
+    }
+    if version == latest_version() {
+        return Ok(());
+    }
+
+    let has_tables: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table')",
+        [],
+        |row| row.get(0),
+    )?;
+    if has_tables {
+        backup(conn, db_path, version)?;
+    }
+
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        let target = index as u32 + 1;
+        let tx = conn.transaction()?;
+        if let Err(e) = (migration.apply)(&tx) {
+            log::error!("Migration {} ({}) failed: {}", target, migration.description, e);
+            return Err(e);
+        }
+        tx.pragma_update(None, "user_version", target)?;
+        tx.commit()?;
+    }
+    Ok(())
+}
+
+/// Where the copy made before migrating from `version` is stored
+pub fn backup_path(db_path: &Path, version: u32) -> PathBuf {
+    db_path.with_file_name(format!("database.v{}.backup.sqlite", version))
+}
+
+/// Page-by-page copy of the whole database
+fn backup(conn: &Connection, db_path: &Path, version: u32) -> Result<()> {
+    conn.backup(DatabaseName::Main, backup_path(db_path, version), None)
+}
+
+/// Add a column unless it is already there. Databases created before schema
+/// versioning got columns added one by one at startup, so they may already
+/// have some of the columns the early steps add.
+fn add_column(tx: &Transaction, table: &str, column: &str, definition: &str) -> Result<()> {
+    let mut stmt = tx.prepare(&format!("PRAGMA table_info({})", table))?;
+    let exists = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .collect::<Result<Vec<_>>>()?
+        .iter()
+        .any(|name| name == column);
+
+    if !exists {
+        tx.execute(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition), [])?;
+    }
+    Ok(())
+}
+
+fn initial_schema(tx: &Transaction) -> Result<()> {
+    tx.execute_batch(r#"
+        -- Mounted folders table
+        CREATE TABLE IF NOT EXISTS mounted_folders (
+            id TEXT PRIMARY KEY,
+            path TEXT NOT NULL UNIQUE,
+            name TEXT NOT NULL,
+            scan_depth INTEGER NOT NULL DEFAULT 2,
+            created_at TEXT NOT NULL
+        );
+
+        -- Videos table
+        CREATE TABLE IF NOT EXISTS videos (
+            id TEXT PRIMARY KEY,
+            path TEXT NOT NULL UNIQUE,
+            filename TEXT NOT NULL,
+            folder_path TEXT NOT NULL,
+            size INTEGER NOT NULL DEFAULT 0,
+            duration REAL,
+            thumbnail_path TEXT,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        );
+
+        -- Tags table
+        CREATE TABLE IF NOT EXISTS tags (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL UNIQUE,
+            color TEXT NOT NULL DEFAULT '#6366f1'
+        );
+
+        -- Video-Tags junction table
+        CREATE TABLE IF NOT EXISTS video_tags (
+            video_id TEXT NOT NULL,
+            tag_id TEXT NOT NULL,
+            PRIMARY KEY (video_id, tag_id),
+            FOREIGN KEY (video_id) REFERENCES videos(id) ON DELETE CASCADE,
+            FOREIGN KEY (tag_id) REFERENCES tags(id) ON DELETE CASCADE
+        );
+
+        -- Participants table
+        CREATE TABLE IF NOT EXISTS participants (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL UNIQUE
+        );
+
+        -- Video-Participants junction table
+        CREATE TABLE IF NOT EXISTS video_participants (
+            video_id TEXT NOT NULL,
+            participant_id TEXT NOT NULL,
+            PRIMARY KEY (video_id, participant_id),
+            FOREIGN KEY (video_id) REFERENCES videos(id) ON DELETE CASCADE,
+            FOREIGN KEY (participant_id) REFERENCES participants(id) ON DELETE CASCADE
+        );
+
+        -- Languages table
+        CREATE TABLE IF NOT EXISTS languages (
+            id TEXT PRIMARY KEY,
+            code TEXT NOT NULL UNIQUE,
+            name TEXT NOT NULL
+        );
+
+        -- Video-Languages junction table
+        CREATE TABLE IF NOT EXISTS video_languages (
+            video_id TEXT NOT NULL,
+            language_id TEXT NOT NULL,
+            PRIMARY KEY (video_id, language_id),
+            FOREIGN KEY (video_id) REFERENCES videos(id) ON DELETE CASCADE,
+            FOREIGN KEY (language_id) REFERENCES languages(id) ON DELETE CASCADE
+        );
+
+        -- Playback history
+        CREATE TABLE IF NOT EXISTS playback_history (
+            video_id TEXT PRIMARY KEY,
+            position REAL NOT NULL DEFAULT 0,
+            last_played TEXT NOT NULL,
+            FOREIGN KEY (video_id) REFERENCES videos(id) ON DELETE CASCADE
+        );
+
+        -- Create indexes for better performance
+        CREATE INDEX IF NOT EXISTS idx_videos_folder ON videos(folder_path);
+        CREATE INDEX IF NOT EXISTS idx_videos_filename ON videos(filename);
+    "#)
+}
+
+fn media_info(tx: &Transaction) -> Result<()> {
+    for (column, definition) in [
+        ("mtime", "INTEGER"),
+        ("width", "INTEGER"),
+        ("height", "INTEGER"),
+        ("video_codec", "TEXT"),
+        ("audio_codec", "TEXT"),
+        ("bitrate", "INTEGER"),
+        ("frame_rate", "REAL"),
+        ("audio_tracks", "INTEGER"),
+        ("probed_at", "TEXT"),
+        ("thumbnail_checked_at", "TEXT"),
+    ] {
+        add_column(tx, "videos", column, definition)?;
+    }
+
+    tx.execute_batch(r#"
+        -- Application settings
+        CREATE TABLE IF NOT EXISTS settings (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL
+        );
+    "#)
+}
+
+fn watch_history(tx: &Transaction) -> Result<()> {
+    for (column, definition) in [
+        ("completed", "INTEGER NOT NULL DEFAULT 0"),
+        ("play_count", "INTEGER NOT NULL DEFAULT 0"),
+        ("total_watch_time", "REAL NOT NULL DEFAULT 0"),
+        ("first_played", "TEXT"),
+    ] {
+        add_column(tx, "playback_history", column, definition)?;
+    }
+
+    tx.execute_batch(r#"
+        -- Append-only log of viewing sessions
+        CREATE TABLE IF NOT EXISTS playback_sessions (
+            id TEXT PRIMARY KEY,
+            video_id TEXT NOT NULL,
+            started_at TEXT NOT NULL,
+            ended_at TEXT,
+            start_position REAL NOT NULL DEFAULT 0,
+            end_position REAL,
+            FOREIGN KEY (video_id) REFERENCES videos(id) ON DELETE CASCADE
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_playback_sessions_started ON playback_sessions(started_at);
+    "#)
+}
+
+/// Rewrites the `video_search` rows of the videos matching `{condition}`, as
+/// step 4 wrote it into its triggers
+const SEARCH_REFRESH: &str = r#"
+    INSERT OR IGNORE INTO video_search_keys (video_id) SELECT v.id FROM videos v WHERE {condition};
+    DELETE FROM video_search WHERE rowid IN (
+        SELECT k.id FROM video_search_keys k INNER JOIN videos v ON v.id = k.video_id WHERE {condition});
+    INSERT INTO video_search (rowid, filename, path, tags, participants, languages, notes)
+    SELECT k.id, v.filename, v.path,
+        COALESCE((SELECT group_concat(t.name, ' ') FROM video_tags vt
+                  INNER JOIN tags t ON t.id = vt.tag_id WHERE vt.video_id = v.id), ''),
+        COALESCE((SELECT group_concat(p.name, ' ') FROM video_participants vp
+                  INNER JOIN participants p ON p.id = vp.participant_id WHERE vp.video_id = v.id), ''),
+        COALESCE((SELECT group_concat(l.name || ' ' || l.code, ' ') FROM video_languages vl
+                  INNER JOIN languages l ON l.id = vl.language_id WHERE vl.video_id = v.id), ''),
+        COALESCE(v.notes, '')
+    FROM videos v INNER JOIN video_search_keys k ON k.video_id = v.id WHERE {condition};
+"#;
+
+/// Triggers created by step 4 that keep the index in sync, as `(name, event, condition)`
+const SEARCH_TRIGGERS: &[(&str, &str, &str)] = &[
+    ("videos_search_insert", "AFTER INSERT ON videos", "v.id = NEW.id"),
+    ("videos_search_update", "AFTER UPDATE OF filename, path, notes ON videos", "v.id = NEW.id"),
+    ("video_tags_search_insert", "AFTER INSERT ON video_tags", "v.id = NEW.video_id"),
+    ("video_tags_search_delete", "AFTER DELETE ON video_tags", "v.id = OLD.video_id"),
+    ("video_participants_search_insert", "AFTER INSERT ON video_participants", "v.id = NEW.video_id"),
+    ("video_participants_search_delete", "AFTER DELETE ON video_participants", "v.id = OLD.video_id"),
+    ("video_languages_search_insert", "AFTER INSERT ON video_languages", "v.id = NEW.video_id"),
+    ("video_languages_search_delete", "AFTER DELETE ON video_languages", "v.id = OLD.video_id"),
+    ("tags_search_update", "AFTER UPDATE OF name ON tags",
+        "v.id IN (SELECT video_id FROM video_tags WHERE tag_id = NEW.id)"),
+    ("tags_search_delete", "AFTER DELETE ON tags",
+        "v.id IN (SELECT video_id FROM video_tags WHERE tag_id = OLD.id)"),
+    ("participants_search_update", "AFTER UPDATE OF name ON participants",
+        "v.id IN (SELECT video_id FROM video_participants WHERE participant_id = NEW.id)"),
+    ("participants_search_delete", "AFTER DELETE ON participants",
+        "v.id IN (SELECT video_id FROM video_participants WHERE participant_id = OLD.id)"),
+    ("languages_search_update", "AFTER UPDATE OF name, code ON languages",
+        "v.id IN (SELECT video_id FROM video_languages WHERE language_id = NEW.id)"),
+    ("languages_search_delete", "AFTER DELETE ON languages",
+        "v.id IN (SELECT video_id FROM video_languages WHERE language_id = OLD.id)"),
+];
+
+fn search(tx: &Transaction) -> Result<()> {
+    add_column(tx, "videos", "notes", "TEXT")?;
+
+    // Index rows are keyed by `video_search_keys.id`: `videos` has a TEXT
+    // primary key, so VACUUM may renumber its rowids
+    let mut sql = String::from(r#"
+        CREATE VIRTUAL TABLE IF NOT EXISTS video_search USING fts5(
+            filename, path, tags, participants, languages, notes,
+            tokenize = 'unicode61 remove_diacritics 2'
+        );
+        CREATE VIRTUAL TABLE IF NOT EXISTS video_search_vocab USING fts5vocab(video_search, 'row');
+        CREATE TABLE IF NOT EXISTS video_search_keys (
+            id INTEGER PRIMARY KEY,
+            video_id TEXT NOT NULL UNIQUE
+        );
+
+        CREATE TRIGGER IF NOT EXISTS videos_search_delete AFTER DELETE ON videos BEGIN
+            DELETE FROM video_search WHERE rowid IN (SELECT id FROM video_search_keys WHERE video_id = OLD.id);
+            DELETE FROM video_search_keys WHERE video_id = OLD.id;
+        END;
+    "#);
+    for (name, event, condition) in SEARCH_TRIGGERS {
+        sql.push_str(&format!(
+            "CREATE TRIGGER IF NOT EXISTS {} {} BEGIN {} END;\n",
+            name, event, SEARCH_REFRESH.replace("{condition}", condition)
+        ));
+    }
+    // Index the videos that were there before
+    sql.push_str(&SEARCH_REFRESH.replace("{condition}", "1"));
+
+    tx.execute_batch(&sql)
+}
+
+fn smart_collections(tx: &Transaction) -> Result<()> {
+    tx.execute_batch(r#"
+        -- Saved filters shown in the sidebar
+        CREATE TABLE IF NOT EXISTS smart_collections (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            filter TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        );
+    "#)
+}
+
+fn playlists(tx: &Transaction) -> Result<()> {
+    tx.execute_batch(r#"
+        -- Manually ordered playlists
+        CREATE TABLE IF NOT EXISTS playlists (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS playlist_items (
+            id TEXT PRIMARY KEY,
+            playlist_id TEXT NOT NULL,
+            video_id TEXT NOT NULL,
+            position INTEGER NOT NULL,
+            FOREIGN KEY (playlist_id) REFERENCES playlists(id) ON DELETE CASCADE,
+            FOREIGN KEY (video_id) REFERENCES videos(id) ON DELETE CASCADE
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_playlist_items_playlist ON playlist_items(playlist_id, position);
+
+        -- Persisted "up next" queue
+        CREATE TABLE IF NOT EXISTS play_queue (
+            position INTEGER PRIMARY KEY,
+            video_id TEXT NOT NULL,
+            FOREIGN KEY (video_id) REFERENCES videos(id) ON DELETE CASCADE
+        );
+    "#)
+}
+
+fn fingerprints(tx: &Transaction) -> Result<()> {
+    for (column, definition) in [
+        ("partial_hash", "TEXT"),
+        ("content_hash", "TEXT"),
+        ("hashed_at", "TEXT"),
+        ("inode", "INTEGER"),
+    ] {
+        add_column(tx, "videos", column, definition)?;
+    }
+
+    tx.execute_batch(r#"
+        CREATE INDEX IF NOT EXISTS idx_videos_partial_hash ON videos(partial_hash);
+        CREATE INDEX IF NOT EXISTS idx_videos_content_hash ON videos(content_hash);
+        CREATE INDEX IF NOT EXISTS idx_videos_size ON videos(size);
+    "#)
+}
+
+fn availability(tx: &Transaction) -> Result<()> {
+    add_column(tx, "videos", "availability", "TEXT NOT NULL DEFAULT 'online'")?;
+    // Device id seen by the last scan, to recognize the empty mount point of an unmounted volume
+    add_column(tx, "mounted_folders", "device_id", "INTEGER")?;
+    tx.execute_batch("CREATE INDEX IF NOT EXISTS idx_videos_availability ON videos(availability);")
+}
+
+fn foreign_keys(tx: &Transaction) -> Result<()> {
+    tx.execute_batch(r#"
+        DELETE FROM video_tags
+            WHERE video_id NOT IN (SELECT id FROM videos) OR tag_id NOT IN (SELECT id FROM tags);
+        DELETE FROM video_participants
+            WHERE video_id NOT IN (SELECT id FROM videos) OR participant_id NOT IN (SELECT id FROM participants);
+        DELETE FROM video_languages
+            WHERE video_id NOT IN (SELECT id FROM videos) OR language_id NOT IN (SELECT id FROM languages);
+        DELETE FROM playback_history WHERE video_id NOT IN (SELECT id FROM videos);
+        DELETE FROM playback_sessions WHERE video_id NOT IN (SELECT id FROM videos);
+        DELETE FROM playlist_items
+            WHERE video_id NOT IN (SELECT id FROM videos) OR playlist_id NOT IN (SELECT id FROM playlists);
+        DELETE FROM play_queue WHERE video_id NOT IN (SELECT id FROM videos);
+
+        -- Cascading deletes look children up by their parent column
+        CREATE INDEX IF NOT EXISTS idx_video_tags_tag ON video_tags(tag_id);
+        CREATE INDEX IF NOT EXISTS idx_video_participants_participant ON video_participants(participant_id);
+        CREATE INDEX IF NOT EXISTS idx_video_languages_language ON video_languages(language_id);
+        CREATE INDEX IF NOT EXISTS idx_playback_sessions_video ON playback_sessions(video_id);
+        CREATE INDEX IF NOT EXISTS idx_playlist_items_video ON playlist_items(video_id);
+        CREATE INDEX IF NOT EXISTS idx_play_queue_video ON play_queue(video_id);
+    "#)
+}
+
+fn scan_rules(tx: &Transaction) -> Result<()> {
+    // JSON-encoded `ScanRules`; NULL means the defaults
+    add_column(tx, "mounted_folders", "scan_rules", "TEXT")
+}
+
+fn scan_reports(tx: &Transaction) -> Result<()> {
+    tx.execute_batch(r#"
+        CREATE TABLE IF NOT EXISTS scan_reports (
+            folder_path TEXT PRIMARY KEY REFERENCES mounted_folders(path) ON DELETE CASCADE,
+            started_at TEXT NOT NULL,
+            duration_ms INTEGER NOT NULL,
+            status TEXT NOT NULL,
+            error TEXT,
+            total_videos INTEGER NOT NULL DEFAULT 0,
+            new_videos INTEGER NOT NULL DEFAULT 0,
+            updated_videos INTEGER NOT NULL DEFAULT 0,
+            removed_videos INTEGER NOT NULL DEFAULT 0,
+            relinked_videos INTEGER NOT NULL DEFAULT 0,
+            -- JSON array of `ScanError`
+            errors TEXT NOT NULL DEFAULT '[]'
+        );
+    "#)
+}
+
+fn series(tx: &Transaction) -> Result<()> {
+    // Filled from video file names on startup and after every scan
+    tx.execute_batch(r#"
+        CREATE TABLE IF NOT EXISTS series (
+            id TEXT PRIMARY KEY,
+            title TEXT NOT NULL,
+            -- Lowercased title words, shared by every spelling of the name
+            match_key TEXT NOT NULL UNIQUE,
+            year INTEGER,
+            created_at TEXT NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS episodes (
+            video_id TEXT PRIMARY KEY REFERENCES videos(id) ON DELETE CASCADE,
+            series_id TEXT NOT NULL REFERENCES series(id) ON DELETE CASCADE,
+            season INTEGER NOT NULL,
+            episode INTEGER NOT NULL,
+            episode_end INTEGER,
+            resolution TEXT,
+            release_group TEXT
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_episodes_series ON episodes(series_id, season, episode);
+    "#)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A database as written by the first release, with some library data
+    const V1_FIXTURE: &str = include_str!("../tests/fixtures/database_v1.sql");
+
+    /// Path of a database file in an empty directory, which is removed along
+    /// with any backups when this is dropped
+    struct TempDb(PathBuf);
+
+    impl Drop for TempDb {
+        fn drop(&mut self) {
+            if let Some(dir) = self.0.parent() {
+                let _ = std::fs::remove_dir_all(dir);
+            }
+        }
+    }
+
+    impl std::ops::Deref for TempDb {
+        type Target = Path;
+
+        fn deref(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    impl AsRef<Path> for TempDb {
+        fn as_ref(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    fn temp_db(name: &str) -> TempDb {
+        let dir = std::env::temp_dir().join(format!("videoplayer-migrations-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        TempDb(dir.join("database.sqlite"))
+    }
+
+    fn user_version(conn: &Connection) -> u32 {
+        conn.query_row("PRAGMA user_version", [], |row| row.get(0)).unwrap()
+    }
+
+    fn count(conn: &Connection, sql: &str) -> i64 {
+        conn.query_row(sql, [], |row| row.get(0)).unwrap()
+    }
+
+    #[test]
+    fn migrates_v1_fixture_to_latest() {
+        let path = temp_db("v1");
+        let mut conn = Connection::open(&path).unwrap();
+        conn.execute_batch(V1_FIXTURE).unwrap();
+        assert_eq!(user_version(&conn), 1);
+
+        migrate(&mut conn, &path).unwrap();
+        assert_eq!(user_version(&conn), latest_version());
+
+        // Existing rows survive and pick up the defaults of new columns
+        assert_eq!(count(&conn, "SELECT COUNT(*) FROM videos"), 2);
+        assert_eq!(count(&conn, "SELECT COUNT(*) FROM video_tags"), 2);
+        assert_eq!(count(&conn, "SELECT COUNT(*) FROM videos WHERE availability = 'online'"), 2);
+        let (position, play_count, completed): (f64, i64, bool) = conn.query_row(
+            "SELECT position, play_count, completed FROM playback_history WHERE video_id = 'video-1'",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        ).unwrap();
+        assert_eq!((position, play_count, completed), (42.5, 0, false));
+
+        // Tables added by later steps exist and are usable
+        for table in ["settings", "playback_sessions", "smart_collections", "playlists", "playlist_items", "play_queue"] {
+            count(&conn, &format!("SELECT COUNT(*) FROM {}", table));
+        }
+
+        // The search index covers videos that existed before it
+        assert_eq!(count(&conn, "SELECT COUNT(*) FROM video_search WHERE video_search MATCH 'holiday'"), 1);
+        assert_eq!(count(&conn, "SELECT COUNT(*) FROM video_search WHERE video_search MATCH 'tags:favorite'"), 2);
+    }
+
+    #[test]
+    fn backs_up_before_migrating() {
+        let path = temp_db("backup");
+        let mut conn = Connection::open(&path).unwrap();
+        conn.execute_batch(V1_FIXTURE).unwrap();
+        migrate(&mut conn, &path).unwrap();
+
+        let backup = Connection::open(backup_path(&path, 1)).unwrap();
+        assert_eq!(user_version(&backup), 1);
+        assert_eq!(count(&backup, "SELECT COUNT(*) FROM videos"), 2);
+        assert_eq!(count(&backup, "SELECT COUNT(*) FROM sqlite_master WHERE name = 'settings'"), 0);
+    }
+
+    #[test]
+    fn new_database_starts_at_latest_without_backup() {
+        let path = temp_db("fresh");
+        let mut conn = Connection::open(&path).unwrap();
+        migrate(&mut conn, &path).unwrap();
+
+        assert_eq!(user_version(&conn), latest_version());
+        assert!(!backup_path(&path, 0).exists());
+
+        // Running again is a no-op
+        migrate(&mut conn, &path).unwrap();
+        assert_eq!(user_version(&conn), latest_version());
+    }
+
+    #[test]
+    fn migrates_unversioned_database_with_some_columns_added() {
+        // Before versioning, columns were added at startup as features shipped
+        let path = temp_db("unversioned");
+        let mut conn = Connection::open(&path).unwrap();
+        conn.execute_batch(V1_FIXTURE).unwrap();
+        conn.execute_batch(r#"
+            PRAGMA user_version = 0;
+            ALTER TABLE videos ADD COLUMN mtime INTEGER;
+            ALTER TABLE videos ADD COLUMN notes TEXT;
+            ALTER TABLE playback_history ADD COLUMN completed INTEGER NOT NULL DEFAULT 0;
+            UPDATE videos SET notes = 'from the beach' WHERE id = 'video-2';
+        "#).unwrap();
+
+        migrate(&mut conn, &path).unwrap();
+        assert_eq!(user_version(&conn), latest_version());
+        assert!(backup_path(&path, 0).exists());
+        assert_eq!(count(&conn, "SELECT COUNT(*) FROM video_search WHERE video_search MATCH 'beach'"), 1);
+    }
+
+    #[test]
+    fn refuses_database_from_newer_version() {
+        let path = temp_db("newer");
+        let mut conn = Connection::open(&path).unwrap();
+        conn.pragma_update(None, "user_version", latest_version() + 1).unwrap();
+
+        assert!(migrate(&mut conn, &path).is_err());
+    }
+}
@@ -9,8 +9,47 @@ pub struct Video {
     pub size: u64,
     pub duration: Option<f64>,
     pub thumbnail_path: Option<String>,
+    /// File modification time in seconds since the Unix epoch
+    pub mtime: Option<i64>,
     pub created_at: String,
     pub updated_at: String,
+    #[serde(default)]
+    pub availability: Availability,
+}
+
+/// Whether a video's file can currently be reached
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Availability {
+    #[default]
+    Online,
+    /// The file is gone although its mounted folder is reachable
+    Missing,
+    /// The mounted folder itself is unreachable, e.g. an unplugged drive
+    OfflineVolume,
+    /// The file is there but the folder's scan rules or depth now skip it.
+    /// Its metadata is kept in case the rules change back.
+    Excluded,
+}
+
+impl Availability {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Availability::Online => "online",
+            Availability::Missing => "missing",
+            Availability::OfflineVolume => "offline_volume",
+            Availability::Excluded => "excluded",
+        }
+    }
+
+    pub fn parse(value: &str) -> Self {
+        match value {
+            "missing" => Availability::Missing,
+            "offline_volume" => Availability::OfflineVolume,
+            "excluded" => Availability::Excluded,
+            _ => Availability::Online,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -40,6 +79,52 @@ pub struct MountedFolder {
     pub name: String,
     pub scan_depth: usize,
     pub created_at: String,
+    #[serde(default)]
+    pub scan_rules: ScanRules,
+}
+
+/// Which files a scan of a mounted folder picks up
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScanRules {
+    /// Extensions scanned on top of the built-in ones, e.g. `ts` or `m2ts`
+    pub extra_extensions: Vec<String>,
+    /// When set, only videos matching one of these globs are scanned
+    pub include_patterns: Vec<String>,
+    /// Files and folders matching these globs are skipped
+    pub exclude_patterns: Vec<String>,
+    /// Smaller videos are skipped, e.g. samples; bytes
+    pub min_file_size: u64,
+    pub follow_symlinks: bool,
+    pub include_hidden: bool,
+}
+
+impl Default for ScanRules {
+    fn default() -> Self {
+        ScanRules {
+            extra_extensions: Vec::new(),
+            include_patterns: Vec::new(),
+            exclude_patterns: vec!["node_modules".to_string(), "Library".to_string()],
+            min_file_size: 0,
+            follow_symlinks: false,
+            include_hidden: false,
+        }
+    }
+}
+
+/// Stream information probed from the media file itself
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MediaInfo {
+    /// Duration in seconds
+    pub duration: Option<f64>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub video_codec: Option<String>,
+    pub audio_codec: Option<String>,
+    /// Overall bitrate in bits per second
+    pub bitrate: Option<u64>,
+    pub frame_rate: Option<f64>,
+    pub audio_tracks: u32,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -48,6 +133,36 @@ pub struct VideoWithMetadata {
     pub tags: Vec<Tag>,
     pub participants: Vec<Participant>,
     pub languages: Vec<Language>,
+    /// `None` until the file has been probed
+    pub media_info: Option<MediaInfo>,
+    pub notes: Option<String>,
+}
+
+/// Outcome of `check_database`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatabaseCheck {
+    /// Problems reported by SQLite's integrity and foreign key checks
+    pub problems: Vec<String>,
+    pub orphans_removed: usize,
+}
+
+/// Videos whose files have identical content
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateGroup {
+    pub content_hash: String,
+    pub size: u64,
+    pub videos: Vec<VideoWithMetadata>,
+}
+
+/// What happens to the other copies when a duplicate group is resolved
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicateAction {
+    /// Move tags, participants, languages and history to the kept video;
+    /// the other files stay in the library
+    Merge,
+    /// Merge, then move the other files to the trash and drop them from the library
+    Trash,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -55,15 +170,263 @@ pub struct FolderNode {
     pub path: String,
     pub name: String,
     pub children: Vec<FolderNode>,
+    /// Videos in this folder and every folder below it
     pub video_count: usize,
+    #[serde(default)]
+    pub total_size: u64,
+    /// Seconds; videos that haven't been probed yet count as zero
+    #[serde(default)]
+    pub total_duration: f64,
+    /// Whether the folder has subfolders with videos, loaded into `children` or not
+    #[serde(default)]
+    pub has_children: bool,
+}
+
+/// A stored video whose file was found under a new path by a scan
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RelinkedVideo {
+    pub video_id: String,
+    pub old_path: String,
+    pub new_path: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ScanResult {
     pub total_videos: usize,
     pub new_videos: usize,
+    pub updated_videos: usize,
+    pub removed_videos: usize,
+    /// Files moved or renamed outside the app since the last scan
+    pub relinked: Vec<RelinkedVideo>,
     pub folders: Vec<FolderNode>,
     pub videos: Vec<Video>,
+    /// Files and directories that couldn't be read; their videos are left as they were
+    #[serde(default)]
+    pub errors: Vec<ScanError>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanError {
+    pub path: String,
+    pub kind: ScanErrorKind,
+    pub message: String,
+    /// Raw error code from the OS (`errno` on Unix), if it reported one
+    pub os_error: Option<i32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScanErrorKind {
+    /// A directory couldn't be listed
+    ReadDir,
+    /// Listing a directory took longer than the scanner waits for
+    Timeout,
+    /// A file's size, type or modification time couldn't be read
+    Metadata,
+    /// A symlink couldn't be resolved
+    Symlink,
+    /// A name that isn't valid UTF-8, which the library can't store
+    InvalidName,
+}
+
+/// Summary of the most recent scan of a mounted folder
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanReport {
+    pub folder_path: String,
+    pub started_at: String,
+    pub duration_ms: u64,
+    pub status: ScanReportStatus,
+    /// Why a failed scan failed
+    pub error: Option<String>,
+    pub total_videos: usize,
+    pub new_videos: usize,
+    pub updated_videos: usize,
+    pub removed_videos: usize,
+    pub relinked_videos: usize,
+    pub errors: Vec<ScanError>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScanReportStatus {
+    Completed,
+    Cancelled,
+    Failed,
+}
+
+impl ScanReportStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ScanReportStatus::Completed => "completed",
+            ScanReportStatus::Cancelled => "cancelled",
+            ScanReportStatus::Failed => "failed",
+        }
+    }
+
+    pub fn parse(value: &str) -> Self {
+        match value {
+            "cancelled" => ScanReportStatus::Cancelled,
+            "failed" => ScanReportStatus::Failed,
+            _ => ScanReportStatus::Completed,
+        }
+    }
+}
+
+/// A folder scan queued or running in the background
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanJob {
+    pub id: String,
+    pub folder_path: String,
+    pub status: ScanJobStatus,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScanJobStatus {
+    Queued,
+    Running,
+}
+
+/// Payload of the `scan-progress` event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanProgress {
+    pub job_id: String,
+    pub folder_path: String,
+    pub dirs_visited: usize,
+    pub files_found: usize,
+    /// Directory being read
+    pub current_path: String,
+}
+
+/// Payload of the `scan-finished` event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanFinished {
+    pub job_id: String,
+    pub folder_path: String,
+    #[serde(flatten)]
+    pub outcome: ScanOutcome,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum ScanOutcome {
+    Completed { result: ScanResult },
+    Cancelled,
+    Failed { error: String },
+}
+
+/// Payload of the `library-changed` event emitted by the folder watcher
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LibraryChangedEvent {
+    pub folder_path: String,
+    pub paths: Vec<String>,
+}
+
+/// Playback state of the external mpv player, mirrored over IPC
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MpvState {
+    pub running: bool,
+    pub path: Option<String>,
+    /// Current position in seconds
+    pub time_pos: Option<f64>,
+    pub duration: Option<f64>,
+    pub paused: bool,
+    pub eof_reached: bool,
+    pub volume: Option<f64>,
+    /// Index into mpv's internal playlist
+    pub playlist_pos: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlaybackHistory {
+    pub video_id: String,
+    /// Resume position in seconds
+    pub position: f64,
+    pub completed: bool,
+    pub play_count: u32,
+    /// Seconds spent actually playing, summed over all sessions
+    pub total_watch_time: f64,
+    pub first_played: Option<String>,
+    pub last_played: String,
+}
+
+/// One viewing of a video, from opening it to closing it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlaybackSession {
+    pub id: String,
+    pub video_id: String,
+    pub started_at: String,
+    pub ended_at: Option<String>,
+    pub start_position: f64,
+    pub end_position: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchHistoryEntry {
+    pub session: PlaybackSession,
+    pub video: Video,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VideoWithHistory {
+    pub video: Video,
+    pub history: PlaybackHistory,
+}
+
+/// Inclusive RFC 3339 bounds; `None` leaves that side open
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DateRange {
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
+/// How a list of selected tags (participants, languages) is combined
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchMode {
+    /// Videos with at least one of them
+    #[default]
+    Any,
+    /// Videos with all of them
+    All,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WatchState {
+    /// Played to the end at least once
+    Watched,
+    /// Never played to the end
+    Unwatched,
+    /// Started but not finished
+    InProgress,
+}
+
+/// Autocomplete candidate for the search box
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchSuggestion {
+    pub text: String,
+    /// "tag", "participant", "language" or "term" (a word from filenames, paths or notes)
+    pub kind: String,
+}
+
+/// Payload of the `playback-progress` event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlaybackProgress {
+    pub video_id: String,
+    pub position: f64,
+    pub duration: Option<f64>,
+    pub paused: bool,
+    pub completed: bool,
+}
+
+/// Payload of the `thumbnail-progress` event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThumbnailProgress {
+    pub video_id: String,
+    pub thumbnail_path: Option<String>,
+    pub completed: usize,
+    pub total: usize,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -72,7 +435,24 @@ pub struct FilterOptions {
     pub tag_ids: Vec<String>,
     pub participant_ids: Vec<String>,
     pub language_ids: Vec<String>,
+    #[serde(default)]
+    pub tag_match: MatchMode,
+    #[serde(default)]
+    pub exclude_tag_ids: Vec<String>,
+    #[serde(default)]
+    pub participant_match: MatchMode,
+    #[serde(default)]
+    pub exclude_participant_ids: Vec<String>,
+    #[serde(default)]
+    pub language_match: MatchMode,
+    #[serde(default)]
+    pub exclude_language_ids: Vec<String>,
     pub search_query: Option<String>,
+    #[serde(default)]
+    pub watch_state: Option<WatchState>,
+    /// Expression in the library query language, see `query.rs`
+    #[serde(default)]
+    pub query: Option<String>,
     pub sort_by: String,
     pub sort_order: String,
     pub limit: usize,
@@ -86,7 +466,15 @@ impl Default for FilterOptions {
             tag_ids: Vec::new(),
             participant_ids: Vec::new(),
             language_ids: Vec::new(),
+            tag_match: MatchMode::Any,
+            exclude_tag_ids: Vec::new(),
+            participant_match: MatchMode::Any,
+            exclude_participant_ids: Vec::new(),
+            language_match: MatchMode::Any,
+            exclude_language_ids: Vec::new(),
             search_query: None,
+            watch_state: None,
+            query: None,
             sort_by: "filename".to_string(),
             sort_order: "asc".to_string(),
             limit: 100,
@@ -95,6 +483,84 @@ impl Default for FilterOptions {
     }
 }
 
+/// A named filter, evaluated whenever it is opened
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SmartCollection {
+    pub id: String,
+    pub name: String,
+    /// Filter and sort order; `limit` and `offset` are supplied by the caller
+    pub filter: FilterOptions,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SidebarCollection {
+    pub id: String,
+    pub name: String,
+    pub video_count: usize,
+}
+
+/// Everything the sidebar lists: mounted folders and smart collections
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SidebarData {
+    pub folders: Vec<MountedFolder>,
+    pub collections: Vec<SidebarCollection>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Playlist {
+    pub id: String,
+    pub name: String,
+    pub item_count: usize,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlaylistItem {
+    pub id: String,
+    pub playlist_id: String,
+    /// Zero-based index in play order
+    pub position: usize,
+    pub video: Video,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlaylistImport {
+    pub playlist: Playlist,
+    /// Entries of the M3U file that aren't in the library
+    pub missing: Vec<String>,
+}
+
+/// The playlist item mpv is currently playing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlaylistPlayback {
+    pub playlist_id: String,
+    pub index: usize,
+    pub item_id: String,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RepeatMode {
+    #[default]
+    Off,
+    One,
+    All,
+}
+
+/// Payload of the `queue-changed` event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueueState {
+    pub items: Vec<Video>,
+    /// Index of the item playing or last played
+    pub current: Option<usize>,
+    pub repeat: RepeatMode,
+    /// Whether mpv is currently playing from the queue
+    pub playing: bool,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PaginatedVideos {
     pub videos: Vec<Video>,
@@ -102,3 +568,40 @@ pub struct PaginatedVideos {
     pub has_more: bool,
 }
 
+
+/// A TV series made up of the episodes found in video file names
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Series {
+    pub id: String,
+    pub title: String,
+    pub year: Option<u32>,
+    pub season_count: usize,
+    /// Episode files; a multi-episode file counts once
+    pub episode_count: usize,
+    pub watched_count: usize,
+    pub last_played: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Season {
+    pub season: u32,
+    pub episode_count: usize,
+    pub watched_count: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Episode {
+    pub video: Video,
+    pub series_id: String,
+    pub season: u32,
+    pub episode: u32,
+    /// Last episode of a file holding several, e.g. 2 for `S01E01E02`
+    pub episode_end: Option<u32>,
+    pub resolution: Option<String>,
+    pub release_group: Option<String>,
+    /// Whether the episode's file can be played; missing and offline
+    /// episodes are still listed
+    pub availability: Availability,
+    /// `None` for episodes that were never played
+    pub history: Option<PlaybackHistory>,
+}
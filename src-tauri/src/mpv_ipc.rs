@@ -0,0 +1,285 @@
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use serde_json::{json, Value};
+use crate::models::MpvState;
+
+#[cfg(unix)]
+type IpcStream = std::os::unix::net::UnixStream;
+#[cfg(windows)]
+type IpcStream = std::fs::File;
+
+/// Properties mirrored into `MpvState` via `observe_property`
+const OBSERVED_PROPERTIES: &[&str] = &["path", "time-pos", "duration", "pause", "eof-reached", "volume", "playlist-pos"];
+
+/// How long to wait for mpv to create its socket after launch
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long to wait for the reply to a single command
+const REPLY_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How often the pipe thread looks for replies while no command is waiting to be sent
+#[cfg(windows)]
+const PIPE_POLL: Duration = Duration::from_millis(20);
+
+type Pending = Arc<Mutex<HashMap<u64, Sender<Value>>>>;
+
+/// Sends command lines to mpv. On Windows the pipe is owned by a single
+/// thread, since synchronous I/O on a pipe handle is serialized: a write
+/// would wait for a blocked read on a cloned handle to finish.
+#[cfg(unix)]
+type Outgoing = IpcStream;
+#[cfg(windows)]
+type Outgoing = Sender<String>;
+
+/// Location of the IPC socket (a named pipe on Windows) for this app instance
+pub fn socket_path() -> String {
+    let name = format!("videoplayer-mpv-{}", std::process::id());
+    if cfg!(windows) {
+        format!("\\\\.\\pipe\\{}", name)
+    } else {
+        std::env::temp_dir().join(format!("{}.sock", name)).to_string_lossy().to_string()
+    }
+}
+
+fn open_stream(path: &str) -> io::Result<IpcStream> {
+    #[cfg(unix)]
+    {
+        std::os::unix::net::UnixStream::connect(path)
+    }
+    #[cfg(windows)]
+    {
+        std::fs::OpenOptions::new().read(true).write(true).open(path)
+    }
+}
+
+/// Client for mpv's JSON IPC protocol.
+///
+/// Commands are matched to their replies by `request_id`; observed property
+/// changes are folded into a shared `MpvState` by a reader thread.
+pub struct MpvIpc {
+    writer: Mutex<Outgoing>,
+    next_request_id: AtomicU64,
+    pending: Pending,
+    state: Arc<Mutex<MpvState>>,
+}
+
+impl MpvIpc {
+    /// Connect to a freshly launched mpv, retrying until its socket appears
+    pub fn connect(socket_path: &str) -> Result<Self, String> {
+        let deadline = Instant::now() + CONNECT_TIMEOUT;
+        let stream = loop {
+            match open_stream(socket_path) {
+                Ok(stream) => break stream,
+                Err(e) if Instant::now() >= deadline => {
+                    return Err(format!("Failed to connect to mpv: {}", e));
+                }
+                Err(_) => thread::sleep(Duration::from_millis(50)),
+            }
+        };
+        let pending: Pending = Arc::new(Mutex::new(HashMap::new()));
+        let state = Arc::new(Mutex::new(MpvState {
+            running: true,
+            ..Default::default()
+        }));
+
+        #[cfg(unix)]
+        let writer = {
+            let reader = stream.try_clone().map_err(|e| e.to_string())?;
+            let pending = Arc::clone(&pending);
+            let state = Arc::clone(&state);
+            thread::spawn(move || read_messages(reader, pending, state));
+            stream
+        };
+        #[cfg(windows)]
+        let writer = {
+            let (sender, outgoing) = mpsc::channel();
+            let pending = Arc::clone(&pending);
+            let state = Arc::clone(&state);
+            thread::spawn(move || run_pipe(stream, outgoing, pending, state));
+            sender
+        };
+
+        let ipc = MpvIpc {
+            writer: Mutex::new(writer),
+            next_request_id: AtomicU64::new(1),
+            pending,
+            state,
+        };
+
+        for (id, name) in OBSERVED_PROPERTIES.iter().enumerate() {
+            ipc.command(json!(["observe_property", id + 1, name]))?;
+        }
+
+        Ok(ipc)
+    }
+
+    /// Send a command such as `["seek", 10, "relative"]` and wait for its result
+    pub fn command(&self, args: Value) -> Result<Value, String> {
+        let request_id = self.next_request_id.fetch_add(1, Ordering::Relaxed);
+        let (sender, receiver) = mpsc::channel();
+        self.pending.lock().unwrap().insert(request_id, sender);
+
+        let mut line = json!({ "command": args, "request_id": request_id }).to_string();
+        line.push('\n');
+
+        let written = send_line(&mut self.writer.lock().unwrap(), line);
+        let reply = match written {
+            Ok(()) => receiver.recv_timeout(REPLY_TIMEOUT).ok(),
+            Err(_) => None,
+        };
+        self.pending.lock().unwrap().remove(&request_id);
+
+        let reply = reply.ok_or("mpv is not responding")?;
+        match reply["error"].as_str() {
+            Some("success") => Ok(reply["data"].clone()),
+            Some(error) => Err(format!("mpv: {}", error)),
+            None => Err("Malformed reply from mpv".to_string()),
+        }
+    }
+
+    pub fn set_property(&self, name: &str, value: Value) -> Result<(), String> {
+        self.command(json!(["set_property", name, value])).map(|_| ())
+    }
+
+    /// Latest values of the observed properties
+    pub fn state(&self) -> MpvState {
+        self.state.lock().unwrap().clone()
+    }
+}
+
+#[cfg(unix)]
+fn send_line(stream: &mut Outgoing, line: String) -> io::Result<()> {
+    stream.write_all(line.as_bytes())
+}
+
+#[cfg(windows)]
+fn send_line(sender: &mut Outgoing, line: String) -> io::Result<()> {
+    sender.send(line).map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe))
+}
+
+#[cfg(unix)]
+fn read_messages(reader: IpcStream, pending: Pending, state: Arc<Mutex<MpvState>>) {
+    use std::io::{BufRead, BufReader};
+
+    for line in BufReader::new(reader).lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        handle_message(&line, &pending, &state);
+    }
+    disconnected(&pending, &state);
+}
+
+/// Own the pipe: send queued commands and read whatever mpv has written,
+/// without ever blocking in a read
+#[cfg(windows)]
+fn run_pipe(mut pipe: IpcStream, outgoing: mpsc::Receiver<String>, pending: Pending, state: Arc<Mutex<MpvState>>) {
+    use std::io::Read;
+    use std::sync::mpsc::RecvTimeoutError;
+
+    let mut buffer: Vec<u8> = Vec::new();
+    let mut chunk = [0u8; 4096];
+    loop {
+        // Commands go out first so a stream of property changes can't hold them up
+        let mut next = outgoing.try_recv().ok();
+        let available = match bytes_available(&pipe) {
+            Ok(available) => available,
+            Err(_) => break,
+        };
+        if next.is_none() && available == 0 {
+            match outgoing.recv_timeout(PIPE_POLL) {
+                Ok(line) => next = Some(line),
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+        }
+        if let Some(line) = next {
+            if pipe.write_all(line.as_bytes()).is_err() {
+                break;
+            }
+        }
+        if available == 0 {
+            continue;
+        }
+
+        let wanted = (available as usize).min(chunk.len());
+        match pipe.read(&mut chunk[..wanted]) {
+            Ok(0) | Err(_) => break,
+            Ok(read) => buffer.extend_from_slice(&chunk[..read]),
+        }
+        while let Some(end) = buffer.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = buffer.drain(..=end).collect();
+            handle_message(&String::from_utf8_lossy(&line), &pending, &state);
+        }
+    }
+    disconnected(&pending, &state);
+}
+
+/// Bytes waiting in the pipe; fails once mpv has closed its end
+#[cfg(windows)]
+fn bytes_available(pipe: &IpcStream) -> io::Result<u32> {
+    use std::os::windows::io::AsRawHandle;
+    use windows_sys::Win32::System::Pipes::PeekNamedPipe;
+
+    let mut available: u32 = 0;
+    // SAFETY: the handle is open for as long as `pipe` lives and no buffer is passed
+    let ok = unsafe {
+        PeekNamedPipe(
+            pipe.as_raw_handle(),
+            std::ptr::null_mut(),
+            0,
+            std::ptr::null_mut(),
+            &mut available,
+            std::ptr::null_mut(),
+        )
+    };
+    if ok == 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(available)
+    }
+}
+
+fn handle_message(line: &str, pending: &Pending, state: &Mutex<MpvState>) {
+    let Ok(message) = serde_json::from_str::<Value>(line) else {
+        return;
+    };
+
+    if let Some(request_id) = message["request_id"].as_u64() {
+        if let Some(sender) = pending.lock().unwrap().remove(&request_id) {
+            let _ = sender.send(message);
+        }
+        return;
+    }
+
+    if message["event"] == "property-change" {
+        if let Some(name) = message["name"].as_str() {
+            apply_property(&mut state.lock().unwrap(), name, &message["data"]);
+        }
+    }
+}
+
+/// The connection closed: mpv has exited
+fn disconnected(pending: &Pending, state: &Mutex<MpvState>) {
+    state.lock().unwrap().running = false;
+    pending.lock().unwrap().clear();
+}
+
+fn apply_property(state: &mut MpvState, name: &str, data: &Value) {
+    match name {
+        "path" => state.path = data.as_str().map(str::to_string),
+        "time-pos" => state.time_pos = data.as_f64(),
+        "duration" => state.duration = data.as_f64(),
+        "pause" => state.paused = data.as_bool().unwrap_or(false),
+        "eof-reached" => state.eof_reached = data.as_bool().unwrap_or(false),
+        "volume" => state.volume = data.as_f64(),
+        // -1 when nothing is playing
+        "playlist-pos" => state.playlist_pos = data.as_u64().map(|p| p as usize),
+        _ => {}
+    }
+}
@@ -0,0 +1,151 @@
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+use crate::commands::AppState;
+use crate::models::{MpvState, PlaybackProgress};
+use crate::queue;
+
+/// Fraction of the duration after which a video counts as watched
+const COMPLETION_THRESHOLD: f64 = 0.95;
+
+/// How often mpv's state is sampled
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How often the position of a playing video is written to the database
+const SAVE_INTERVAL: Duration = Duration::from_secs(5);
+
+pub fn is_completed(position: f64, duration: Option<f64>) -> bool {
+    duration.map(|d| d > 0.0 && position >= d * COMPLETION_THRESHOLD).unwrap_or(false)
+}
+
+/// The video currently playing in mpv
+struct Session {
+    path: String,
+    video_id: Option<String>,
+    /// Row in `playback_sessions` for this viewing
+    session_id: Option<String>,
+    /// Seconds played since the last save
+    unsaved_watch_time: f64,
+    position: f64,
+    duration: Option<f64>,
+    paused: bool,
+    completed: bool,
+    last_saved: Instant,
+}
+
+/// Start the thread that persists mpv's playback position into `playback_history`
+/// and logs each viewing into `playback_sessions`. It also moves the play queue
+/// along when a file ends.
+pub fn spawn_tracker(app: AppHandle) {
+    thread::spawn(move || {
+        let mut session: Option<Session> = None;
+        let mut at_eof = false;
+        loop {
+            thread::sleep(POLL_INTERVAL);
+            let eof = track(&app, &mut session);
+            if eof && !at_eof {
+                queue::on_end_of_file(&app);
+            }
+            at_eof = eof;
+        }
+    });
+}
+
+/// Sample mpv and persist progress; returns whether mpv is at the end of a file
+fn track(app: &AppHandle, session: &mut Option<Session>) -> bool {
+    let state = app.state::<AppState>();
+    let snapshot: Option<MpvState> = match state.player.player.lock() {
+        Ok(mut player) => player.ipc().ok().map(|ipc| ipc.state()),
+        Err(_) => return false,
+    };
+
+    let Some(mpv) = snapshot.filter(|s| s.running && s.path.is_some()) else {
+        // mpv exited: keep where the user stopped
+        if let Some(mut finished) = session.take() {
+            save(app, &mut finished);
+            queue::on_player_exit(app, finished.completed);
+        }
+        return false;
+    };
+    let path = mpv.path.clone().unwrap_or_default();
+
+    // A different file was loaded into the same mpv instance
+    if session.as_ref().map(|s| s.path != path).unwrap_or(true) {
+        if let Some(mut previous) = session.take() {
+            save(app, &mut previous);
+        }
+        let start_position = mpv.time_pos.unwrap_or(0.0);
+        let video_id = state.db.get_video_by_path(&path).ok().flatten().map(|v| v.id);
+        let session_id = video_id.as_deref()
+            .and_then(|id| state.db.begin_playback_session(id, start_position).ok());
+        *session = Some(Session {
+            path,
+            video_id,
+            session_id,
+            unsaved_watch_time: 0.0,
+            position: 0.0,
+            duration: None,
+            paused: false,
+            completed: false,
+            last_saved: Instant::now(),
+        });
+    }
+
+    let Some(current) = session.as_mut() else {
+        return mpv.eof_reached;
+    };
+    let Some(video_id) = current.video_id.clone() else {
+        return mpv.eof_reached;
+    };
+
+    if !current.paused && !current.completed {
+        current.unsaved_watch_time += POLL_INTERVAL.as_secs_f64();
+    }
+    let pause_changed = current.paused != mpv.paused;
+    current.position = mpv.time_pos.unwrap_or(current.position);
+    current.duration = mpv.duration.or(current.duration);
+    current.paused = mpv.paused;
+
+    let now_completed = mpv.eof_reached || is_completed(current.position, current.duration);
+    let just_completed = now_completed && !current.completed;
+    current.completed = now_completed;
+
+    if just_completed || pause_changed || current.last_saved.elapsed() >= SAVE_INTERVAL {
+        save(app, current);
+        current.last_saved = Instant::now();
+    }
+
+    let _ = app.emit("playback-progress", PlaybackProgress {
+        video_id,
+        position: current.position,
+        duration: current.duration,
+        paused: current.paused,
+        completed: current.completed,
+    });
+    mpv.eof_reached
+}
+
+fn save(app: &AppHandle, session: &mut Session) {
+    let Some(video_id) = session.video_id.as_deref() else {
+        return;
+    };
+    let db = &app.state::<AppState>().db;
+
+    let result = if session.completed {
+        db.mark_playback_completed(video_id)
+    } else if session.position > 0.0 {
+        db.save_playback_position(video_id, session.position)
+    } else {
+        Ok(())
+    };
+    if let Err(e) = result {
+        log::error!("Failed to save playback position: {}", e);
+    }
+
+    if let Some(session_id) = session.session_id.as_deref() {
+        match db.update_playback_session(session_id, session.position, session.unsaved_watch_time) {
+            Ok(()) => session.unsaved_watch_time = 0.0,
+            Err(e) => log::error!("Failed to update playback session: {}", e),
+        }
+    }
+}
@@ -2,28 +2,21 @@ use std::path::{Path, PathBuf};
 use std::process::{Command, Child, Stdio};
 use std::sync::Mutex;
 use std::env;
+use crate::mpv_ipc::{self, MpvIpc};
+use crate::queue::PlayQueue;
 
 pub struct MpvPlayer {
     process: Option<Child>,
+    ipc: Option<MpvIpc>,
 }
 
 impl MpvPlayer {
     pub fn new() -> Self {
-        MpvPlayer { process: None }
+        MpvPlayer { process: None, ipc: None }
     }
 
     pub fn play(&mut self, video_path: &str, subtitle_path: Option<&str>, start_position: Option<f64>) -> Result<(), String> {
-        // Kill existing process if any
-        self.stop();
-
-        let mut args: Vec<String> = vec![
-            video_path.to_string(),
-            "--force-window=yes".to_string(),
-            "--keep-open=yes".to_string(),
-            "--osd-level=1".to_string(),
-            "--input-default-bindings=yes".to_string(),
-            "--input-vo-keyboard=yes".to_string(),
-        ];
+        let mut args: Vec<String> = vec![video_path.to_string()];
 
         // Add subtitle if provided
         if let Some(sub_path) = subtitle_path {
@@ -40,27 +33,35 @@ impl MpvPlayer {
             args.push(format!("--start={}", pos));
         }
 
-        // Try to find mpv executable (bundled first, then system)
-        let mpv_path = find_bundled_mpv()
-            .or_else(find_system_mpv)
-            .ok_or("mpv not found. The bundled mpv is missing and mpv is not installed on the system.")?;
+        self.launch(args)
+    }
 
-        // Set library path for bundled libs on macOS
-        let mut command = Command::new(&mpv_path);
-        
-        #[cfg(target_os = "macos")]
-        {
-            if let Some(libs_path) = get_bundled_libs_path() {
-                // Set DYLD_LIBRARY_PATH for macOS
-                let current_dyld = env::var("DYLD_LIBRARY_PATH").unwrap_or_default();
-                let new_dyld = if current_dyld.is_empty() {
-                    libs_path.to_string_lossy().to_string()
-                } else {
-                    format!("{}:{}", libs_path.to_string_lossy(), current_dyld)
-                };
-                command.env("DYLD_LIBRARY_PATH", new_dyld);
-            }
-        }
+    /// Play the files listed in an M3U file in order, starting with `start_index`
+    pub fn play_playlist(&mut self, playlist_file: &Path, start_index: usize) -> Result<(), String> {
+        self.launch(vec![
+            format!("--playlist={}", playlist_file.to_string_lossy()),
+            format!("--playlist-start={}", start_index),
+        ])
+    }
+
+    fn launch(&mut self, mut args: Vec<String>) -> Result<(), String> {
+        // Kill existing process if any
+        self.stop();
+
+        args.extend([
+            "--force-window=yes".to_string(),
+            "--keep-open=yes".to_string(),
+            "--osd-level=1".to_string(),
+            "--input-default-bindings=yes".to_string(),
+            "--input-vo-keyboard=yes".to_string(),
+        ]);
+
+        // Keep a control channel open so the app can act as a remote
+        let socket_path = mpv_ipc::socket_path();
+        args.push(format!("--input-ipc-server={}", socket_path));
+
+        let mut command = mpv_command()
+            .ok_or("mpv not found. The bundled mpv is missing and mpv is not installed on the system.")?;
 
         let child = command
             .args(&args)
@@ -71,10 +72,22 @@ impl MpvPlayer {
             .map_err(|e| format!("Failed to start mpv: {}", e))?;
 
         self.process = Some(child);
+
+        // Playback still works without IPC, only remote control is unavailable
+        self.ipc = MpvIpc::connect(&socket_path).ok();
         Ok(())
     }
 
+    /// IPC client of the running mpv instance
+    pub fn ipc(&mut self) -> Result<&MpvIpc, String> {
+        if !self.is_running() {
+            return Err("mpv is not running".to_string());
+        }
+        self.ipc.as_ref().ok_or_else(|| "mpv remote control is unavailable".to_string())
+    }
+
     pub fn stop(&mut self) {
+        self.ipc = None;
         if let Some(mut child) = self.process.take() {
             let _ = child.kill();
             let _ = child.wait();
@@ -86,6 +99,7 @@ impl MpvPlayer {
             match child.try_wait() {
                 Ok(Some(_)) => {
                     self.process = None;
+                    self.ipc = None;
                     false
                 }
                 Ok(None) => true,
@@ -103,6 +117,31 @@ impl Drop for MpvPlayer {
     }
 }
 
+/// Build a command for the mpv executable (bundled first, then system)
+pub fn mpv_command() -> Option<Command> {
+    let mpv_path = find_bundled_mpv().or_else(find_system_mpv)?;
+
+    // Set library path for bundled libs on macOS
+    #[cfg_attr(not(target_os = "macos"), allow(unused_mut))]
+    let mut command = Command::new(&mpv_path);
+    
+    #[cfg(target_os = "macos")]
+    {
+        if let Some(libs_path) = get_bundled_libs_path() {
+            // Set DYLD_LIBRARY_PATH for macOS
+            let current_dyld = env::var("DYLD_LIBRARY_PATH").unwrap_or_default();
+            let new_dyld = if current_dyld.is_empty() {
+                libs_path.to_string_lossy().to_string()
+            } else {
+                format!("{}:{}", libs_path.to_string_lossy(), current_dyld)
+            };
+            command.env("DYLD_LIBRARY_PATH", new_dyld);
+        }
+    }
+
+    Some(command)
+}
+
 /// Find bundled mpv executable
 fn find_bundled_mpv() -> Option<String> {
     // In development, check the binaries folder
@@ -290,14 +329,26 @@ fn find_subtitle_file(video_path: &str) -> Option<String> {
     None
 }
 
+/// Playlist handed to mpv by `play_playlist`, in mpv's order
+pub struct ActivePlaylist {
+    pub playlist_id: String,
+    pub item_ids: Vec<String>,
+}
+
+/// Locks are always taken in field order: `queue`, then `player`, then
+/// `playlist`. The playback tracker advances the queue while holding `queue`.
 pub struct PlayerState {
+    pub queue: Mutex<PlayQueue>,
     pub player: Mutex<MpvPlayer>,
+    pub playlist: Mutex<Option<ActivePlaylist>>,
 }
 
 impl PlayerState {
     pub fn new() -> Self {
         PlayerState {
+            queue: Mutex::new(PlayQueue::default()),
             player: Mutex::new(MpvPlayer::new()),
+            playlist: Mutex::new(None),
         }
     }
 }
@@ -0,0 +1,124 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use crate::models::Video;
+
+/// Contents of an M3U/M3U8 file
+pub struct M3uPlaylist {
+    /// From the `#PLAYLIST:` directive, if present
+    pub name: Option<String>,
+    /// Entries resolved to absolute paths, in order
+    pub paths: Vec<String>,
+}
+
+/// Location of the list handed to mpv by `play_playlist`
+pub fn temp_playlist_path() -> PathBuf {
+    std::env::temp_dir().join(format!("videoplayer-playlist-{}.m3u8", std::process::id()))
+}
+
+/// Write an extended M3U file (UTF-8) listing `videos` in order
+pub fn write_m3u(path: &Path, name: Option<&str>, videos: &[Video]) -> io::Result<()> {
+    let mut contents = String::from("#EXTM3U\n");
+    if let Some(name) = name {
+        contents.push_str(&format!("#PLAYLIST:{}\n", name));
+    }
+    for video in videos {
+        let duration = video.duration.map(|d| d.round() as i64).unwrap_or(-1);
+        contents.push_str(&format!("#EXTINF:{},{}\n{}\n", duration, video.filename, video.path));
+    }
+    fs::write(path, contents)
+}
+
+/// Read an M3U/M3U8 file; relative entries are resolved against its folder
+/// and URLs are skipped
+pub fn read_m3u(path: &Path) -> io::Result<M3uPlaylist> {
+    let bytes = fs::read(path)?;
+    let contents = String::from_utf8_lossy(&bytes);
+    let base = path.parent().unwrap_or(Path::new(""));
+
+    let mut name = None;
+    let mut paths = Vec::new();
+    for line in contents.lines() {
+        let line = line.trim().trim_start_matches('\u{feff}');
+        if let Some(playlist_name) = line.strip_prefix("#PLAYLIST:") {
+            name = Some(playlist_name.trim().to_string());
+        } else if line.is_empty() || line.starts_with('#') || line.contains("://") {
+            continue;
+        } else {
+            let entry = Path::new(line);
+            let resolved = if entry.is_absolute() { entry.to_path_buf() } else { base.join(entry) };
+            paths.push(resolved.to_string_lossy().to_string());
+        }
+    }
+
+    Ok(M3uPlaylist { name, paths })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Availability;
+
+    fn video(path: &Path, duration: Option<f64>) -> Video {
+        Video {
+            id: path.to_string_lossy().to_string(),
+            path: path.to_string_lossy().to_string(),
+            filename: path.file_name().unwrap().to_string_lossy().to_string(),
+            folder_path: path.parent().unwrap().to_string_lossy().to_string(),
+            size: 1000,
+            duration,
+            thumbnail_path: None,
+            mtime: None,
+            created_at: String::new(),
+            updated_at: String::new(),
+            availability: Availability::Online,
+        }
+    }
+
+    #[test]
+    fn written_playlists_read_back() {
+        let dir = std::env::temp_dir().join(format!("videoplayer-m3u-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let videos = [
+            video(&dir.join("a.mkv"), Some(61.4)),
+            video(&dir.join("nested").join("b c.mp4"), None),
+        ];
+
+        let file = dir.join("list.m3u8");
+        write_m3u(&file, Some("Road trip"), &videos).unwrap();
+        let contents = fs::read_to_string(&file).unwrap();
+        assert!(contents.starts_with("#EXTM3U\n#PLAYLIST:Road trip\n#EXTINF:61,a.mkv\n"));
+        assert!(contents.contains("#EXTINF:-1,b c.mp4\n"));
+
+        let read = read_m3u(&file).unwrap();
+        assert_eq!(read.name.as_deref(), Some("Road trip"));
+        assert_eq!(read.paths, [videos[0].path.clone(), videos[1].path.clone()]);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn reads_files_from_other_players() {
+        let dir = std::env::temp_dir().join(format!("videoplayer-m3u-import-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let absolute = dir.join("elsewhere").join("c.mkv");
+
+        // A BOM, Windows line endings, a stream URL and a relative entry
+        let file = dir.join("exported.m3u8");
+        fs::write(&file, format!(
+            "\u{feff}#EXTM3U\r\n#EXTINF:10,A\r\nsub/a.mkv\r\n\r\nhttp://example.com/live.ts\r\n# comment\r\n{}\r\n",
+            absolute.to_string_lossy()
+        )).unwrap();
+
+        let read = read_m3u(&file).unwrap();
+        assert!(read.name.is_none());
+        assert_eq!(read.paths, [
+            dir.join("sub/a.mkv").to_string_lossy().to_string(),
+            absolute.to_string_lossy().to_string(),
+        ]);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
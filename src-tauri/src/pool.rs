@@ -0,0 +1,93 @@
+use rusqlite::{Connection, Result};
+use std::ops::Deref;
+use std::path::Path;
+use std::sync::{Condvar, Mutex, MutexGuard};
+use std::time::Duration;
+
+/// How long a statement waits for another connection's write lock before failing
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// SQLite connections shared by every command and background worker.
+///
+/// WAL mode lets readers run while a write is in progress, so the pool keeps
+/// several read-only connections next to a single writer. Writers still
+/// queue up behind each other, which SQLite would enforce anyway.
+pub struct ConnectionPool {
+    writer: Mutex<Connection>,
+    readers: Mutex<Vec<Connection>>,
+    reader_returned: Condvar,
+}
+
+impl ConnectionPool {
+    /// Build the pool around an already opened (and migrated) writer
+    pub fn new(writer: Connection, db_path: &Path, reader_count: usize) -> Result<Self> {
+        let readers = (0..reader_count.max(1))
+            .map(|_| {
+                let conn = open_connection(db_path)?;
+                // Catch a write sent to a reader instead of letting it race the writer
+                conn.pragma_update(None, "query_only", true)?;
+                Ok(conn)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(ConnectionPool {
+            writer: Mutex::new(writer),
+            readers: Mutex::new(readers),
+            reader_returned: Condvar::new(),
+        })
+    }
+
+    /// Borrow a read-only connection, waiting for one to be returned if all are in use
+    pub fn read(&self) -> Reader<'_> {
+        let mut idle = self.readers.lock().unwrap();
+        loop {
+            if let Some(conn) = idle.pop() {
+                return Reader {
+                    pool: self,
+                    conn: Some(conn),
+                };
+            }
+            idle = self.reader_returned.wait(idle).unwrap();
+        }
+    }
+
+    /// The connection for anything that modifies the database
+    pub fn write(&self) -> MutexGuard<'_, Connection> {
+        self.writer.lock().unwrap()
+    }
+}
+
+/// Read-only connection borrowed from the pool; handed back when dropped
+pub struct Reader<'a> {
+    pool: &'a ConnectionPool,
+    conn: Option<Connection>,
+}
+
+impl Deref for Reader<'_> {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        self.conn.as_ref().unwrap()
+    }
+}
+
+impl Drop for Reader<'_> {
+    fn drop(&mut self) {
+        if let Some(conn) = self.conn.take() {
+            self.pool.readers.lock().unwrap().push(conn);
+            self.pool.reader_returned.notify_one();
+        }
+    }
+}
+
+/// Open a connection with the settings every connection in the pool shares
+pub fn open_connection(db_path: &Path) -> Result<Connection> {
+    let conn = Connection::open(db_path)?;
+
+    // Foreign keys are off by default in SQLite, which would turn every
+    // ON DELETE CASCADE into a no-op
+    conn.pragma_update(None, "foreign_keys", true)?;
+    conn.pragma_update(None, "journal_mode", "WAL")?;
+    conn.busy_timeout(BUSY_TIMEOUT)?;
+    Ok(conn)
+}
@@ -0,0 +1,241 @@
+use std::io::Read;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+use serde_json::Value;
+use tauri::{AppHandle, Emitter, Manager};
+use crate::commands::AppState;
+use crate::models::MediaInfo;
+use crate::player;
+
+/// Give up on a single file after this long (slow network mounts, broken files)
+const PROBE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Number of videos fetched from the database per probing pass
+const BATCH_SIZE: usize = 50;
+
+/// Marker printed by mpv so its output line can be found among the track listing
+const MPV_MARKER: &str = "VIDEOPLAYER_PROBE";
+
+/// Background queue that fills in media info for every unprobed video.
+///
+/// Work is tracked in the database (`videos.probed_at IS NULL`), so waking the
+/// queue after a scan is enough; nothing is lost across restarts.
+pub struct ProbeQueue {
+    sender: Sender<()>,
+    receiver: Mutex<Option<Receiver<()>>>,
+}
+
+impl ProbeQueue {
+    pub fn new() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        ProbeQueue {
+            sender,
+            receiver: Mutex::new(Some(receiver)),
+        }
+    }
+
+    /// Start the worker thread and process anything left over from previous runs
+    pub fn start(&self, app: AppHandle) {
+        if let Some(receiver) = self.receiver.lock().unwrap().take() {
+            thread::spawn(move || run_worker(app, receiver));
+            self.wake();
+        }
+    }
+
+    /// Ask the worker to look for unprobed videos
+    pub fn wake(&self) {
+        let _ = self.sender.send(());
+    }
+}
+
+fn run_worker(app: AppHandle, receiver: Receiver<()>) {
+    while receiver.recv().is_ok() {
+        // Collapse wake-ups that arrived while we were busy
+        while receiver.try_recv().is_ok() {}
+
+        if find_ffprobe().is_none() && !player::is_mpv_available() {
+            continue;
+        }
+
+        loop {
+            let state = app.state::<AppState>();
+            let batch = state.db.get_unprobed_videos(BATCH_SIZE).unwrap_or_default();
+            if batch.is_empty() {
+                break;
+            }
+
+            let mut probed: Vec<String> = Vec::with_capacity(batch.len());
+            for (video_id, path) in batch {
+                let info = probe_file(Path::new(&path));
+                if state.db.save_media_info(&video_id, info.as_ref()).is_ok() {
+                    probed.push(video_id);
+                }
+            }
+
+            let _ = app.emit("media-info-updated", &probed);
+        }
+    }
+}
+
+/// Read duration, resolution and codecs from a media file, preferring ffprobe
+/// and falling back to mpv
+pub fn probe_file(path: &Path) -> Option<MediaInfo> {
+    let mut info = probe_with_ffprobe(path).or_else(|| probe_with_mpv(path))?;
+
+    // Derive the overall bitrate when the container doesn't state it
+    if info.bitrate.is_none() {
+        if let (Some(duration), Ok(metadata)) = (info.duration, std::fs::metadata(path)) {
+            if duration > 0.0 {
+                info.bitrate = Some((metadata.len() as f64 * 8.0 / duration) as u64);
+            }
+        }
+    }
+
+    Some(info)
+}
+
+fn probe_with_ffprobe(path: &Path) -> Option<MediaInfo> {
+    let mut command = Command::new(find_ffprobe()?);
+    command.args(["-v", "error", "-print_format", "json", "-show_format", "-show_streams"])
+        .arg(path);
+
+    let output = output_with_timeout(command)?;
+    parse_ffprobe_output(&output)
+}
+
+fn parse_ffprobe_output(output: &str) -> Option<MediaInfo> {
+    let json: Value = serde_json::from_str(output).ok()?;
+    let streams = json["streams"].as_array()?;
+
+    // Cover art is reported as a video stream
+    let video = streams.iter().find(|s| {
+        s["codec_type"] == "video" && s["disposition"]["attached_pic"].as_i64() != Some(1)
+    });
+    let audio: Vec<&Value> = streams.iter().filter(|s| s["codec_type"] == "audio").collect();
+
+    Some(MediaInfo {
+        duration: json["format"]["duration"].as_str().and_then(|d| d.parse().ok()),
+        width: video.and_then(|v| v["width"].as_u64()).map(|w| w as u32),
+        height: video.and_then(|v| v["height"].as_u64()).map(|h| h as u32),
+        video_codec: video.and_then(|v| v["codec_name"].as_str()).map(str::to_string),
+        audio_codec: audio.first().and_then(|a| a["codec_name"].as_str()).map(str::to_string),
+        bitrate: json["format"]["bit_rate"].as_str().and_then(|b| b.parse().ok()),
+        frame_rate: video
+            .and_then(|v| v["avg_frame_rate"].as_str())
+            .and_then(parse_frame_rate),
+        audio_tracks: audio.len() as u32,
+    })
+}
+
+/// Parse ffprobe's rational frame rates such as `24000/1001`
+fn parse_frame_rate(rate: &str) -> Option<f64> {
+    let (num, den) = rate.split_once('/').unwrap_or((rate, "1"));
+    let num: f64 = num.parse().ok()?;
+    let den: f64 = den.parse().ok()?;
+    if num > 0.0 && den > 0.0 {
+        Some(num / den)
+    } else {
+        None
+    }
+}
+
+fn probe_with_mpv(path: &Path) -> Option<MediaInfo> {
+    let mut command = player::mpv_command()?;
+    command.args([
+        "--no-config",
+        "--frames=0",
+        "--vo=null",
+        "--ao=null",
+        "--no-sub",
+    ])
+    .arg(format!(
+        "--term-playing-msg={}|${{=duration:}}|${{=current-tracks/video/demux-w:}}|${{=current-tracks/video/demux-h:}}|${{current-tracks/video/codec:}}|${{current-tracks/audio/codec:}}|${{=current-tracks/video/demux-fps:}}",
+        MPV_MARKER
+    ))
+    .arg("--")
+    .arg(path);
+
+    let output = output_with_timeout(command)?;
+    parse_mpv_output(&output)
+}
+
+fn parse_mpv_output(output: &str) -> Option<MediaInfo> {
+    let line = output.lines().find_map(|l| l.trim().strip_prefix(MPV_MARKER))?;
+    let fields: Vec<&str> = line.split('|').skip(1).collect();
+    let field = |i: usize| fields.get(i).map(|f| f.trim()).filter(|f| !f.is_empty());
+
+    // mpv lists each audio track as "(+) Audio --aid=1 ..." before playback starts
+    let audio_tracks = output.lines().filter(|l| l.contains("Audio --aid=")).count() as u32;
+
+    Some(MediaInfo {
+        duration: field(0).and_then(|d| d.parse().ok()),
+        width: field(1).and_then(|w| w.parse().ok()),
+        height: field(2).and_then(|h| h.parse().ok()),
+        video_codec: field(3).map(str::to_string),
+        audio_codec: field(4).map(str::to_string),
+        bitrate: None,
+        frame_rate: field(5).and_then(|f| f.parse().ok()),
+        audio_tracks,
+    })
+}
+
+/// Find an ffprobe executable on the system (looked up once per run)
+fn find_ffprobe() -> Option<String> {
+    static FFPROBE: OnceLock<Option<String>> = OnceLock::new();
+    FFPROBE.get_or_init(locate_ffprobe).clone()
+}
+
+fn locate_ffprobe() -> Option<String> {
+    if Command::new("ffprobe").arg("-version").output().is_ok() {
+        return Some("ffprobe".to_string());
+    }
+
+    let possible_paths = if cfg!(target_os = "macos") {
+        vec!["/opt/homebrew/bin/ffprobe", "/usr/local/bin/ffprobe"]
+    } else if cfg!(target_os = "windows") {
+        vec!["C:\\ffmpeg\\bin\\ffprobe.exe"]
+    } else {
+        vec!["/usr/bin/ffprobe", "/usr/local/bin/ffprobe"]
+    };
+
+    possible_paths.into_iter()
+        .find(|p| Path::new(p).exists())
+        .map(str::to_string)
+}
+
+/// Run a command and capture its stdout, killing it after `PROBE_TIMEOUT`
+pub fn output_with_timeout(mut command: Command) -> Option<String> {
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+
+    // Drain stdout on another thread so a chatty process can't fill the pipe and stall
+    let mut stdout = child.stdout.take()?;
+    let reader = thread::spawn(move || {
+        let mut output = String::new();
+        let _ = stdout.read_to_string(&mut output);
+        output
+    });
+
+    let deadline = Instant::now() + PROBE_TIMEOUT;
+    loop {
+        match child.try_wait() {
+            Ok(Some(_)) => break,
+            Ok(None) if Instant::now() < deadline => thread::sleep(Duration::from_millis(50)),
+            _ => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return None;
+            }
+        }
+    }
+
+    reader.join().ok()
+}
@@ -0,0 +1,657 @@
+//! Query language for the library filter.
+//!
+//! A query is a list of terms combined with `AND` (implicit between terms),
+//! `OR`, `NOT` / `-` and parentheses:
+//!
+//! ```text
+//! tag:action AND NOT tag:seen participant:"Jane Doe" lang:ko duration:>30m size:<2GB added:last-7d
+//! ```
+//!
+//! Terms without a field are matched against the full-text index. Queries are
+//! compiled to a parameterized SQL condition over `videos v`.
+
+use std::fmt;
+use chrono::{Duration, NaiveDate, Utc};
+use rusqlite::types::Value;
+use serde::Serialize;
+use crate::database::{fts_match_expression, watch_state_condition};
+use crate::models::WatchState;
+
+/// A syntax or value error, located by UTF-16 code unit offsets into the
+/// query, i.e. JavaScript string indices
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct QueryError {
+    pub message: String,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} (at {}-{})", self.message, self.start, self.end)
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+fn error<T>(message: impl Into<String>, start: usize, end: usize) -> Result<T, QueryError> {
+    Err(QueryError { message: message.into(), start, end })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Comparison {
+    Less,
+    LessOrEqual,
+    Equal,
+    GreaterOrEqual,
+    Greater,
+}
+
+impl Comparison {
+    fn sql(self) -> &'static str {
+        match self {
+            Comparison::Less => "<",
+            Comparison::LessOrEqual => "<=",
+            Comparison::Equal => "=",
+            Comparison::GreaterOrEqual => ">=",
+            Comparison::Greater => ">",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Term {
+    Text(String),
+    Tag(String),
+    Participant(String),
+    Language(String),
+    Filename(String),
+    Path(String),
+    Extension(String),
+    Duration(Comparison, f64),
+    Size(Comparison, i64),
+    /// Half-open range of `created_at` timestamps
+    Added(Option<String>, Option<String>),
+    Watch(WatchState),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    And(Vec<Expr>),
+    Or(Vec<Expr>),
+    Not(Box<Expr>),
+    Term(Term),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    LeftParen,
+    RightParen,
+    And,
+    Or,
+    Not,
+    /// `field:value`; `value_start` is the offset of the value
+    Field { field: String, value: String, value_start: usize },
+    Text(String),
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: TokenKind,
+    start: usize,
+    end: usize,
+}
+
+fn tokenize(query: &str) -> Result<Vec<Token>, QueryError> {
+    let chars: Vec<char> = query.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    // Read a double-quoted string starting at `chars[i] == '"'`
+    let read_quoted = |i: usize| -> Result<(String, usize), QueryError> {
+        match chars[i + 1..].iter().position(|&c| c == '"') {
+            Some(len) => Ok((chars[i + 1..i + 1 + len].iter().collect(), i + len + 2)),
+            None => error("Unterminated quote", i, chars.len()),
+        }
+    };
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        let start = i;
+        let kind = match c {
+            '(' => {
+                i += 1;
+                TokenKind::LeftParen
+            }
+            ')' => {
+                i += 1;
+                TokenKind::RightParen
+            }
+            '-' if chars.get(i + 1).map(|n| !n.is_whitespace()).unwrap_or(false) => {
+                i += 1;
+                TokenKind::Not
+            }
+            '"' => {
+                let (text, end) = read_quoted(i)?;
+                i = end;
+                TokenKind::Text(text)
+            }
+            _ => {
+                while i < chars.len() && !chars[i].is_whitespace() && !matches!(chars[i], '(' | ')' | '"') {
+                    i += 1;
+                }
+                let word: String = chars[start..i].iter().collect();
+
+                match word.split_once(':') {
+                    Some((field, value)) => {
+                        let field_len = field.chars().count() + 1;
+                        let mut value = value.to_string();
+                        // `participant:"Jane Doe"` and `duration:>"1h 30m"`
+                        if i < chars.len() && chars[i] == '"' && !value.contains(|c: char| c.is_alphanumeric()) {
+                            let (quoted, end) = read_quoted(i)?;
+                            value.push_str(&quoted);
+                            i = end;
+                        }
+                        TokenKind::Field {
+                            field: field.to_lowercase(),
+                            value,
+                            value_start: start + field_len,
+                        }
+                    }
+                    None => match word.as_str() {
+                        "AND" => TokenKind::And,
+                        "OR" => TokenKind::Or,
+                        "NOT" => TokenKind::Not,
+                        _ => TokenKind::Text(word),
+                    },
+                }
+            }
+        };
+        tokens.push(Token { kind, start, end: i });
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    /// Length of the query, used to locate "unexpected end" errors
+    length: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, QueryError> {
+        let mut operands = vec![self.parse_and()?];
+        while matches!(self.peek(), Some(Token { kind: TokenKind::Or, .. })) {
+            self.next();
+            operands.push(self.parse_and()?);
+        }
+        Ok(if operands.len() == 1 { operands.remove(0) } else { Expr::Or(operands) })
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, QueryError> {
+        let mut operands = vec![self.parse_unary()?];
+        loop {
+            match self.peek().map(|t| &t.kind) {
+                Some(TokenKind::And) => {
+                    self.next();
+                    operands.push(self.parse_unary()?);
+                }
+                // Juxtaposed terms are joined with AND
+                Some(TokenKind::Or) | Some(TokenKind::RightParen) | None => break,
+                Some(_) => operands.push(self.parse_unary()?),
+            }
+        }
+        Ok(if operands.len() == 1 { operands.remove(0) } else { Expr::And(operands) })
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, QueryError> {
+        let Some(token) = self.next() else {
+            return error("Expected a search term", self.length, self.length);
+        };
+
+        match token.kind {
+            TokenKind::Not => Ok(Expr::Not(Box::new(self.parse_unary()?))),
+            TokenKind::LeftParen => {
+                let inner = self.parse_or()?;
+                match self.next() {
+                    Some(Token { kind: TokenKind::RightParen, .. }) => Ok(inner),
+                    _ => error("Missing closing parenthesis", token.start, token.end),
+                }
+            }
+            TokenKind::RightParen => error("Unexpected ')'", token.start, token.end),
+            TokenKind::And | TokenKind::Or => {
+                error("Expected a search term before this operator", token.start, token.end)
+            }
+            TokenKind::Text(text) => Ok(Expr::Term(Term::Text(text))),
+            TokenKind::Field { field, value, value_start } => {
+                parse_field(&field, &value, token.start, value_start, token.end).map(Expr::Term)
+            }
+        }
+    }
+}
+
+/// Parse a whole query; empty queries are rejected
+pub fn parse(query: &str) -> Result<Expr, QueryError> {
+    parse_chars(query).map_err(|e| QueryError {
+        start: utf16_offset(query, e.start),
+        end: utf16_offset(query, e.end),
+        ..e
+    })
+}
+
+/// `parse` with errors located by character offsets, as the tokenizer counts them
+fn parse_chars(query: &str) -> Result<Expr, QueryError> {
+    let tokens = tokenize(query)?;
+    let mut parser = Parser {
+        tokens,
+        position: 0,
+        length: query.chars().count(),
+    };
+
+    let expr = parser.parse_or()?;
+    if let Some(token) = parser.next() {
+        return error("Unexpected ')'", token.start, token.end);
+    }
+    Ok(expr)
+}
+
+/// Length in UTF-16 code units of the first `chars` characters of `text`
+fn utf16_offset(text: &str, chars: usize) -> usize {
+    text.chars().take(chars).map(char::len_utf16).sum()
+}
+
+fn split_comparison(value: &str) -> (Option<Comparison>, &str) {
+    for (prefix, comparison) in [
+        (">=", Comparison::GreaterOrEqual),
+        ("<=", Comparison::LessOrEqual),
+        (">", Comparison::Greater),
+        ("<", Comparison::Less),
+        ("=", Comparison::Equal),
+    ] {
+        if let Some(rest) = value.strip_prefix(prefix) {
+            return (Some(comparison), rest);
+        }
+    }
+    (None, value)
+}
+
+fn parse_field(field: &str, value: &str, start: usize, value_start: usize, end: usize) -> Result<Term, QueryError> {
+    let (comparison, operand) = split_comparison(value);
+    let operand = operand.trim();
+    if operand.is_empty() {
+        return error(format!("Missing value for '{}:'", field), start, end);
+    }
+
+    let text_value = |make: fn(String) -> Term| {
+        if comparison.is_some() {
+            error(format!("'{}:' can't be compared with < or >", field), value_start, end)
+        } else {
+            Ok(make(operand.to_string()))
+        }
+    };
+
+    match field {
+        "tag" => text_value(Term::Tag),
+        "participant" | "person" => text_value(Term::Participant),
+        "lang" | "language" => text_value(Term::Language),
+        "name" | "filename" => text_value(Term::Filename),
+        "path" => text_value(Term::Path),
+        "ext" => text_value(|ext| Term::Extension(ext.trim_start_matches('.').to_string())),
+        "duration" => match parse_duration(operand) {
+            Some(seconds) => Ok(Term::Duration(comparison.unwrap_or(Comparison::Equal), seconds)),
+            None => error(format!("Invalid duration '{}', expected e.g. 30m or 1h30m", operand), value_start, end),
+        },
+        "size" => match parse_size(operand) {
+            Some(bytes) => Ok(Term::Size(comparison.unwrap_or(Comparison::Equal), bytes)),
+            None => error(format!("Invalid size '{}', expected e.g. 700MB or 2GB", operand), value_start, end),
+        },
+        "added" => match parse_added(comparison, operand) {
+            Some((from, to)) => Ok(Term::Added(from, to)),
+            None => error(
+                format!("Invalid date '{}', expected e.g. last-7d, today or 2024-01-31", value),
+                value_start,
+                end,
+            ),
+        },
+        "watched" => {
+            let state = match operand.to_lowercase().as_str() {
+                "yes" | "true" => Some(WatchState::Watched),
+                "no" | "false" => Some(WatchState::Unwatched),
+                "partial" | "started" => Some(WatchState::InProgress),
+                _ => None,
+            };
+            match (state, comparison) {
+                (Some(state), None) => Ok(Term::Watch(state)),
+                _ => error("Expected watched:yes, watched:no or watched:partial", value_start, end),
+            }
+        }
+        _ => error(format!("Unknown field '{}'", field), start, value_start - 1),
+    }
+}
+
+/// Split `1h30m` into `[(1, 'h'), (30, 'm')]`; a trailing number gets `default_unit`
+fn parse_amounts(text: &str, default_unit: &str) -> Option<Vec<(f64, String)>> {
+    let mut amounts = Vec::new();
+    let mut rest = text.trim();
+    while !rest.is_empty() {
+        let number_len = rest.find(|c: char| !(c.is_ascii_digit() || c == '.')).unwrap_or(rest.len());
+        if number_len == 0 {
+            return None;
+        }
+        let number: f64 = rest[..number_len].parse().ok()?;
+        rest = rest[number_len..].trim_start();
+
+        let unit_len = rest.find(|c: char| !c.is_ascii_alphabetic()).unwrap_or(rest.len());
+        let unit = if unit_len == 0 { default_unit.to_string() } else { rest[..unit_len].to_lowercase() };
+        rest = rest[unit_len..].trim_start();
+
+        amounts.push((number, unit));
+    }
+    if amounts.is_empty() {
+        None
+    } else {
+        Some(amounts)
+    }
+}
+
+/// `90`, `45s`, `30m`, `1h30m` → seconds
+fn parse_duration(text: &str) -> Option<f64> {
+    parse_amounts(text, "s")?.into_iter().try_fold(0.0, |total, (number, unit)| {
+        let scale = match unit.as_str() {
+            "s" | "sec" => 1.0,
+            "m" | "min" => 60.0,
+            "h" | "hr" => 3600.0,
+            _ => return None,
+        };
+        Some(total + number * scale)
+    })
+}
+
+/// `2GB`, `700mb`, `1.5G` → bytes (binary multiples)
+fn parse_size(text: &str) -> Option<i64> {
+    let amounts = parse_amounts(text, "b")?;
+    let [(number, unit)] = amounts.as_slice() else {
+        return None;
+    };
+    let exponent = match unit.as_str() {
+        "b" => 0,
+        "k" | "kb" => 1,
+        "m" | "mb" => 2,
+        "g" | "gb" => 3,
+        "t" | "tb" => 4,
+        _ => return None,
+    };
+    Some((number * 1024f64.powi(exponent)) as i64)
+}
+
+/// Timestamp in the format `created_at` is stored in, so they compare as text
+fn timestamp(date: NaiveDate) -> String {
+    date.and_hms_opt(0, 0, 0).unwrap().and_utc().to_rfc3339()
+}
+
+/// Turn `last-7d`, `today` or a `YYYY-MM-DD` comparison into a `[from, to)` range
+fn parse_added(comparison: Option<Comparison>, text: &str) -> Option<(Option<String>, Option<String>)> {
+    let text = text.to_lowercase();
+    let now = Utc::now();
+
+    if let Some(period) = text.strip_prefix("last-") {
+        if comparison.is_some() {
+            return None;
+        }
+        let amounts = parse_amounts(period, "d")?;
+        let [(number, unit)] = amounts.as_slice() else {
+            return None;
+        };
+        let days = match unit.as_str() {
+            "h" => return Some((Some((now - Duration::minutes((number * 60.0) as i64)).to_rfc3339()), None)),
+            "d" => 1.0,
+            "w" => 7.0,
+            "m" | "mo" => 30.0,
+            "y" => 365.0,
+            _ => return None,
+        };
+        let since = now - Duration::minutes((number * days * 24.0 * 60.0) as i64);
+        return Some((Some(since.to_rfc3339()), None));
+    }
+
+    let day = match text.as_str() {
+        "today" => now.date_naive(),
+        "yesterday" => now.date_naive() - Duration::days(1),
+        _ => NaiveDate::parse_from_str(&text, "%Y-%m-%d").ok()?,
+    };
+    let day_start = timestamp(day);
+    let next_day_start = timestamp(day + Duration::days(1));
+
+    Some(match comparison.unwrap_or(Comparison::Equal) {
+        Comparison::Equal => (Some(day_start), Some(next_day_start)),
+        Comparison::Greater => (Some(next_day_start), None),
+        Comparison::GreaterOrEqual => (Some(day_start), None),
+        Comparison::Less => (None, Some(day_start)),
+        Comparison::LessOrEqual => (None, Some(next_day_start)),
+    })
+}
+
+fn like_pattern(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
+
+impl Expr {
+    /// Compile into a SQL condition over `videos v`. Placeholders are numbered
+    /// from `first_param`; the returned values bind to them in order.
+    pub fn to_sql(&self, first_param: usize) -> (String, Vec<Value>) {
+        let mut params = Vec::new();
+        let sql = self.write_sql(first_param, &mut params);
+        (sql, params)
+    }
+
+    fn write_sql(&self, first_param: usize, params: &mut Vec<Value>) -> String {
+        match self {
+            Expr::And(operands) => {
+                let parts: Vec<String> = operands.iter().map(|e| e.write_sql(first_param, params)).collect();
+                format!("({})", parts.join(" AND "))
+            }
+            Expr::Or(operands) => {
+                let parts: Vec<String> = operands.iter().map(|e| e.write_sql(first_param, params)).collect();
+                format!("({})", parts.join(" OR "))
+            }
+            Expr::Not(inner) => format!("NOT {}", inner.write_sql(first_param, params)),
+            Expr::Term(term) => term.write_sql(first_param, params),
+        }
+    }
+}
+
+impl Term {
+    fn write_sql(&self, first_param: usize, params: &mut Vec<Value>) -> String {
+        let mut bind = |value: Value| {
+            params.push(value);
+            format!("?{}", first_param + params.len() - 1)
+        };
+
+        match self {
+            Term::Text(text) => match fts_match_expression(text) {
+                Some(expression) => format!(
+                    "v.id IN (SELECT k.video_id FROM video_search_keys k
+                              WHERE k.id IN (SELECT rowid FROM video_search WHERE video_search MATCH {}))",
+                    bind(Value::Text(expression))
+                ),
+                // Nothing searchable, e.g. a lone punctuation mark
+                None => "1".to_string(),
+            },
+            Term::Tag(name) => format!(
+                "EXISTS (SELECT 1 FROM video_tags vt INNER JOIN tags t ON t.id = vt.tag_id
+                         WHERE vt.video_id = v.id AND t.name = {} COLLATE NOCASE)",
+                bind(Value::Text(name.clone()))
+            ),
+            Term::Participant(name) => format!(
+                "EXISTS (SELECT 1 FROM video_participants vp INNER JOIN participants p ON p.id = vp.participant_id
+                         WHERE vp.video_id = v.id AND p.name = {} COLLATE NOCASE)",
+                bind(Value::Text(name.clone()))
+            ),
+            Term::Language(name) => {
+                let placeholder = bind(Value::Text(name.clone()));
+                format!(
+                    "EXISTS (SELECT 1 FROM video_languages vl INNER JOIN languages l ON l.id = vl.language_id
+                             WHERE vl.video_id = v.id AND (l.code = {0} COLLATE NOCASE OR l.name = {0} COLLATE NOCASE))",
+                    placeholder
+                )
+            }
+            Term::Filename(text) => format!(
+                "v.filename LIKE {} ESCAPE '\\'",
+                bind(Value::Text(format!("%{}%", like_pattern(text))))
+            ),
+            Term::Path(text) => format!(
+                "v.path LIKE {} ESCAPE '\\'",
+                bind(Value::Text(format!("%{}%", like_pattern(text))))
+            ),
+            Term::Extension(ext) => format!(
+                "v.filename LIKE {} ESCAPE '\\'",
+                bind(Value::Text(format!("%.{}", like_pattern(ext))))
+            ),
+            Term::Duration(comparison, seconds) => {
+                format!("v.duration {} {}", comparison.sql(), bind(Value::Real(*seconds)))
+            }
+            Term::Size(comparison, bytes) => {
+                format!("v.size {} {}", comparison.sql(), bind(Value::Integer(*bytes)))
+            }
+            Term::Added(from, to) => {
+                let mut bounds = Vec::new();
+                if let Some(from) = from {
+                    bounds.push(format!("v.created_at >= {}", bind(Value::Text(from.clone()))));
+                }
+                if let Some(to) = to {
+                    bounds.push(format!("v.created_at < {}", bind(Value::Text(to.clone()))));
+                }
+                format!("({})", bounds.join(" AND "))
+            }
+            Term::Watch(state) => watch_state_condition(*state).to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn term(term: Term) -> Expr {
+        Expr::Term(term)
+    }
+
+    fn text(word: &str) -> Expr {
+        term(Term::Text(word.to_string()))
+    }
+
+    fn tag(name: &str) -> Expr {
+        term(Term::Tag(name.to_string()))
+    }
+
+    fn error_at(query: &str) -> (usize, usize) {
+        let e = parse(query).unwrap_err();
+        (e.start, e.end)
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        assert_eq!(
+            parse("a b OR c AND d").unwrap(),
+            Expr::Or(vec![Expr::And(vec![text("a"), text("b")]), Expr::And(vec![text("c"), text("d")])])
+        );
+        assert_eq!(
+            parse("a (b OR c)").unwrap(),
+            Expr::And(vec![text("a"), Expr::Or(vec![text("b"), text("c")])])
+        );
+    }
+
+    #[test]
+    fn negation_applies_to_one_term() {
+        assert_eq!(
+            parse("NOT tag:seen tag:new").unwrap(),
+            Expr::And(vec![Expr::Not(Box::new(tag("seen"))), tag("new")])
+        );
+        assert_eq!(parse("-tag:seen").unwrap(), Expr::Not(Box::new(tag("seen"))));
+        assert_eq!(
+            parse("-(a OR b)").unwrap(),
+            Expr::Not(Box::new(Expr::Or(vec![text("a"), text("b")])))
+        );
+        // A dash inside a word or before a space is not an operator
+        assert_eq!(parse("x-ray").unwrap(), text("x-ray"));
+    }
+
+    #[test]
+    fn quotes_keep_spaces_and_operators() {
+        assert_eq!(parse("\"one OR two\"").unwrap(), text("one OR two"));
+        assert_eq!(
+            parse("participant:\"Jane Doe\"").unwrap(),
+            term(Term::Participant("Jane Doe".to_string()))
+        );
+        assert_eq!(parse("duration:>\"1h 30m\"").unwrap(), term(Term::Duration(Comparison::Greater, 5400.0)));
+    }
+
+    #[test]
+    fn parses_field_filters() {
+        assert_eq!(parse("lang:ko").unwrap(), term(Term::Language("ko".to_string())));
+        assert_eq!(parse("ext:.MKV").unwrap(), term(Term::Extension("MKV".to_string())));
+        assert_eq!(parse("duration:<=90").unwrap(), term(Term::Duration(Comparison::LessOrEqual, 90.0)));
+        assert_eq!(parse("size:>1.5G").unwrap(), term(Term::Size(Comparison::Greater, 1_610_612_736)));
+        assert_eq!(parse("watched:partial").unwrap(), term(Term::Watch(WatchState::InProgress)));
+        assert_eq!(
+            parse("added:2024-01-31").unwrap(),
+            term(Term::Added(
+                Some("2024-01-31T00:00:00+00:00".to_string()),
+                Some("2024-02-01T00:00:00+00:00".to_string()),
+            ))
+        );
+        assert!(matches!(parse("added:last-7d").unwrap(), Expr::Term(Term::Added(Some(_), None))));
+    }
+
+    #[test]
+    fn errors_point_at_the_problem() {
+        assert_eq!(error_at("tag:a \"open"), (6, 11));
+        assert_eq!(error_at("(a OR b"), (0, 1));
+        assert_eq!(error_at("a )"), (2, 3));
+        assert_eq!(error_at("a OR"), (4, 4));
+        assert_eq!(error_at("AND a"), (0, 3));
+        assert_eq!(error_at("colour:red"), (0, 6));
+        assert_eq!(error_at("size:huge"), (5, 9));
+        assert_eq!(error_at("tag:>a"), (4, 6));
+        assert_eq!(parse("duration:").unwrap_err().message, "Missing value for 'duration:'");
+    }
+
+    #[test]
+    fn error_offsets_are_utf16_units() {
+        // '🎬' is one char but two UTF-16 units
+        assert_eq!(error_at("🎬 size:huge"), (8, 12));
+        assert_eq!(error_at("é size:huge"), (7, 11));
+    }
+
+    #[test]
+    fn values_are_bound_not_spliced() {
+        let injection = "x' OR 1=1; DROP TABLE videos; --";
+        let expr = parse(&format!("tag:\"{}\" name:\"{}\"", injection, injection)).unwrap();
+        let (sql, params) = expr.to_sql(3);
+
+        assert!(!sql.contains("DROP"));
+        assert!(sql.contains("?3") && sql.contains("?4"));
+        assert_eq!(params.len(), 2);
+        assert_eq!(params[0], Value::Text(injection.to_string()));
+        // LIKE wildcards in the value match literally
+        let (_, params) = parse("name:100%_done").unwrap().to_sql(1);
+        assert_eq!(params[0], Value::Text("%100\\%\\_done%".to_string()));
+    }
+}
@@ -0,0 +1,365 @@
+use serde_json::json;
+use tauri::{AppHandle, Emitter, Manager};
+use crate::commands::AppState;
+use crate::models::{QueueState, RepeatMode, Video};
+
+/// "Up next" list played one file at a time through mpv.
+///
+/// The queue survives restarts through the `play_queue` table; every change
+/// goes through `publish`, which saves it and emits `queue-changed`.
+#[derive(Default)]
+pub struct PlayQueue {
+    items: Vec<Video>,
+    current: Option<usize>,
+    repeat: RepeatMode,
+    /// Whether mpv is playing from the queue (as opposed to a single video or a playlist)
+    playing: bool,
+}
+
+impl PlayQueue {
+    pub fn restore(items: Vec<Video>, current: Option<usize>, repeat: RepeatMode) -> Self {
+        let current = current.filter(|&i| i < items.len());
+        PlayQueue {
+            items,
+            current,
+            repeat,
+            playing: false,
+        }
+    }
+
+    pub fn snapshot(&self) -> QueueState {
+        QueueState {
+            items: self.items.clone(),
+            current: self.current,
+            repeat: self.repeat,
+            playing: self.playing,
+        }
+    }
+
+    pub fn video_ids(&self) -> Vec<String> {
+        self.items.iter().map(|v| v.id.clone()).collect()
+    }
+
+    pub fn current(&self) -> Option<usize> {
+        self.current
+    }
+
+    pub fn repeat(&self) -> RepeatMode {
+        self.repeat
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    pub fn set_playing(&mut self, playing: bool) {
+        self.playing = playing;
+    }
+
+    pub fn enqueue(&mut self, videos: Vec<Video>) {
+        self.items.extend(videos);
+    }
+
+    /// Insert right after the current item
+    pub fn play_next(&mut self, videos: Vec<Video>) {
+        let at = self.current.map(|i| i + 1).unwrap_or(0).min(self.items.len());
+        self.items.splice(at..at, videos);
+    }
+
+    pub fn remove(&mut self, index: usize) {
+        if index >= self.items.len() {
+            return;
+        }
+        self.items.remove(index);
+        self.current = match self.current {
+            Some(c) if c > index => Some(c - 1),
+            Some(c) if c == index && c >= self.items.len() => None,
+            other => other,
+        };
+    }
+
+    pub fn move_item(&mut self, from: usize, to: usize) {
+        if from >= self.items.len() {
+            return;
+        }
+        let to = to.min(self.items.len() - 1);
+        let item = self.items.remove(from);
+        self.items.insert(to, item);
+
+        self.current = self.current.map(|c| {
+            if c == from {
+                to
+            } else if from < c && c <= to {
+                c - 1
+            } else if to <= c && c < from {
+                c + 1
+            } else {
+                c
+            }
+        });
+    }
+
+    pub fn clear(&mut self) {
+        self.items.clear();
+        self.current = None;
+        self.playing = false;
+    }
+
+    /// Randomize the order of everything after the current item
+    pub fn shuffle(&mut self) {
+        let start = self.current.map(|i| i + 1).unwrap_or(0).min(self.items.len());
+        // Random v4 UUIDs make a cheap shuffle key without another dependency
+        self.items[start..].sort_by_cached_key(|_| uuid::Uuid::new_v4());
+    }
+
+    pub fn set_repeat(&mut self, repeat: RepeatMode) {
+        self.repeat = repeat;
+    }
+
+    /// Swap every entry for one of `video_ids` with `video`, e.g. after duplicates
+    /// were merged. Returns whether anything changed.
+    pub fn replace_videos(&mut self, video_ids: &[String], video: &Video) -> bool {
+        let mut changed = false;
+        for item in self.items.iter_mut().filter(|item| video_ids.contains(&item.id)) {
+            *item = video.clone();
+            changed = true;
+        }
+        changed
+    }
+
+    /// Make `index` current and return its video
+    pub fn jump(&mut self, index: usize) -> Option<Video> {
+        let video = self.items.get(index)?.clone();
+        self.current = Some(index);
+        Some(video)
+    }
+
+    /// Step after the current item finished, honoring the repeat mode
+    pub fn advance(&mut self) -> Option<Video> {
+        let next = match (self.current, self.repeat) {
+            (Some(c), RepeatMode::One) => c,
+            (Some(c), RepeatMode::All) if c + 1 >= self.items.len() => 0,
+            (Some(c), _) => c + 1,
+            (None, _) => 0,
+        };
+        self.jump(next)
+    }
+
+    /// Skip forward regardless of repeat-one
+    pub fn next(&mut self) -> Option<Video> {
+        let next = match self.current {
+            Some(c) if c + 1 < self.items.len() => c + 1,
+            Some(_) if self.repeat == RepeatMode::All => 0,
+            Some(_) => return None,
+            None => 0,
+        };
+        self.jump(next)
+    }
+
+    pub fn previous(&mut self) -> Option<Video> {
+        let previous = match self.current {
+            Some(0) if self.repeat == RepeatMode::All => self.items.len().checked_sub(1)?,
+            Some(c) => c.checked_sub(1)?,
+            None => return None,
+        };
+        self.jump(previous)
+    }
+}
+
+/// Save the queue and tell every window about it
+pub fn publish(app: &AppHandle, queue: &PlayQueue) {
+    let state = app.state::<AppState>();
+    if let Err(e) = state.db.save_play_queue(&queue.video_ids(), queue.current(), queue.repeat()) {
+        log::error!("Failed to save play queue: {}", e);
+    }
+    let _ = app.emit("queue-changed", queue.snapshot());
+}
+
+/// Play `video` in mpv, reusing the running instance when there is one.
+/// Playback resumes where the video was last left off.
+pub fn play_video(app: &AppHandle, video: &Video) -> Result<(), String> {
+    let state = app.state::<AppState>();
+    let resume = state.db.get_playback_position(&video.id).ok().flatten()
+        .filter(|p| *p > 0.0);
+
+    let mut player = state.player.player.lock().map_err(|e| e.to_string())?;
+    *state.player.playlist.lock().map_err(|e| e.to_string())? = None;
+
+    if let Ok(ipc) = player.ipc() {
+        // `start` is a global option in mpv, so set it for every file
+        let start = resume.map(|p| p.to_string()).unwrap_or_else(|| "none".to_string());
+        ipc.set_property("start", json!(start))?;
+        ipc.command(json!(["loadfile", video.path, "replace"]))?;
+        ipc.set_property("pause", json!(false))
+    } else {
+        player.play(&video.path, None, resume)
+    }
+}
+
+/// Called by the playback tracker when mpv reaches the end of a file
+pub fn on_end_of_file(app: &AppHandle) {
+    let state = app.state::<AppState>();
+    let Ok(mut queue) = state.player.queue.lock() else {
+        return;
+    };
+    if !queue.is_playing() {
+        return;
+    }
+
+    match queue.advance() {
+        Some(video) => {
+            if let Err(e) = play_video(app, &video) {
+                log::error!("Failed to play next queue item: {}", e);
+                queue.set_playing(false);
+            }
+        }
+        // Reached the end without repeat
+        None => queue.set_playing(false),
+    }
+    publish(app, &queue);
+}
+
+/// Called by the playback tracker when mpv exits. If the file had been played
+/// to the end (mpv was quit from the end screen, or IPC was unavailable) the
+/// queue moves on; closing mpv mid-file stops the queue instead.
+pub fn on_player_exit(app: &AppHandle, completed: bool) {
+    if completed {
+        on_end_of_file(app);
+        return;
+    }
+
+    let state = app.state::<AppState>();
+    let Ok(mut queue) = state.player.queue.lock() else {
+        return;
+    };
+    if queue.is_playing() {
+        queue.set_playing(false);
+        publish(app, &queue);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Availability;
+
+    fn video(id: &str) -> Video {
+        Video {
+            id: id.to_string(),
+            path: format!("/videos/{}.mp4", id),
+            filename: format!("{}.mp4", id),
+            folder_path: "/videos".to_string(),
+            size: 1000,
+            duration: None,
+            thumbnail_path: None,
+            mtime: None,
+            created_at: String::new(),
+            updated_at: String::new(),
+            availability: Availability::Online,
+        }
+    }
+
+    fn queue_of<S: AsRef<str>>(ids: &[S]) -> PlayQueue {
+        let mut queue = PlayQueue::default();
+        queue.enqueue(ids.iter().map(|id| video(id.as_ref())).collect());
+        queue
+    }
+
+    fn advance_id(queue: &mut PlayQueue) -> Option<String> {
+        queue.advance().map(|v| v.id)
+    }
+
+    #[test]
+    fn advances_through_the_queue() {
+        let mut queue = queue_of(&["a", "b", "c"]);
+        assert_eq!(advance_id(&mut queue).as_deref(), Some("a"));
+        assert_eq!(advance_id(&mut queue).as_deref(), Some("b"));
+        assert_eq!(advance_id(&mut queue).as_deref(), Some("c"));
+        assert_eq!(advance_id(&mut queue), None);
+        // Running off the end keeps the last item current
+        assert_eq!(queue.current(), Some(2));
+
+        assert_eq!(PlayQueue::default().advance().map(|v| v.id), None);
+    }
+
+    #[test]
+    fn repeat_modes_change_what_comes_next() {
+        let mut queue = queue_of(&["a", "b"]);
+        queue.jump(1);
+
+        queue.set_repeat(RepeatMode::One);
+        assert_eq!(advance_id(&mut queue).as_deref(), Some("b"));
+        // Skipping ignores repeat-one
+        assert_eq!(queue.next().map(|v| v.id), None);
+
+        queue.set_repeat(RepeatMode::All);
+        assert_eq!(advance_id(&mut queue).as_deref(), Some("a"));
+        assert_eq!(queue.previous().map(|v| v.id).as_deref(), Some("b"));
+        assert_eq!(queue.next().map(|v| v.id).as_deref(), Some("a"));
+
+        queue.set_repeat(RepeatMode::Off);
+        assert_eq!(queue.previous().map(|v| v.id), None);
+    }
+
+    #[test]
+    fn shuffle_keeps_played_items_in_place() {
+        let ids: Vec<String> = (0..50).map(|i| i.to_string()).collect();
+        let mut queue = queue_of(&ids);
+        queue.jump(9);
+        queue.shuffle();
+
+        let shuffled = queue.video_ids();
+        assert_eq!(queue.current(), Some(9));
+        assert_eq!(shuffled[..10], ids[..10]);
+        let mut rest = shuffled[10..].to_vec();
+        rest.sort();
+        let mut expected = ids[10..].to_vec();
+        expected.sort();
+        assert_eq!(rest, expected);
+
+        // Nothing current: everything is shuffled, and nothing is lost
+        let mut queue = queue_of(&ids);
+        queue.shuffle();
+        let mut all = queue.video_ids();
+        all.sort();
+        let mut expected = ids.clone();
+        expected.sort();
+        assert_eq!(all, expected);
+        assert_eq!(queue.current(), None);
+    }
+
+    #[test]
+    fn restore_drops_out_of_range_positions() {
+        let restored = PlayQueue::restore(vec![video("a"), video("b")], Some(1), RepeatMode::All);
+        let snapshot = restored.snapshot();
+        assert_eq!(snapshot.current, Some(1));
+        assert_eq!(snapshot.repeat, RepeatMode::All);
+        // A restored queue never claims to be playing
+        assert!(!snapshot.playing);
+
+        let restored = PlayQueue::restore(vec![video("a")], Some(3), RepeatMode::Off);
+        assert_eq!(restored.current(), None);
+        let restored = PlayQueue::restore(Vec::new(), Some(0), RepeatMode::Off);
+        assert_eq!(restored.current(), None);
+    }
+
+    #[test]
+    fn editing_keeps_the_current_item() {
+        let mut queue = queue_of(&["a", "b", "c", "d"]);
+        queue.jump(2);
+
+        queue.move_item(0, 3);
+        assert_eq!(queue.video_ids(), ["b", "c", "d", "a"]);
+        assert_eq!(queue.current(), Some(1));
+
+        queue.remove(0);
+        assert_eq!(queue.current(), Some(0));
+        queue.play_next(vec![video("e")]);
+        assert_eq!(queue.video_ids(), ["c", "e", "d", "a"]);
+        assert_eq!(advance_id(&mut queue).as_deref(), Some("e"));
+
+        assert!(queue.replace_videos(&["d".to_string(), "a".to_string()], &video("c")));
+        assert_eq!(queue.video_ids(), ["c", "e", "c", "c"]);
+        assert!(!queue.replace_videos(&["x".to_string()], &video("c")));
+    }
+}
@@ -0,0 +1,297 @@
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+use crate::availability;
+use crate::commands::AppState;
+use crate::models::{Availability, ScanFinished, ScanJob, ScanJobStatus, ScanOutcome, ScanProgress, ScanReport, ScanReportStatus, ScanResult, ScanRules};
+use crate::scan_rules::RuleSet;
+use crate::scanner;
+
+/// Minimum time between two `scan-progress` events of the same job
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+struct QueuedJob {
+    job: ScanJob,
+    cancelled: Arc<AtomicBool>,
+}
+
+/// Runs folder scans one at a time on a worker thread.
+///
+/// `scan_folder` only queues a job; the worker reports `scan-progress` while
+/// walking the disk and `scan-finished` with the outcome.
+pub struct ScanQueue {
+    jobs: Mutex<Vec<QueuedJob>>,
+    sender: Sender<String>,
+    receiver: Mutex<Option<Receiver<String>>>,
+}
+
+impl ScanQueue {
+    pub fn new() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        ScanQueue {
+            jobs: Mutex::new(Vec::new()),
+            sender,
+            receiver: Mutex::new(Some(receiver)),
+        }
+    }
+
+    /// Start the worker thread
+    pub fn start(&self, app: AppHandle) {
+        if let Some(receiver) = self.receiver.lock().unwrap().take() {
+            thread::spawn(move || run_worker(app, receiver));
+        }
+    }
+
+    /// Queue a scan of `folder_path`. A folder that is already waiting for its
+    /// turn isn't queued twice; its existing job is returned instead.
+    pub fn enqueue(&self, folder_path: &str) -> ScanJob {
+        let mut jobs = self.jobs.lock().unwrap();
+        if let Some(queued) = jobs.iter().find(|q| {
+            q.job.folder_path == folder_path && q.job.status == ScanJobStatus::Queued
+        }) {
+            return queued.job.clone();
+        }
+
+        let job = ScanJob {
+            id: uuid::Uuid::new_v4().to_string(),
+            folder_path: folder_path.to_string(),
+            status: ScanJobStatus::Queued,
+        };
+        jobs.push(QueuedJob {
+            job: job.clone(),
+            cancelled: Arc::new(AtomicBool::new(false)),
+        });
+        let _ = self.sender.send(job.id.clone());
+        job
+    }
+
+    /// Jobs waiting or running, in the order they will finish
+    pub fn jobs(&self) -> Vec<ScanJob> {
+        self.jobs.lock().unwrap().iter().map(|q| q.job.clone()).collect()
+    }
+
+    /// Stop a running scan, or drop a queued one before it starts. A cancelled
+    /// scan leaves the library untouched.
+    pub fn cancel(&self, job_id: &str) -> Result<(), String> {
+        let jobs = self.jobs.lock().unwrap();
+        let queued = jobs.iter()
+            .find(|q| q.job.id == job_id)
+            .ok_or_else(|| "Scan job not found".to_string())?;
+        queued.cancelled.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Mark a job as running and return its folder and cancellation flag
+    fn begin(&self, job_id: &str) -> Option<(String, Arc<AtomicBool>)> {
+        let mut jobs = self.jobs.lock().unwrap();
+        let queued = jobs.iter_mut().find(|q| q.job.id == job_id)?;
+        queued.job.status = ScanJobStatus::Running;
+        Some((queued.job.folder_path.clone(), queued.cancelled.clone()))
+    }
+
+    fn finish(&self, job_id: &str) {
+        self.jobs.lock().unwrap().retain(|q| q.job.id != job_id);
+    }
+}
+
+fn run_worker(app: AppHandle, receiver: Receiver<String>) {
+    while let Ok(job_id) = receiver.recv() {
+        let state = app.state::<AppState>();
+        let Some((folder_path, cancelled)) = state.scans.begin(&job_id) else {
+            continue;
+        };
+
+        let outcome = if cancelled.load(Ordering::Relaxed) {
+            ScanOutcome::Cancelled
+        } else {
+            let started_at = chrono::Utc::now().to_rfc3339();
+            let started = Instant::now();
+            let outcome = match run_scan(&app, &job_id, &folder_path, &cancelled) {
+                Ok(Some(result)) => ScanOutcome::Completed { result },
+                Ok(None) => ScanOutcome::Cancelled,
+                Err(error) => ScanOutcome::Failed { error },
+            };
+            
+            let report = scan_report(&folder_path, started_at, started.elapsed(), &outcome);
+            if let Err(e) = state.db.save_scan_report(&report) {
+                log::error!("Failed to save scan report for {}: {}", folder_path, e);
+            }
+            outcome
+        };
+
+        state.scans.finish(&job_id);
+        let _ = app.emit("scan-finished", ScanFinished {
+            job_id,
+            folder_path,
+            outcome,
+        });
+    }
+}
+
+fn scan_report(folder_path: &str, started_at: String, duration: Duration, outcome: &ScanOutcome) -> ScanReport {
+    let mut report = ScanReport {
+        folder_path: folder_path.to_string(),
+        started_at,
+        duration_ms: duration.as_millis() as u64,
+        status: ScanReportStatus::Completed,
+        error: None,
+        total_videos: 0,
+        new_videos: 0,
+        updated_videos: 0,
+        removed_videos: 0,
+        relinked_videos: 0,
+        errors: Vec::new(),
+    };
+    match outcome {
+        ScanOutcome::Completed { result } => {
+            report.total_videos = result.total_videos;
+            report.new_videos = result.new_videos;
+            report.updated_videos = result.updated_videos;
+            report.removed_videos = result.removed_videos;
+            report.relinked_videos = result.relinked.len();
+            report.errors = result.errors.clone();
+        }
+        ScanOutcome::Cancelled => report.status = ScanReportStatus::Cancelled,
+        ScanOutcome::Failed { error } => {
+            report.status = ScanReportStatus::Failed;
+            report.error = Some(error.clone());
+        }
+    }
+    report
+}
+
+/// Walk the folder and reconcile it with the database. Returns `None` when
+/// the scan was cancelled before anything was written.
+fn run_scan(app: &AppHandle, job_id: &str, folder_path: &str, cancelled: &AtomicBool) -> Result<Option<ScanResult>, String> {
+    let state = app.state::<AppState>();
+
+    // Get scan depth and rules for this folder
+    let (scan_depth, scan_rules) = state.db.get_mounted_folder(folder_path)
+        .map_err(|e| e.to_string())?
+        .map(|f| (f.scan_depth, f.scan_rules))
+        .unwrap_or_else(|| (2, ScanRules::default()));
+    let rules = Arc::new(RuleSet::new(folder_path, &scan_rules)?);
+
+    // An unreachable folder would look empty and mark every stored video missing
+    if !availability::is_volume_online(&state.db, folder_path) {
+        state.db.mark_videos_at(folder_path, Availability::OfflineVolume).map_err(|e| e.to_string())?;
+        return Err(format!("Folder is unreachable: {}", folder_path));
+    }
+
+    let mut last_progress: Option<Instant> = None;
+    let mut scan_result = scanner::scan_folder_with_progress(folder_path, scan_depth, &rules, &mut |dirs_visited, files_found, current_path| {
+        if last_progress.map(|t| t.elapsed() >= PROGRESS_INTERVAL).unwrap_or(true) {
+            last_progress = Some(Instant::now());
+            let _ = app.emit("scan-progress", ScanProgress {
+                job_id: job_id.to_string(),
+                folder_path: folder_path.to_string(),
+                dirs_visited,
+                files_found,
+                current_path: current_path.to_string(),
+            });
+        }
+        !cancelled.load(Ordering::Relaxed)
+    });
+
+    // A partial walk would mark everything it didn't reach as missing
+    if cancelled.load(Ordering::Relaxed) {
+        return Ok(None);
+    }
+
+    // Diff scanned videos against the database, keeping existing ids. Videos
+    // in directories that couldn't be read are left alone; files the rules
+    // skip now keep their rows and metadata as excluded.
+    let unreadable: Vec<String> = scan_result.errors.iter().map(|e| e.path.clone()).collect();
+    let changes = state.db.apply_folder_scan(folder_path, &mut scan_result.videos, &unreadable)
+        .map_err(|e| e.to_string())?;
+    if let Some(device_id) = availability::device_id(Path::new(folder_path)) {
+        state.db.set_folder_device(folder_path, device_id).map_err(|e| e.to_string())?;
+    }
+
+    // Probe, thumbnail and hash new and changed files in the background
+    state.probe.wake();
+    state.thumbnails.wake();
+    state.hashing.wake();
+
+    let tree = state.db.get_folder_tree(folder_path, None).map_err(|e| e.to_string())?;
+
+    Ok(Some(ScanResult {
+        total_videos: scan_result.total_videos,
+        new_videos: changes.new_videos,
+        updated_videos: changes.updated_videos,
+        removed_videos: changes.removed_videos,
+        relinked: changes.relinked,
+        folders: vec![tree],
+        videos: scan_result.videos,
+        errors: scan_result.errors,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn folders_waiting_for_a_scan_are_queued_once() {
+        let scans = ScanQueue::new();
+        let first = scans.enqueue("/videos");
+        assert_eq!(scans.enqueue("/videos").id, first.id);
+        let other = scans.enqueue("/other");
+        assert_ne!(other.id, first.id);
+        assert_eq!(scans.jobs().len(), 2);
+
+        // Changes made while a scan runs need another one afterwards
+        scans.begin(&first.id).unwrap();
+        let again = scans.enqueue("/videos");
+        assert_ne!(again.id, first.id);
+        let statuses: Vec<ScanJobStatus> = scans.jobs().iter().map(|j| j.status).collect();
+        assert_eq!(statuses, [ScanJobStatus::Running, ScanJobStatus::Queued, ScanJobStatus::Queued]);
+    }
+
+    #[test]
+    fn cancelled_jobs_never_start() {
+        let scans = ScanQueue::new();
+        let job = scans.enqueue("/videos");
+        scans.cancel(&job.id).unwrap();
+
+        let (folder_path, cancelled) = scans.begin(&job.id).unwrap();
+        assert_eq!(folder_path, "/videos");
+        assert!(cancelled.load(Ordering::Relaxed));
+
+        scans.finish(&job.id);
+        assert!(scans.jobs().is_empty());
+        assert!(scans.cancel(&job.id).is_err());
+    }
+
+    #[test]
+    fn cancelling_a_running_scan_stops_the_walk() {
+        let root = std::env::temp_dir().join(format!("videoplayer-scan-cancel-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        for i in 0..200 {
+            let dir = root.join(format!("dir{}", i));
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join("video.mp4"), b"video").unwrap();
+        }
+        let root_str = root.to_string_lossy().to_string();
+        let rules = Arc::new(RuleSet::new(&root_str, &ScanRules::default()).unwrap());
+
+        let scans = ScanQueue::new();
+        let job = scans.enqueue(&root_str);
+        let (folder_path, cancelled) = scans.begin(&job.id).unwrap();
+        let result = scanner::scan_folder_with_progress(&folder_path, 2, &rules, &mut |dirs_visited, _, _| {
+            if dirs_visited == 1 {
+                scans.cancel(&job.id).unwrap();
+            }
+            !cancelled.load(Ordering::Relaxed)
+        });
+
+        assert!(cancelled.load(Ordering::Relaxed));
+        assert!(result.videos.len() < 200);
+        let _ = fs::remove_dir_all(&root);
+    }
+}
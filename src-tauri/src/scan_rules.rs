@@ -0,0 +1,197 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use crate::models::ScanRules;
+
+/// Extensions scanned in every folder, on top of a folder's `extra_extensions`
+pub const VIDEO_EXTENSIONS: &[&str] = &[
+    "mp4", "mkv", "avi", "webm", "mov", "wmv", "flv", "m4v", "mpg", "mpeg", "3gp"
+];
+
+/// File that excludes paths below the directory holding it, one glob per line
+pub const IGNORE_FILE: &str = ".videoignore";
+
+/// `ScanRules` of a mounted folder, compiled for matching.
+///
+/// Glob patterns follow `.gitignore` conventions: they are matched against
+/// the path relative to the mounted folder (or to the `.videoignore` they come
+/// from), `*` doesn't cross directory boundaries, a pattern without a `/`
+/// matches a name at any depth and a leading `/` anchors it to the top.
+pub struct RuleSet {
+    root: PathBuf,
+    extensions: HashSet<String>,
+    include: Option<GlobSet>,
+    exclude: GlobSet,
+    min_file_size: u64,
+    include_hidden: bool,
+    pub follow_symlinks: bool,
+}
+
+impl RuleSet {
+    pub fn new(root: &str, rules: &ScanRules) -> Result<Self, String> {
+        let extensions = VIDEO_EXTENSIONS.iter()
+            .map(|ext| ext.to_string())
+            .chain(rules.extra_extensions.iter().map(|ext| ext.trim().trim_start_matches('.').to_lowercase()))
+            .filter(|ext| !ext.is_empty())
+            .collect();
+        let include = if rules.include_patterns.is_empty() {
+            None
+        } else {
+            Some(compile(&rules.include_patterns)?)
+        };
+
+        Ok(RuleSet {
+            root: PathBuf::from(root),
+            extensions,
+            include,
+            exclude: compile(&rules.exclude_patterns)?,
+            min_file_size: rules.min_file_size,
+            include_hidden: rules.include_hidden,
+            follow_symlinks: rules.follow_symlinks,
+        })
+    }
+
+    pub fn is_video_file(&self, path: &Path) -> bool {
+        path.extension()
+            .map(|ext| self.extensions.contains(&ext.to_string_lossy().to_lowercase()))
+            .unwrap_or(false)
+    }
+
+    /// Whether a file or directory is skipped by the hidden and exclude rules.
+    /// `.videoignore` files are checked separately since they depend on where
+    /// the walk has been.
+    pub fn is_excluded(&self, path: &Path) -> bool {
+        let hidden = path.file_name()
+            .map(|name| name.to_string_lossy().starts_with('.'))
+            .unwrap_or(false);
+        if hidden && !self.include_hidden {
+            return true;
+        }
+        relative_path(&self.root, path)
+            .map(|relative| self.exclude.is_match(relative))
+            .unwrap_or(false)
+    }
+
+    /// Whether a video file of `size` bytes passes the include and size rules
+    pub fn accepts_file(&self, path: &Path, size: u64) -> bool {
+        if size < self.min_file_size {
+            return false;
+        }
+        match (&self.include, relative_path(&self.root, path)) {
+            (Some(include), Some(relative)) => include.is_match(relative),
+            (Some(_), None) => false,
+            (None, _) => true,
+        }
+    }
+
+    /// Whether a scan would pick up the video file at `path`, depth aside
+    pub fn includes(&self, path: &Path) -> bool {
+        let Ok(metadata) = fs::metadata(path) else {
+            return false;
+        };
+        self.is_video_file(path) && self.accepts_file(path, metadata.len()) && self.allows(path)
+    }
+
+    /// Whether `path` would be reached by a scan: neither it nor any folder
+    /// between it and the mounted folder is excluded or ignored. For use
+    /// outside a walk, e.g. on a single changed file.
+    pub fn allows(&self, path: &Path) -> bool {
+        let Ok(relative) = path.strip_prefix(&self.root) else {
+            return false;
+        };
+        let mut ignore_files: Vec<IgnoreFile> = IgnoreFile::load(&self.root).into_iter().collect();
+        let mut current = self.root.clone();
+        for component in relative.components() {
+            current.push(component);
+            if self.is_excluded(&current) || ignore_files.iter().any(|f| f.matches(&current)) {
+                return false;
+            }
+            ignore_files.extend(IgnoreFile::load(&current));
+        }
+        true
+    }
+
+    /// `.videoignore` files that apply inside `dir` but live above it, for a
+    /// walk that starts below the mounted folder
+    pub fn ignore_files_above(&self, dir: &Path) -> Vec<IgnoreFile> {
+        let Ok(relative) = dir.strip_prefix(&self.root) else {
+            return Vec::new();
+        };
+        let mut ignore_files = Vec::new();
+        let mut current = self.root.clone();
+        for component in relative.components() {
+            ignore_files.extend(IgnoreFile::load(&current));
+            current.push(component);
+        }
+        ignore_files
+    }
+}
+
+/// Patterns of one `.videoignore` file
+pub struct IgnoreFile {
+    dir: PathBuf,
+    patterns: GlobSet,
+}
+
+impl IgnoreFile {
+    /// Read `dir/.videoignore`. Blank lines and lines starting with `#` are
+    /// skipped, as are invalid patterns, so one typo doesn't stop a scan.
+    pub fn load(dir: &Path) -> Option<IgnoreFile> {
+        let contents = fs::read_to_string(dir.join(IGNORE_FILE)).ok()?;
+        let mut builder = GlobSetBuilder::new();
+        for line in contents.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Ok(glob) = glob(line) {
+                builder.add(glob);
+            }
+        }
+        Some(IgnoreFile {
+            dir: dir.to_path_buf(),
+            patterns: builder.build().ok()?,
+        })
+    }
+
+    pub fn matches(&self, path: &Path) -> bool {
+        relative_path(&self.dir, path)
+            .map(|relative| self.patterns.is_match(relative))
+            .unwrap_or(false)
+    }
+}
+
+fn compile(patterns: &[String]) -> Result<GlobSet, String> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns.iter().map(|p| p.trim()).filter(|p| !p.is_empty()) {
+        builder.add(glob(pattern).map_err(|e| format!("Invalid pattern {}: {}", pattern, e))?);
+    }
+    builder.build().map_err(|e| e.to_string())
+}
+
+fn glob(pattern: &str) -> Result<globset::Glob, globset::Error> {
+    // A trailing slash only marks a directory; directories and files are matched alike
+    let pattern = pattern.trim_end_matches('/');
+    let pattern = match pattern.strip_prefix('/') {
+        Some(anchored) => anchored.to_string(),
+        None if !pattern.contains('/') => format!("**/{}", pattern),
+        None => pattern.to_string(),
+    };
+    GlobBuilder::new(&pattern).literal_separator(true).build()
+}
+
+/// `path` relative to `base` with `/` separators on every platform
+fn relative_path(base: &Path, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(base).ok()?;
+    let parts: Vec<String> = relative.components()
+        .filter_map(|c| match c {
+            Component::Normal(name) => Some(name.to_string_lossy().to_string()),
+            _ => None,
+        })
+        .collect();
+    if parts.is_empty() {
+        None
+    } else {
+        Some(parts.join("/"))
+    }
+}
@@ -1,101 +1,396 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsString;
+use std::fs::{self, Metadata};
+use std::io;
 use std::path::{Path, PathBuf};
-use std::fs;
-use crate::models::{Video, FolderNode, ScanResult};
-
-const VIDEO_EXTENSIONS: &[&str] = &[
-    "mp4", "mkv", "avi", "webm", "mov", "wmv", "flv", "m4v", "mpg", "mpeg", "3gp"
-];
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, UNIX_EPOCH};
+use crate::availability;
+use crate::models::{Availability, ScanError, ScanErrorKind, Video, ScanResult};
+use crate::scan_rules::{IgnoreFile, RuleSet, VIDEO_EXTENSIONS};
 
 const IMAGE_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "webp"];
 
+/// Directories read at the same time. Walking is bound by I/O rather than
+/// CPU, so this is about keeping slow (network) disks busy.
+const WALKER_THREADS: usize = 8;
+
+/// How long reading a single directory may take before it is reported and
+/// skipped, so one hung network share can't stall the whole scan
+const READ_DIR_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How often a scan waiting on slow directories checks for cancellation and
+/// for reads that ran past `READ_DIR_TIMEOUT`
+const CANCEL_POLL: Duration = Duration::from_millis(100);
+
+/// Called as each directory has been read with the number of directories
+/// visited and videos found so far; returning `false` stops the scan
+pub type ProgressCallback<'a> = &'a mut dyn FnMut(usize, usize, &str) -> bool;
+
+/// A directory waiting to be read
+struct DirJob {
+    path: PathBuf,
+    depth: usize,
+    /// Device id of the volume the directory is on, where the platform has one
+    device: Option<u64>,
+    /// `.videoignore` files of the directories above it, outermost first
+    ignore_files: Vec<Arc<IgnoreFile>>,
+}
+
+/// What was found in one directory
+#[derive(Default)]
+struct DirListing {
+    videos: Vec<Video>,
+    subdirs: Vec<DirJob>,
+    errors: Vec<ScanError>,
+}
+
+impl DirListing {
+    fn failed(error: ScanError) -> Self {
+        DirListing {
+            errors: vec![error],
+            ..Default::default()
+        }
+    }
+}
+
+/// A directory a walker is reading right now
+struct InFlight {
+    path: PathBuf,
+    device: Option<u64>,
+    started: Instant,
+}
+
+/// Settings and state shared by the walker threads of one scan
+struct Walk {
+    rules: Arc<RuleSet>,
+    max_depth: usize,
+    /// Canonical paths of directories already walked, so symlink loops end
+    visited: Mutex<HashSet<PathBuf>>,
+    stopped: AtomicBool,
+    /// What each walker is reading, by walker id. A walker that was given up
+    /// on is removed from here and exits once its read returns.
+    in_flight: Mutex<HashMap<usize, InFlight>>,
+    /// Device ids of volumes on which a directory stopped responding; nothing
+    /// else on them is read. Without a device id only the hung directory's
+    /// subtree is lost, as it is never listed.
+    stalled: Mutex<HashSet<u64>>,
+}
+
+type WalkResult = (PathBuf, DirListing);
+
 /// Scan a folder for videos with specified depth
-pub fn scan_folder(folder_path: &str, max_depth: usize) -> ScanResult {
+pub fn scan_folder(folder_path: &str, max_depth: usize, rules: &Arc<RuleSet>) -> ScanResult {
+    scan_folder_with_progress(folder_path, max_depth, rules, &mut |_, _, _| true)
+}
+
+/// Like `scan_folder`, reporting progress as it goes. A scan stopped by the
+/// callback returns what was found up to that point. `folders` is left empty;
+/// the folder tree is built from the database once the scan is stored.
+///
+/// Directories are read by a bounded pool of threads; the calling thread
+/// hands them out and collects what was found, so progress is reported from
+/// one place. Directories that can't be read are listed in `errors` and
+/// skipped. A read that hangs (e.g. on a dropped network mount) can't be
+/// interrupted, so after `READ_DIR_TIMEOUT` its walker is left behind and
+/// replaced, and the rest of that volume is skipped rather than handed to
+/// more walkers that would hang as well. Where volumes can't be told apart
+/// only the directory that hung is skipped.
+pub fn scan_folder_with_progress(folder_path: &str, max_depth: usize, rules: &Arc<RuleSet>, on_directory: ProgressCallback) -> ScanResult {
+    let walk = Arc::new(Walk {
+        rules: rules.clone(),
+        max_depth,
+        visited: Mutex::new(HashSet::new()),
+        stopped: AtomicBool::new(false),
+        in_flight: Mutex::new(HashMap::new()),
+        stalled: Mutex::new(HashSet::new()),
+    });
+    let (job_sender, job_receiver) = mpsc::channel::<DirJob>();
+    let job_receiver = Arc::new(Mutex::new(job_receiver));
+    let (result_sender, result_receiver) = mpsc::channel::<WalkResult>();
+    
     let mut videos: Vec<Video> = Vec::new();
-    let mut folder_video_counts: HashMap<String, usize> = HashMap::new();
+    let mut errors: Vec<ScanError> = Vec::new();
     
-    // Scan with user-specified depth
-    scan_directory_shallow(folder_path, &mut videos, &mut folder_video_counts, 0, max_depth);
+    // Walker ids, and how many walkers are still taking directories
+    let mut next_id = 0;
+    let mut live = 0;
+    let mut spawn_walker = |live: &mut usize, errors: &mut Vec<ScanError>| {
+        let (walk, jobs, results) = (walk.clone(), job_receiver.clone(), result_sender.clone());
+        let id = next_id;
+        next_id += 1;
+        let spawned = thread::Builder::new()
+            .name("scan-walker".to_string())
+            .spawn(move || run_walker(id, &walk, &jobs, results));
+        match spawned {
+            Ok(_) => *live += 1,
+            Err(e) => errors.push(scan_error(Path::new(folder_path), ScanErrorKind::ReadDir, &e)),
+        }
+    };
+    for _ in 0..WALKER_THREADS {
+        spawn_walker(&mut live, &mut errors);
+    }
     
-    // Build folder tree
-    let folder_tree = build_folder_tree(folder_path, &folder_video_counts);
+    // A walk can start below the mounted folder, e.g. from the watcher
+    let root = DirJob {
+        path: PathBuf::from(folder_path),
+        depth: 0,
+        device: availability::device_id(Path::new(folder_path)),
+        ignore_files: rules.ignore_files_above(Path::new(folder_path)).into_iter().map(Arc::new).collect(),
+    };
+    let _ = job_sender.send(root);
+    let mut pending = 1;
+    let mut dirs_visited = 0;
+    
+    while pending > 0 && live > 0 {
+        let (path, listing) = match result_receiver.recv_timeout(CANCEL_POLL) {
+            Ok(result) => result,
+            Err(RecvTimeoutError::Timeout) => {
+                // Give up on hung reads; after a stop, on every read still running
+                let stopped = walk.stopped.load(Ordering::Relaxed);
+                let abandoned: Vec<InFlight> = {
+                    let mut in_flight = walk.in_flight.lock().unwrap();
+                    let overdue: Vec<usize> = in_flight.iter()
+                        .filter(|(_, read)| stopped || read.started.elapsed() >= READ_DIR_TIMEOUT)
+                        .map(|(id, _)| *id)
+                        .collect();
+                    overdue.iter().filter_map(|id| in_flight.remove(id)).collect()
+                };
+                for read in abandoned {
+                    pending -= 1;
+                    live -= 1;
+                    if !stopped {
+                        if let Some(device) = read.device {
+                            walk.stalled.lock().unwrap().insert(device);
+                        }
+                        errors.push(ScanError {
+                            path: read.path.to_string_lossy().to_string(),
+                            kind: ScanErrorKind::Timeout,
+                            message: format!("No response after {} seconds", READ_DIR_TIMEOUT.as_secs()),
+                            os_error: None,
+                        });
+                        spawn_walker(&mut live, &mut errors);
+                    }
+                }
+                continue;
+            }
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
+        pending -= 1;
+        dirs_visited += 1;
+        
+        videos.extend(listing.videos);
+        errors.extend(listing.errors);
+        
+        // After a stop, only wait for the directories already handed out
+        if walk.stopped.load(Ordering::Relaxed) {
+            continue;
+        }
+        if !on_directory(dirs_visited, videos.len(), &path.to_string_lossy()) {
+            walk.stopped.store(true, Ordering::Relaxed);
+            continue;
+        }
+        for subdir in listing.subdirs {
+            pending += 1;
+            let _ = job_sender.send(subdir);
+        }
+    }
+    
+    // Walkers exit once the queue is closed; ones left behind on a hung read
+    // exit when it returns
+    drop(job_sender);
+    
+    // Threads finish in any order; keep results stable between scans
+    videos.sort_by(|a, b| a.path.cmp(&b.path));
+    errors.sort_by(|a, b| a.path.cmp(&b.path));
     
     ScanResult {
         total_videos: videos.len(),
         new_videos: videos.len(),
-        folders: vec![folder_tree],
-        videos: videos.clone(),
+        updated_videos: 0,
+        removed_videos: 0,
+        relinked: Vec::new(),
+        folders: Vec::new(),
+        videos,
+        errors,
     }
 }
 
-/// Scan directory with limited depth
-fn scan_directory_shallow(
-    dir_path: &str, 
-    videos: &mut Vec<Video>, 
-    folder_counts: &mut HashMap<String, usize>,
-    current_depth: usize,
-    max_depth: usize
-) {
-    if current_depth > max_depth {
-        return;
+fn run_walker(id: usize, walk: &Walk, jobs: &Mutex<Receiver<DirJob>>, results: Sender<WalkResult>) {
+    loop {
+        let job = match jobs.lock().unwrap().recv() {
+            Ok(job) => job,
+            Err(_) => return,
+        };
+        let path = job.path.clone();
+        
+        let listing = if walk.stopped.load(Ordering::Relaxed) {
+            DirListing::default()
+        } else if job.device.is_some_and(|device| walk.stalled.lock().unwrap().contains(&device)) {
+            DirListing::failed(ScanError {
+                path: path.to_string_lossy().to_string(),
+                kind: ScanErrorKind::Timeout,
+                message: "Skipped: another directory on this volume stopped responding".to_string(),
+                os_error: None,
+            })
+        } else {
+            walk.in_flight.lock().unwrap().insert(id, InFlight {
+                path: path.clone(),
+                device: job.device,
+                started: Instant::now(),
+            });
+            let listing = read_directory(&walk.rules, &walk.visited, walk.max_depth, job);
+            // The scan gave up on this read and reported it already
+            if walk.in_flight.lock().unwrap().remove(&id).is_none() {
+                return;
+            }
+            listing
+        };
+        if results.send((path, listing)).is_err() {
+            return;
+        }
+    }
+}
+
+/// List one directory, applying the folder's scan rules. Whatever can't be
+/// read is skipped and reported in the listing's `errors`.
+fn read_directory(rules: &RuleSet, visited: &Mutex<HashSet<PathBuf>>, max_depth: usize, job: DirJob) -> DirListing {
+    if rules.follow_symlinks {
+        // A link back to a parent would otherwise be walked until the depth limit
+        let real_path = match fs::canonicalize(&job.path) {
+            Ok(real_path) => real_path,
+            Err(e) => return DirListing::failed(scan_error(&job.path, ScanErrorKind::Symlink, &e)),
+        };
+        if !visited.lock().unwrap().insert(real_path) {
+            return DirListing::default();
+        }
     }
     
-    let dir = match fs::read_dir(dir_path) {
-        Ok(d) => d,
-        Err(_) => return,
-    };
+    let mut listing = DirListing::default();
+    let mut entries: Vec<fs::DirEntry> = Vec::new();
+    match fs::read_dir(&job.path) {
+        Ok(read_dir) => {
+            for entry in read_dir {
+                match entry {
+                    Ok(entry) => entries.push(entry),
+                    Err(e) => listing.errors.push(scan_error(&job.path, ScanErrorKind::ReadDir, &e)),
+                }
+            }
+        }
+        Err(e) => return DirListing::failed(scan_error(&job.path, ScanErrorKind::ReadDir, &e)),
+    }
+    
+    let mut ignore_files = job.ignore_files;
+    ignore_files.extend(IgnoreFile::load(&job.path).map(Arc::new));
     
-    for entry in dir.filter_map(|e| e.ok()) {
+    // Sidecar thumbnails are looked up in the listing instead of with a stat each
+    let names: HashSet<OsString> = entries.iter().map(|e| e.file_name()).collect();
+    
+    for entry in entries {
         let path = entry.path();
         
-        // Skip hidden files/folders and system folders
-        if let Some(name) = path.file_name() {
-            let name_str = name.to_string_lossy();
-            if name_str.starts_with('.') || 
-               name_str == "node_modules" ||
-               name_str == "Library" ||
-               name_str == ".Trash" {
+        // Skip hidden, excluded and .videoignore'd files and folders
+        if rules.is_excluded(&path) || ignore_files.iter().any(|f| f.matches(&path)) {
+            continue;
+        }
+        let file_type = match entry.file_type() {
+            Ok(file_type) => file_type,
+            Err(e) => {
+                listing.errors.push(scan_error(&path, ScanErrorKind::Metadata, &e));
                 continue;
             }
+        };
+        // The entry's own type of a symlink says nothing about its target
+        let (is_file, is_dir) = if file_type.is_symlink() {
+            match fs::metadata(&path) {
+                Ok(target) => (target.is_file(), target.is_dir()),
+                Err(e) => {
+                    listing.errors.push(scan_error(&path, ScanErrorKind::Symlink, &e));
+                    continue;
+                }
+            }
+        } else {
+            (file_type.is_file(), file_type.is_dir())
+        };
+        
+        // A lossily converted path would point at a file that doesn't exist
+        if path.to_str().is_none() && (is_dir || (is_file && rules.is_video_file(&path))) {
+            listing.errors.push(ScanError {
+                path: path.to_string_lossy().to_string(),
+                kind: ScanErrorKind::InvalidName,
+                message: "Name is not valid UTF-8".to_string(),
+                os_error: None,
+            });
+            continue;
         }
         
-        if path.is_file() {
-            if let Some(ext) = path.extension() {
-                let ext_str = ext.to_string_lossy().to_lowercase();
-                if VIDEO_EXTENSIONS.contains(&ext_str.as_str()) {
-                    if let Some(video) = create_video_from_path(&path) {
-                        *folder_counts.entry(video.folder_path.clone()).or_insert(0) += 1;
-                        videos.push(video);
-                    }
+        if is_file {
+            // Only videos are stat'ed; other files are judged by name alone
+            if !rules.is_video_file(&path) {
+                continue;
+            }
+            let metadata = match fs::metadata(&path) {
+                Ok(metadata) => metadata,
+                Err(e) => {
+                    listing.errors.push(scan_error(&path, ScanErrorKind::Metadata, &e));
+                    continue;
+                }
+            };
+            if rules.accepts_file(&path, metadata.len()) {
+                let thumbnail_path = find_thumbnail_in_listing(&path, &names);
+                if let Some(video) = video_from_metadata(&path, &metadata, thumbnail_path) {
+                    listing.videos.push(video);
                 }
             }
-        } else if path.is_dir() && current_depth < max_depth {
-            // Only scan subdirectories if within depth limit
-            // Don't follow symlinks
-            if !path.is_symlink() {
-                scan_directory_shallow(
-                    &path.to_string_lossy(), 
-                    videos, 
-                    folder_counts, 
-                    current_depth + 1,
-                    max_depth
-                );
+        } else if is_dir && job.depth < max_depth {
+            // Only scan subdirectories if within depth limit, and only
+            // follow symlinks when the folder's rules allow it
+            if rules.follow_symlinks || !file_type.is_symlink() {
+                listing.subdirs.push(DirJob {
+                    device: availability::device_id(&path),
+                    path,
+                    depth: job.depth + 1,
+                    ignore_files: ignore_files.clone(),
+                });
             }
         }
     }
+    
+    listing
+}
+
+fn scan_error(path: &Path, kind: ScanErrorKind, error: &io::Error) -> ScanError {
+    ScanError {
+        path: path.to_string_lossy().to_string(),
+        kind,
+        message: error.to_string(),
+        os_error: error.raw_os_error(),
+    }
 }
 
 pub fn create_video_from_path(path: &Path) -> Option<Video> {
+    let metadata = std::fs::metadata(path).ok()?;
+    
+    // Check for existing thumbnail
+    let thumbnail_path = find_thumbnail_for_video(path);
+    
+    video_from_metadata(path, &metadata, thumbnail_path)
+}
+
+fn video_from_metadata(path: &Path, metadata: &Metadata, thumbnail_path: Option<String>) -> Option<Video> {
     let filename = path.file_name()?.to_string_lossy().to_string();
     let folder_path = path.parent()?.to_string_lossy().to_string();
     let path_str = path.to_string_lossy().to_string();
     
-    let size = std::fs::metadata(path).ok()?.len();
+    let size = metadata.len();
+    let mtime = metadata.modified().ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs() as i64);
     let now = chrono::Utc::now().to_rfc3339();
     
-    // Check for existing thumbnail
-    let thumbnail_path = find_thumbnail_for_video(path);
-    
     Some(Video {
         id: uuid::Uuid::new_v4().to_string(),
         path: path_str,
@@ -104,11 +399,29 @@ pub fn create_video_from_path(path: &Path) -> Option<Video> {
         size,
         duration: None,
         thumbnail_path,
+        mtime,
         created_at: now.clone(),
         updated_at: now,
+        availability: Availability::Online,
     })
 }
 
+/// Like `find_thumbnail_for_video`, checking the names of the video's
+/// directory as already listed
+fn find_thumbnail_in_listing(video_path: &Path, names: &HashSet<OsString>) -> Option<String> {
+    let stem = video_path.file_stem()?;
+    let parent = video_path.parent()?;
+    
+    for ext in IMAGE_EXTENSIONS {
+        let name = format!("{}.{}", stem.to_string_lossy(), ext);
+        if names.contains(&OsString::from(&name)) {
+            return Some(parent.join(name).to_string_lossy().to_string());
+        }
+    }
+    
+    None
+}
+
 pub fn find_thumbnail_for_video(video_path: &Path) -> Option<String> {
     let stem = video_path.file_stem()?;
     let parent = video_path.parent()?;
@@ -123,93 +436,279 @@ pub fn find_thumbnail_for_video(video_path: &Path) -> Option<String> {
     None
 }
 
-fn build_folder_tree(root_path: &str, video_counts: &HashMap<String, usize>) -> FolderNode {
-    let root = Path::new(root_path);
-    let name = root.file_name()
-        .map(|n| n.to_string_lossy().to_string())
-        .unwrap_or_else(|| root_path.to_string());
-    
-    let mut children: Vec<FolderNode> = Vec::new();
+pub fn get_videos_in_folder(folder_path: &str) -> Vec<PathBuf> {
+    let mut videos = Vec::new();
     
-    // Read immediate children only
-    if let Ok(entries) = std::fs::read_dir(root) {
+    // Only scan the specific folder, not recursively
+    if let Ok(entries) = fs::read_dir(folder_path) {
         for entry in entries.filter_map(|e| e.ok()) {
             let path = entry.path();
             
-            // Skip hidden folders
-            if let Some(fname) = path.file_name() {
-                if fname.to_string_lossy().starts_with('.') {
-                    continue;
-                }
-            }
-            
-            if path.is_dir() && !path.is_symlink() {
-                let child_path = path.to_string_lossy().to_string();
-                let child_name = path.file_name()
-                    .map(|n| n.to_string_lossy().to_string())
-                    .unwrap_or_default();
-                
-                // Count videos in this folder and its immediate children
-                let video_count = count_videos_in_path(&child_path, video_counts);
-                
-                if video_count > 0 {
-                    children.push(FolderNode {
-                        path: child_path,
-                        name: child_name,
-                        children: Vec::new(),
-                        video_count,
-                    });
-                }
+            let is_video = path.extension()
+                .map(|ext| VIDEO_EXTENSIONS.contains(&ext.to_string_lossy().to_lowercase().as_str()))
+                .unwrap_or(false);
+            if path.is_file() && is_video {
+                videos.push(path);
             }
         }
     }
     
-    // Sort children by name
-    children.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
-    
-    // Calculate root video count
-    let direct_count = video_counts.get(root_path).copied().unwrap_or(0);
-    let children_count: usize = children.iter().map(|c| c.video_count).sum();
-    
-    FolderNode {
-        path: root_path.to_string(),
-        name,
-        children,
-        video_count: direct_count + children_count,
-    }
+    videos
 }
 
-fn count_videos_in_path(folder_path: &str, video_counts: &HashMap<String, usize>) -> usize {
-    let mut count = video_counts.get(folder_path).copied().unwrap_or(0);
-    
-    // Also count videos in subfolders that match this path prefix
-    for (path, video_count) in video_counts {
-        if path.starts_with(folder_path) && path != folder_path {
-            count += video_count;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ScanRules;
+    use crate::scan_rules::IGNORE_FILE;
+
+    /// An empty directory to build a library in, removed when dropped
+    struct TempTree(PathBuf);
+
+    impl Drop for TempTree {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
         }
     }
-    
-    count
-}
 
-pub fn get_videos_in_folder(folder_path: &str) -> Vec<PathBuf> {
-    let mut videos = Vec::new();
-    
-    // Only scan the specific folder, not recursively
-    if let Ok(entries) = fs::read_dir(folder_path) {
-        for entry in entries.filter_map(|e| e.ok()) {
-            let path = entry.path();
-            
-            if path.is_file() {
-                if let Some(ext) = path.extension() {
-                    let ext_str = ext.to_string_lossy().to_lowercase();
-                    if VIDEO_EXTENSIONS.contains(&ext_str.as_str()) {
-                        videos.push(path);
-                    }
+    impl std::ops::Deref for TempTree {
+        type Target = Path;
+
+        fn deref(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    impl AsRef<Path> for TempTree {
+        fn as_ref(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    fn temp_tree(name: &str) -> TempTree {
+        let dir = std::env::temp_dir().join(format!("videoplayer-scanner-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        TempTree(dir)
+    }
+
+    /// Create `relative` below `root` with `size` bytes, along with its folders
+    fn write_file(root: &Path, relative: &str, size: usize) {
+        let path = root.join(relative);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, vec![0u8; size]).unwrap();
+    }
+
+    /// Paths found by a scan, relative to `root` and sorted
+    fn scan(root: &Path, rules: &ScanRules) -> Vec<String> {
+        let root_str = root.to_string_lossy().to_string();
+        let rule_set = Arc::new(RuleSet::new(&root_str, rules).unwrap());
+        let mut found: Vec<String> = scan_folder(&root_str, 5, &rule_set).videos
+            .iter()
+            .map(|v| Path::new(&v.path).strip_prefix(root).unwrap().to_string_lossy().replace('\\', "/"))
+            .collect();
+        found.sort();
+        found
+    }
+
+    #[test]
+    fn extra_extensions_are_scanned() {
+        let root = temp_tree("extensions");
+        write_file(&root, "movie.mkv", 10);
+        write_file(&root, "broadcast.TS", 10);
+        write_file(&root, "disc/00001.m2ts", 10);
+        write_file(&root, "notes.txt", 10);
+
+        assert_eq!(scan(&root, &ScanRules::default()), vec!["movie.mkv"]);
+
+        let rules = ScanRules {
+            extra_extensions: vec!["ts".into(), ".m2ts".into()],
+            ..ScanRules::default()
+        };
+        assert_eq!(scan(&root, &rules), vec!["broadcast.TS", "disc/00001.m2ts", "movie.mkv"]);
+    }
+
+    #[test]
+    fn include_patterns_limit_the_scan() {
+        let root = temp_tree("include");
+        write_file(&root, "Movies/a.mkv", 10);
+        write_file(&root, "Movies/extras/b.mkv", 10);
+        write_file(&root, "Clips/c.mp4", 10);
+
+        let rules = ScanRules {
+            include_patterns: vec!["Movies/*".into()],
+            ..ScanRules::default()
+        };
+        assert_eq!(scan(&root, &rules), vec!["Movies/a.mkv"]);
+
+        let rules = ScanRules {
+            include_patterns: vec!["*.mp4".into()],
+            ..ScanRules::default()
+        };
+        assert_eq!(scan(&root, &rules), vec!["Clips/c.mp4"]);
+    }
+
+    #[test]
+    fn exclude_patterns_skip_files_and_folders() {
+        let root = temp_tree("exclude");
+        write_file(&root, "a.mkv", 10);
+        write_file(&root, "a.sample.mkv", 10);
+        write_file(&root, "Extras/b.mkv", 10);
+        write_file(&root, "Show/Extras/c.mkv", 10);
+        write_file(&root, "node_modules/d.mkv", 10);
+
+        let rules = ScanRules {
+            exclude_patterns: vec!["*.sample.*".into(), "/Extras/".into()],
+            ..ScanRules::default()
+        };
+        // Replacing the defaults scans node_modules again
+        assert_eq!(scan(&root, &rules), vec!["Show/Extras/c.mkv", "a.mkv", "node_modules/d.mkv"]);
+        assert_eq!(scan(&root, &ScanRules::default()).len(), 4);
+    }
+
+    #[test]
+    fn videoignore_files_apply_below_their_folder() {
+        let root = temp_tree("videoignore");
+        write_file(&root, "a.mkv", 10);
+        write_file(&root, "Trailers/t.mkv", 10);
+        write_file(&root, "Show/e01.mkv", 10);
+        write_file(&root, "Show/e01.preview.mkv", 10);
+        write_file(&root, "Other/x.preview.mkv", 10);
+        fs::write(root.join(IGNORE_FILE), "# top level\nTrailers/\n").unwrap();
+        fs::write(root.join("Show").join(IGNORE_FILE), "*.preview.mkv\n").unwrap();
+
+        assert_eq!(scan(&root, &ScanRules::default()), vec!["Other/x.preview.mkv", "Show/e01.mkv", "a.mkv"]);
+
+        // A walk that starts below the mounted folder still honors the files above it
+        let rule_set = Arc::new(RuleSet::new(&root.to_string_lossy(), &ScanRules::default()).unwrap());
+        assert!(!rule_set.allows(&root.join("Trailers").join("t.mkv")));
+        assert!(!rule_set.allows(&root.join("Show").join("e01.preview.mkv")));
+        assert!(rule_set.allows(&root.join("Show").join("e01.mkv")));
+        let show = scan_folder(&root.join("Show").to_string_lossy(), 1, &rule_set);
+        assert_eq!(show.videos.len(), 1);
+    }
+
+    #[test]
+    fn small_files_are_skipped() {
+        let root = temp_tree("min-size");
+        write_file(&root, "movie.mkv", 4096);
+        write_file(&root, "Sample/movie-sample.mkv", 100);
+
+        let rules = ScanRules {
+            min_file_size: 1024,
+            ..ScanRules::default()
+        };
+        assert_eq!(scan(&root, &rules), vec!["movie.mkv"]);
+        assert_eq!(scan(&root, &ScanRules::default()).len(), 2);
+    }
+
+    #[test]
+    fn hidden_files_are_opt_in() {
+        let root = temp_tree("hidden");
+        write_file(&root, "a.mkv", 10);
+        write_file(&root, ".b.mkv", 10);
+        write_file(&root, ".private/c.mkv", 10);
+
+        assert_eq!(scan(&root, &ScanRules::default()), vec!["a.mkv"]);
+
+        let rules = ScanRules {
+            include_hidden: true,
+            ..ScanRules::default()
+        };
+        assert_eq!(scan(&root, &rules), vec![".b.mkv", ".private/c.mkv", "a.mkv"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks_are_followed_once() {
+        let root = temp_tree("symlinks");
+        let elsewhere = temp_tree("symlinks-target");
+        write_file(&root, "a.mkv", 10);
+        write_file(&elsewhere, "b.mkv", 10);
+        std::os::unix::fs::symlink(&elsewhere, root.join("linked")).unwrap();
+        // A loop back to the mounted folder
+        std::os::unix::fs::symlink(&root, root.join("linked-root")).unwrap();
+
+        assert_eq!(scan(&root, &ScanRules::default()), vec!["a.mkv"]);
+
+        let rules = ScanRules {
+            follow_symlinks: true,
+            ..ScanRules::default()
+        };
+        assert_eq!(scan(&root, &rules), vec!["a.mkv", "linked/b.mkv"]);
+    }
+
+    #[test]
+    fn unreadable_directories_are_reported() {
+        let dir = temp_tree("unreadable");
+        let root = dir.join("gone");
+        let rule_set = Arc::new(RuleSet::new(&root.to_string_lossy(), &ScanRules::default()).unwrap());
+
+        let result = scan_folder(&root.to_string_lossy(), 2, &rule_set);
+        assert!(result.videos.is_empty());
+        assert_eq!(result.errors.len(), 1);
+        assert_eq!(result.errors[0].path, root.to_string_lossy());
+        assert_eq!(result.errors[0].kind, ScanErrorKind::ReadDir);
+        assert!(result.errors[0].os_error.is_some());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn broken_links_and_invalid_names_are_reported() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let root = temp_tree("diagnostics");
+        write_file(&root, "a.mkv", 10);
+        std::os::unix::fs::symlink(root.join("gone.mkv"), root.join("link.mkv")).unwrap();
+        let invalid = root.join(std::ffi::OsStr::from_bytes(b"bad-\xff.mkv"));
+        // Some filesystems refuse names that aren't UTF-8
+        let has_invalid = fs::write(&invalid, b"").is_ok();
+
+        let rule_set = Arc::new(RuleSet::new(&root.to_string_lossy(), &ScanRules::default()).unwrap());
+        let result = scan_folder(&root.to_string_lossy(), 1, &rule_set);
+        assert_eq!(result.videos.len(), 1);
+
+        let link = result.errors.iter().find(|e| e.path.ends_with("link.mkv")).unwrap();
+        assert_eq!(link.kind, ScanErrorKind::Symlink);
+        assert!(link.os_error.is_some());
+        if has_invalid {
+            assert!(result.errors.iter().any(|e| e.kind == ScanErrorKind::InvalidName));
+        }
+    }
+
+    /// Thousands of files over a few hundred directories, with a sidecar
+    /// file next to every video
+    #[test]
+    fn walks_a_large_tree() {
+        let root = temp_tree("large");
+        for show in 0..20 {
+            for season in 0..10 {
+                let dir = root.join(format!("show-{:02}", show)).join(format!("season-{:02}", season));
+                fs::create_dir_all(&dir).unwrap();
+                for episode in 0..20 {
+                    fs::write(dir.join(format!("e{:02}.mkv", episode)), b"").unwrap();
+                    fs::write(dir.join(format!("e{:02}.nfo", episode)), b"").unwrap();
                 }
+                fs::write(dir.join("e00.jpg"), b"").unwrap();
             }
         }
+        let rule_set = Arc::new(RuleSet::new(&root.to_string_lossy(), &ScanRules::default()).unwrap());
+
+        let mut progress_calls = 0;
+        let result = scan_folder_with_progress(&root.to_string_lossy(), 2, &rule_set, &mut |_, _, _| {
+            progress_calls += 1;
+            true
+        });
+
+        assert!(result.errors.is_empty());
+        assert_eq!(result.videos.len(), 4000);
+        assert_eq!(progress_calls, 1 + 20 + 200);
+        assert_eq!(result.videos.iter().filter(|v| v.thumbnail_path.is_some()).count(), 200);
+        // Sorted no matter which thread found what
+        assert!(result.videos.windows(2).all(|w| w[0].path < w[1].path));
+
+        // The depth limit still applies
+        let shallow = scan_folder(&root.to_string_lossy(), 1, &rule_set);
+        assert!(shallow.videos.is_empty());
     }
-    
-    videos
 }
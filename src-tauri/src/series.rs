@@ -0,0 +1,329 @@
+use std::path::Path;
+
+/// What a release-style file name says about the episode it holds
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsedEpisode {
+    /// Series title as written, with separators turned into spaces. Empty when
+    /// the name starts with the episode marker, e.g. `S01E02.mkv`.
+    pub title: String,
+    pub year: Option<u32>,
+    pub season: u32,
+    pub episode: u32,
+    /// Last episode of a multi-episode file such as `S01E01E02` or `1x01-02`
+    pub episode_end: Option<u32>,
+    /// e.g. `1080p`
+    pub resolution: Option<String>,
+    pub release_group: Option<String>,
+}
+
+/// An `S02E05` or `2x05` marker, as byte offsets into the name
+struct Marker {
+    start: usize,
+    end: usize,
+    season: u32,
+    episode: u32,
+    episode_end: Option<u32>,
+}
+
+/// Parse a file name such as `Show.Name.S02E05.1080p.WEB-GROUP.mkv` or
+/// `Show Name - 2x05 - Title.mkv`. Returns `None` for names without a season
+/// and episode marker.
+pub fn parse_filename(filename: &str) -> Option<ParsedEpisode> {
+    let stem = Path::new(filename).file_stem()?.to_string_lossy().to_string();
+    let (stem, leading_group) = strip_leading_group(&stem);
+
+    // Dots and underscores stand in for spaces in release names
+    let name: String = stem.chars()
+        .map(|c| if c == '.' || c == '_' { ' ' } else { c })
+        .collect();
+    let marker = find_marker(&name)?;
+
+    let (title, year) = split_year(&clean_title(&name[..marker.start]));
+    let rest = &name[marker.end..];
+    let release_group = leading_group.or_else(|| trailing_group(rest));
+
+    Some(ParsedEpisode {
+        title,
+        year,
+        season: marker.season,
+        episode: marker.episode,
+        episode_end: marker.episode_end,
+        resolution: find_resolution(rest),
+        release_group,
+    })
+}
+
+/// Series title from the folders above a file whose name has no title, e.g.
+/// `Show Name/Season 2/S02E05.mkv`. Season folders are skipped.
+pub fn title_from_folder(folder_path: &str) -> Option<(String, Option<u32>)> {
+    Path::new(folder_path)
+        .ancestors()
+        .filter_map(|dir| dir.file_name())
+        .map(|name| name.to_string_lossy().to_string())
+        .find(|name| !is_season_folder(name))
+        .map(|name| {
+            let name: String = name.chars()
+                .map(|c| if c == '.' || c == '_' { ' ' } else { c })
+                .collect();
+            split_year(&clean_title(&name))
+        })
+        .filter(|(title, _)| !title.is_empty())
+}
+
+/// Key that files of the same series share however their titles are written.
+/// A year is part of the key, so remakes such as `Doctor Who (1963)` and
+/// `Doctor Who (2005)` stay apart.
+pub fn match_key(title: &str, year: Option<u32>) -> String {
+    let words = title.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_lowercase())
+        .collect::<Vec<_>>()
+        .join(" ");
+    match year {
+        Some(year) if !words.is_empty() => format!("{} {}", words, year),
+        _ => words,
+    }
+}
+
+fn is_season_folder(name: &str) -> bool {
+    let lower = name.to_lowercase();
+    let rest = lower.strip_prefix("season")
+        .or_else(|| lower.strip_prefix('s'))
+        .map(|rest| rest.trim_start_matches([' ', '.', '_']));
+    rest.is_some_and(|rest| !rest.is_empty() && rest.chars().all(|c| c.is_ascii_digit()))
+        || lower == "specials"
+}
+
+/// `[Group] Show - 01x02` style names put the release group first
+fn strip_leading_group(stem: &str) -> (String, Option<String>) {
+    if let Some(rest) = stem.strip_prefix('[') {
+        if let Some(end) = rest.find(']') {
+            let group = rest[..end].trim();
+            if !group.is_empty() {
+                return (rest[end + 1..].to_string(), Some(group.to_string()));
+            }
+        }
+    }
+    (stem.to_string(), None)
+}
+
+fn find_marker(name: &str) -> Option<Marker> {
+    let bytes = name.as_bytes();
+    (0..bytes.len())
+        // Markers start a word, so `1920x1080` never reads as season 20
+        .filter(|&i| i == 0 || !bytes[i - 1].is_ascii_alphanumeric())
+        .find_map(|i| season_episode_marker(bytes, i).or_else(|| cross_marker(bytes, i)))
+}
+
+/// `S02E05`, `S02E05E06`, `S02E05-E06` or `S02E05-06`
+fn season_episode_marker(bytes: &[u8], start: usize) -> Option<Marker> {
+    let mut i = start;
+    if !bytes.get(i)?.eq_ignore_ascii_case(&b's') {
+        return None;
+    }
+    i += 1;
+    let (season, next) = digits(bytes, i, 1, 2)?;
+    i = next;
+    if !bytes.get(i)?.eq_ignore_ascii_case(&b'e') {
+        return None;
+    }
+    let (episode, next) = digits(bytes, i + 1, 1, 3)?;
+    i = next;
+
+    let mut episode_end = None;
+    loop {
+        let mut j = i;
+        if bytes.get(j) == Some(&b'-') {
+            j += 1;
+        }
+        if bytes.get(j).is_some_and(|b| b.eq_ignore_ascii_case(&b'e')) {
+            j += 1;
+        } else if j == i {
+            break;
+        }
+        match digits(bytes, j, 1, 3) {
+            Some((end, next)) if end > episode_end.unwrap_or(episode) => {
+                episode_end = Some(end);
+                i = next;
+            }
+            _ => break,
+        }
+    }
+
+    if bytes.get(i).is_some_and(|b| b.is_ascii_alphanumeric()) {
+        return None;
+    }
+    Some(Marker { start, end: i, season, episode, episode_end })
+}
+
+/// `2x05`, `2x05x06` or `2x05-06`
+fn cross_marker(bytes: &[u8], start: usize) -> Option<Marker> {
+    let (season, mut i) = digits(bytes, start, 1, 2)?;
+    if !bytes.get(i)?.eq_ignore_ascii_case(&b'x') {
+        return None;
+    }
+    let (episode, next) = digits(bytes, i + 1, 2, 3)?;
+    i = next;
+
+    let mut episode_end = None;
+    while matches!(bytes.get(i), Some(b'-') | Some(b'x') | Some(b'X')) {
+        match digits(bytes, i + 1, 2, 3) {
+            Some((end, next)) if end > episode_end.unwrap_or(episode) => {
+                episode_end = Some(end);
+                i = next;
+            }
+            _ => break,
+        }
+    }
+
+    if bytes.get(i).is_some_and(|b| b.is_ascii_alphanumeric()) {
+        return None;
+    }
+    Some(Marker { start, end: i, season, episode, episode_end })
+}
+
+/// A run of `min..=max` ASCII digits at `start`, as its value and the offset after it
+fn digits(bytes: &[u8], start: usize, min: usize, max: usize) -> Option<(u32, usize)> {
+    let len = bytes[start.min(bytes.len())..].iter()
+        .take_while(|b| b.is_ascii_digit())
+        .count();
+    if len < min || len > max {
+        return None;
+    }
+    let value = std::str::from_utf8(&bytes[start..start + len]).ok()?.parse().ok()?;
+    Some((value, start + len))
+}
+
+/// Trim the separators around a title and collapse repeated spaces
+fn clean_title(title: &str) -> String {
+    title.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .trim_matches(|c: char| c == '-' || c == ' ' || c == '[' || c == '(')
+        .to_string()
+}
+
+/// Split a trailing year off a title: `Show (2019)` or `Show 2019`. A title
+/// that is only a year (`1923`) is left alone.
+fn split_year(title: &str) -> (String, Option<u32>) {
+    let Some((head, last)) = title.rsplit_once(' ') else {
+        return (title.to_string(), None);
+    };
+    let year = last.trim_matches(|c| c == '(' || c == ')');
+    match year.parse::<u32>() {
+        Ok(value) if year.len() == 4 && (1900..2100).contains(&value) => {
+            (clean_title(head), Some(value))
+        }
+        _ => (title.to_string(), None),
+    }
+}
+
+fn find_resolution(rest: &str) -> Option<String> {
+    rest.split(|c: char| !c.is_ascii_alphanumeric())
+        .map(|token| token.to_lowercase())
+        .find(|token| {
+            let digits = token.trim_end_matches(['p', 'i']);
+            (token == "4k")
+                || (digits.len() < token.len()
+                    && (3..=4).contains(&digits.len())
+                    && digits.chars().all(|c| c.is_ascii_digit()))
+        })
+}
+
+/// Scene names end in `-GROUP`; a dash followed by words is an episode title
+fn trailing_group(rest: &str) -> Option<String> {
+    let (_, group) = rest.rsplit_once('-')?;
+    let group = group.trim_end_matches(']');
+    let valid = !group.is_empty()
+        && group.chars().all(|c| c.is_ascii_alphanumeric())
+        && !group.chars().all(|c| c.is_ascii_digit());
+    valid.then(|| group.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(filename: &str) -> ParsedEpisode {
+        parse_filename(filename).unwrap_or_else(|| panic!("{} didn't parse", filename))
+    }
+
+    #[test]
+    fn parses_scene_names() {
+        let parsed = parse("Show.Name.S02E05.1080p.WEB-DL.x264-GROUP.mkv");
+        assert_eq!(parsed, ParsedEpisode {
+            title: "Show Name".to_string(),
+            year: None,
+            season: 2,
+            episode: 5,
+            episode_end: None,
+            resolution: Some("1080p".to_string()),
+            release_group: Some("GROUP".to_string()),
+        });
+
+        let parsed = parse("the_office_us_s05e14_720p.mp4");
+        assert_eq!((parsed.title.as_str(), parsed.season, parsed.episode), ("the office us", 5, 14));
+        assert_eq!(parsed.resolution.as_deref(), Some("720p"));
+        assert_eq!(parsed.release_group, None);
+    }
+
+    #[test]
+    fn parses_cross_notation() {
+        let parsed = parse("Show - 2x05 - The Episode Title.avi");
+        assert_eq!((parsed.title.as_str(), parsed.season, parsed.episode), ("Show", 2, 5));
+        assert_eq!(parsed.release_group, None);
+
+        // A frame size is not a season
+        let parsed = parse("Show 1920x1080 3x07.mkv");
+        assert_eq!((parsed.season, parsed.episode), (3, 7));
+    }
+
+    #[test]
+    fn parses_episode_ranges() {
+        assert_eq!(parse("Show.S01E01E02.mkv").episode_end, Some(2));
+        assert_eq!(parse("Show.S01E01-E03.mkv").episode_end, Some(3));
+        assert_eq!(parse("Show.S01E09-10.mkv").episode_end, Some(10));
+        assert_eq!(parse("Show 1x01-02.mkv").episode_end, Some(2));
+        assert_eq!(parse("Show 1x01x02.mkv").episode_end, Some(2));
+        assert_eq!(parse("Show.S01E05.mkv").episode_end, None);
+    }
+
+    #[test]
+    fn parses_years_and_leading_groups() {
+        let parsed = parse("Doctor.Who.2005.S10E01.720p-GRP.mkv");
+        assert_eq!((parsed.title.as_str(), parsed.year), ("Doctor Who", Some(2005)));
+
+        let parsed = parse("Show (2019) - S01E02.mkv");
+        assert_eq!((parsed.title.as_str(), parsed.year), ("Show", Some(2019)));
+
+        // A title that is a year stays a title
+        assert_eq!(parse("1923.S01E01.mkv").title, "1923");
+
+        let parsed = parse("[SubGroup] Anime Show - 01x12 [1080p].mkv");
+        assert_eq!(parsed.title, "Anime Show");
+        assert_eq!(parsed.release_group.as_deref(), Some("SubGroup"));
+        assert_eq!(parsed.resolution.as_deref(), Some("1080p"));
+    }
+
+    #[test]
+    fn ignores_names_without_episodes() {
+        assert_eq!(parse_filename("Movie.Name.2010.1080p.BluRay.x264-GROUP.mkv"), None);
+        assert_eq!(parse_filename("holiday 2019.mp4"), None);
+        assert_eq!(parse_filename("Season.mkv"), None);
+    }
+
+    #[test]
+    fn titles_come_from_folders_when_missing() {
+        let parsed = parse("S02E05.mkv");
+        assert_eq!(parsed.title, "");
+        assert_eq!(
+            title_from_folder("/tv/Show Name (2010)/Season 2"),
+            Some(("Show Name".to_string(), Some(2010)))
+        );
+        assert_eq!(title_from_folder("/tv/Show.Name/S02"), Some(("Show Name".to_string(), None)));
+        assert_eq!(match_key("Show.Name", None), match_key("show name", None));
+        assert_ne!(match_key("Doctor Who", Some(1963)), match_key("Doctor Who", Some(2005)));
+        assert_ne!(match_key("Doctor Who", Some(2005)), match_key("Doctor Who", None));
+        assert_eq!(match_key("", Some(2005)), "");
+    }
+}
@@ -0,0 +1,245 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use tauri::{AppHandle, Emitter, Manager};
+use crate::commands::AppState;
+use crate::database::Database;
+use crate::models::ThumbnailProgress;
+use crate::player;
+use crate::probe;
+
+/// Setting key for the frame position, in percent of the video's duration
+pub const OFFSET_SETTING: &str = "thumbnail_offset_percent";
+pub const DEFAULT_OFFSET_PERCENT: f64 = 10.0;
+
+/// Number of mpv processes extracting frames at the same time
+const WORKERS: usize = 2;
+
+/// Number of videos fetched from the database per generation pass
+const BATCH_SIZE: usize = 20;
+
+/// Width of generated thumbnails; height follows the aspect ratio
+const THUMBNAIL_WIDTH: u32 = 480;
+
+struct Job {
+    video_id: String,
+    path: String,
+    mtime: Option<i64>,
+    offset_percent: f64,
+}
+
+/// Background generator for videos that have no sidecar image.
+///
+/// Like the probe queue, pending work lives in the database
+/// (`thumbnail_path IS NULL AND thumbnail_checked_at IS NULL`); `wake` starts a pass.
+pub struct ThumbnailQueue {
+    sender: Sender<()>,
+    receiver: Mutex<Option<Receiver<()>>>,
+}
+
+impl ThumbnailQueue {
+    pub fn new() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        ThumbnailQueue {
+            sender,
+            receiver: Mutex::new(Some(receiver)),
+        }
+    }
+
+    /// Start the dispatcher and worker threads and process anything left over
+    pub fn start(&self, app: AppHandle) {
+        if let Some(receiver) = self.receiver.lock().unwrap().take() {
+            thread::spawn(move || run_dispatcher(app, receiver));
+            self.wake();
+        }
+    }
+
+    /// Ask the dispatcher to look for videos without thumbnails
+    pub fn wake(&self) {
+        let _ = self.sender.send(());
+    }
+}
+
+/// Directory for generated thumbnails
+pub fn get_cache_dir() -> PathBuf {
+    Database::get_data_dir().join("thumbnails")
+}
+
+fn run_dispatcher(app: AppHandle, receiver: Receiver<()>) {
+    let (job_sender, job_receiver) = mpsc::sync_channel::<Job>(WORKERS);
+    let (result_sender, result_receiver) = mpsc::channel::<(String, Option<String>)>();
+    spawn_workers(job_receiver, result_sender);
+
+    while receiver.recv().is_ok() {
+        // Collapse wake-ups that arrived while we were busy
+        while receiver.try_recv().is_ok() {}
+
+        if !player::is_mpv_available() {
+            continue;
+        }
+
+        let state = app.state::<AppState>();
+        let total = state.db.count_videos_missing_thumbnails().unwrap_or(0);
+        let offset_percent = state.db.get_setting(OFFSET_SETTING).ok().flatten()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_OFFSET_PERCENT);
+        let mut completed = 0;
+
+        loop {
+            let batch = state.db.get_videos_missing_thumbnails(BATCH_SIZE).unwrap_or_default();
+            if batch.is_empty() {
+                break;
+            }
+
+            let jobs = batch.len();
+            dispatch_batch(&job_sender, batch, offset_percent);
+
+            for _ in 0..jobs {
+                let Ok((video_id, thumbnail_path)) = result_receiver.recv() else {
+                    return;
+                };
+                let _ = state.db.set_generated_thumbnail(&video_id, thumbnail_path.as_deref());
+
+                completed += 1;
+                let _ = app.emit("thumbnail-progress", ThumbnailProgress {
+                    video_id,
+                    thumbnail_path,
+                    completed,
+                    total: total.max(completed),
+                });
+            }
+        }
+    }
+}
+
+fn dispatch_batch(job_sender: &SyncSender<Job>, batch: Vec<(String, String, Option<i64>)>, offset_percent: f64) {
+    // Send from another thread so results can be consumed while the bounded channel is full
+    let job_sender = job_sender.clone();
+    thread::spawn(move || {
+        for (video_id, path, mtime) in batch {
+            let _ = job_sender.send(Job { video_id, path, mtime, offset_percent });
+        }
+    });
+}
+
+fn spawn_workers(job_receiver: Receiver<Job>, result_sender: Sender<(String, Option<String>)>) {
+    let job_receiver = Arc::new(Mutex::new(job_receiver));
+
+    for _ in 0..WORKERS {
+        let job_receiver = Arc::clone(&job_receiver);
+        let result_sender = result_sender.clone();
+        thread::spawn(move || loop {
+            let job = match job_receiver.lock() {
+                Ok(receiver) => match receiver.recv() {
+                    Ok(job) => job,
+                    Err(_) => return,
+                },
+                Err(_) => return,
+            };
+
+            let thumbnail = generate_thumbnail(&job);
+            if result_sender.send((job.video_id, thumbnail)).is_err() {
+                return;
+            }
+        });
+    }
+}
+
+/// Extract a single frame with mpv into the cache, keyed by video id and mtime
+fn generate_thumbnail(job: &Job) -> Option<String> {
+    let cache_dir = get_cache_dir();
+    let target = thumbnail_file(&cache_dir, &job.video_id, job.mtime);
+    if target.exists() {
+        return Some(target.to_string_lossy().to_string());
+    }
+
+    // mpv names its output files itself, so render into a scratch directory first
+    let work_dir = cache_dir.join(format!(".{}", target.file_stem()?.to_string_lossy()));
+    fs::create_dir_all(&work_dir).ok()?;
+
+    let mut command = player::mpv_command()?;
+    command.args([
+        "--no-config",
+        "--no-audio",
+        "--no-sub",
+        "--frames=1",
+        "--hr-seek=no",
+        "--vo=image",
+        "--vo-image-format=jpg",
+        "--vo-image-jpeg-quality=85",
+    ])
+    .arg(format!("--start={}%", job.offset_percent.clamp(0.0, 99.0)))
+    .arg(format!("--vf=scale={}:-2", THUMBNAIL_WIDTH))
+    .arg(format!("--vo-image-outdir={}", work_dir.to_string_lossy()))
+    .arg("--")
+    .arg(&job.path);
+
+    let result = probe::output_with_timeout(command)
+        .and_then(|_| first_image_in(&work_dir))
+        .and_then(|frame| fs::rename(frame, &target).ok());
+    let _ = fs::remove_dir_all(&work_dir);
+    result?;
+
+    remove_stale_thumbnails(&cache_dir, &job.video_id, &target);
+    Some(target.to_string_lossy().to_string())
+}
+
+/// Cached thumbnail of a video as of `mtime`; a changed file gets a new name
+fn thumbnail_file(cache_dir: &Path, video_id: &str, mtime: Option<i64>) -> PathBuf {
+    cache_dir.join(format!("{}-{}.jpg", video_id, mtime.unwrap_or(0)))
+}
+
+fn first_image_in(dir: &Path) -> Option<PathBuf> {
+    fs::read_dir(dir).ok()?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .find(|p| p.extension().map(|ext| ext == "jpg").unwrap_or(false))
+}
+
+/// Delete thumbnails generated for earlier versions of the same file
+fn remove_stale_thumbnails(cache_dir: &Path, video_id: &str, current: &Path) {
+    let prefix = format!("{}-", video_id);
+    if let Ok(entries) = fs::read_dir(cache_dir) {
+        for path in entries.filter_map(|e| e.ok()).map(|e| e.path()) {
+            let is_stale = path != current && path.file_name()
+                .map(|n| n.to_string_lossy().starts_with(&prefix))
+                .unwrap_or(false);
+            if is_stale {
+                let _ = fs::remove_file(path);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn changed_files_replace_their_thumbnails() {
+        let cache_dir = std::env::temp_dir().join(format!("videoplayer-thumbnails-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&cache_dir);
+        fs::create_dir_all(&cache_dir).unwrap();
+
+        let old = thumbnail_file(&cache_dir, "video-1", Some(100));
+        let current = thumbnail_file(&cache_dir, "video-1", Some(200));
+        let other = thumbnail_file(&cache_dir, "video-2", Some(100));
+        assert_ne!(old, current);
+        assert_eq!(thumbnail_file(&cache_dir, "video-1", None), cache_dir.join("video-1-0.jpg"));
+        for path in [&old, &current, &other] {
+            fs::write(path, b"jpg").unwrap();
+        }
+        // A generation of the same video still in progress
+        fs::create_dir_all(cache_dir.join(".video-1-300")).unwrap();
+
+        remove_stale_thumbnails(&cache_dir, "video-1", &current);
+        assert!(!old.exists());
+        assert!(current.exists());
+        assert!(other.exists());
+        assert!(cache_dir.join(".video-1-300").exists());
+
+        let _ = fs::remove_dir_all(&cache_dir);
+    }
+}
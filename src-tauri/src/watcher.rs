@@ -0,0 +1,266 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Component, Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use tauri::{AppHandle, Emitter, Manager};
+use crate::availability;
+use crate::commands::AppState;
+use crate::database::Database;
+use crate::models::{Availability, LibraryChangedEvent, MountedFolder};
+use crate::scan_rules::{RuleSet, IGNORE_FILE};
+use crate::scanner;
+
+/// Quiet period after the last filesystem event before changes are applied
+const DEBOUNCE: Duration = Duration::from_millis(750);
+
+/// Changed paths reported for a mounted folder
+type WatchMessage = (String, Vec<PathBuf>);
+
+struct WatchedFolder {
+    scan_depth: usize,
+    rules: Arc<RuleSet>,
+    _watcher: RecommendedWatcher,
+}
+
+/// Keeps one filesystem watcher per mounted folder and applies their
+/// (debounced) events to the `videos` table
+pub struct FolderWatcher {
+    folders: Mutex<HashMap<String, WatchedFolder>>,
+    sender: Sender<WatchMessage>,
+    receiver: Mutex<Option<Receiver<WatchMessage>>>,
+}
+
+impl FolderWatcher {
+    pub fn new() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        FolderWatcher {
+            folders: Mutex::new(HashMap::new()),
+            sender,
+            receiver: Mutex::new(Some(receiver)),
+        }
+    }
+
+    /// Start the background thread that debounces events and updates the library
+    pub fn start(&self, app: AppHandle) {
+        if let Some(receiver) = self.receiver.lock().unwrap().take() {
+            thread::spawn(move || run_event_loop(app, receiver));
+        }
+    }
+
+    /// Watch a mounted folder, replacing any existing watcher for the same path
+    pub fn watch(&self, folder: &MountedFolder) -> Result<(), String> {
+        let rules = RuleSet::new(&folder.path, &folder.scan_rules)?;
+        let root = folder.path.clone();
+        let sender = self.sender.clone();
+
+        let mut watcher = notify::recommended_watcher(move |res: notify::Result<Event>| {
+            if let Ok(event) = res {
+                if matches!(event.kind, EventKind::Access(_)) {
+                    return;
+                }
+                let _ = sender.send((root.clone(), event.paths));
+            }
+        }).map_err(|e| e.to_string())?;
+
+        let mode = if folder.scan_depth == 0 {
+            RecursiveMode::NonRecursive
+        } else {
+            RecursiveMode::Recursive
+        };
+        watcher.watch(Path::new(&folder.path), mode)
+            .map_err(|e| format!("Failed to watch {}: {}", folder.path, e))?;
+
+        self.folders.lock().unwrap().insert(folder.path.clone(), WatchedFolder {
+            scan_depth: folder.scan_depth,
+            rules: Arc::new(rules),
+            _watcher: watcher,
+        });
+        Ok(())
+    }
+
+    pub fn unwatch(&self, path: &str) {
+        self.folders.lock().unwrap().remove(path);
+    }
+
+    fn settings(&self, path: &str) -> Option<(usize, Arc<RuleSet>)> {
+        self.folders.lock().unwrap().get(path).map(|f| (f.scan_depth, f.rules.clone()))
+    }
+}
+
+fn run_event_loop(app: AppHandle, receiver: Receiver<WatchMessage>) {
+    let mut pending: HashMap<String, HashSet<PathBuf>> = HashMap::new();
+
+    loop {
+        let message = if pending.is_empty() {
+            receiver.recv().map_err(|_| RecvTimeoutError::Disconnected)
+        } else {
+            receiver.recv_timeout(DEBOUNCE)
+        };
+
+        match message {
+            Ok((root, paths)) => pending.entry(root).or_default().extend(paths),
+            Err(RecvTimeoutError::Timeout) => {
+                for (root, paths) in pending.drain() {
+                    apply_changes(&app, &root, paths);
+                }
+            }
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+    }
+}
+
+fn apply_changes(app: &AppHandle, root: &str, paths: HashSet<PathBuf>) {
+    let state = app.state::<AppState>();
+
+    // The folder may have been unmounted while events were pending
+    let Some((scan_depth, rules)) = state.watcher.settings(root) else {
+        return;
+    };
+
+    let mut changed: Vec<String> = Vec::new();
+    for path in paths {
+        match apply_path_change(&state.db, root, scan_depth, &rules, &path) {
+            Ok(true) => changed.push(path.to_string_lossy().to_string()),
+            Ok(false) => {}
+            Err(e) => log::error!("Failed to apply change for {}: {}", path.display(), e),
+        }
+    }
+
+    if !changed.is_empty() {
+        state.probe.wake();
+        state.thumbnails.wake();
+        state.hashing.wake();
+        let _ = app.emit("library-changed", LibraryChangedEvent {
+            folder_path: root.to_string(),
+            paths: changed,
+        });
+    }
+}
+
+/// Bring the database in line with the current state of `path`.
+/// Returns whether anything in the library changed.
+fn apply_path_change(db: &Database, root: &str, scan_depth: usize, rules: &Arc<RuleSet>, path: &Path) -> rusqlite::Result<bool> {
+    // Events for the mounted folder itself are ignored; an unplugged drive
+    // must not wipe the library
+    let Some(components) = relative_components(root, path).filter(|c| !c.is_empty()) else {
+        return Ok(false);
+    };
+    let path_str = path.to_string_lossy().to_string();
+
+    // An edited or deleted .videoignore can hide or reveal anything next to it
+    if path.file_name().is_some_and(|name| name == IGNORE_FILE) {
+        let Some(dir) = path.parent().filter(|dir| rules.allows(dir)) else {
+            return Ok(false);
+        };
+        if !availability::is_volume_online(db, root) {
+            return Ok(false);
+        }
+        return rescan_directory(db, scan_depth, components.len() - 1, rules, dir);
+    }
+
+    if !path.exists() {
+        // Keep the rows and their metadata; an unplugged drive takes the whole folder offline
+        if !availability::is_volume_online(db, root) {
+            return Ok(db.mark_videos_at(root, Availability::OfflineVolume)? > 0);
+        }
+        return Ok(db.mark_videos_at(&path_str, Availability::Missing)? > 0);
+    }
+
+    if !rules.allows(path) {
+        return Ok(false);
+    }
+
+    if path.is_dir() {
+        // A directory at depth N holds files at depth N
+        return rescan_directory(db, scan_depth, components.len(), rules, path);
+    }
+
+    // A file's depth is that of its parent folder
+    let depth = components.len() - 1;
+    if depth > scan_depth || !rules.is_video_file(path) {
+        return Ok(false);
+    }
+    let Some(mut video) = scanner::create_video_from_path(path) else {
+        return Ok(false);
+    };
+    if !rules.accepts_file(path, video.size) {
+        return Ok(false);
+    }
+
+    let Some(existing) = db.get_video_by_path(&path_str)? else {
+        // A new path may be a stored video that was renamed or moved
+        db.add_video(&mut video)?;
+        return Ok(true);
+    };
+    if existing.size == video.size && existing.mtime == video.mtime {
+        // Unchanged, but it may be back after going missing
+        return Ok(db.mark_videos_at(&path_str, Availability::Online)? > 0);
+    }
+    video.id = existing.id;
+    video.created_at = existing.created_at;
+    video.duration = existing.duration;
+    db.upsert_video(&video)?;
+    Ok(true)
+}
+
+/// Scan a directory `depth` levels below the mounted folder and store the result
+fn rescan_directory(db: &Database, scan_depth: usize, depth: usize, rules: &Arc<RuleSet>, dir: &Path) -> rusqlite::Result<bool> {
+    if depth > scan_depth {
+        return Ok(false);
+    }
+    let dir_str = dir.to_string_lossy().to_string();
+    let mut scan = scanner::scan_folder(&dir_str, scan_depth - depth, rules);
+    let unreadable: Vec<String> = scan.errors.iter().map(|e| e.path.clone()).collect();
+    let changes = db.apply_folder_scan(&dir_str, &mut scan.videos, &unreadable)?;
+    Ok(changes.new_videos + changes.updated_videos + changes.removed_videos + changes.relinked.len() > 0)
+}
+
+/// Names of the path components between `root` and `path`
+fn relative_components(root: &str, path: &Path) -> Option<Vec<String>> {
+    let relative = path.strip_prefix(root).ok()?;
+    Some(relative.components()
+        .filter_map(|c| match c {
+            Component::Normal(name) => Some(name.to_string_lossy().to_string()),
+            _ => None,
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use crate::models::ScanRules;
+
+    #[test]
+    fn renamed_files_keep_their_ids() {
+        let dir = std::env::temp_dir().join(format!("videoplayer-watcher-rename-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let root = dir.join("library");
+        fs::create_dir_all(&root).unwrap();
+        let db = Database::open(&dir.join("database.sqlite")).unwrap();
+        let root_str = root.to_string_lossy().to_string();
+        let rules = Arc::new(RuleSet::new(&root_str, &ScanRules::default()).unwrap());
+
+        let before = root.join("before.mp4");
+        fs::write(&before, vec![1u8; 4096]).unwrap();
+        assert!(apply_path_change(&db, &root_str, 5, &rules, &before).unwrap());
+        let id = db.get_video_by_path(&before.to_string_lossy()).unwrap().unwrap().id;
+
+        // Events for both ends of a rename arrive in no particular order
+        let after = root.join("after.mp4");
+        fs::rename(&before, &after).unwrap();
+        assert!(apply_path_change(&db, &root_str, 5, &rules, &after).unwrap());
+        assert!(!apply_path_change(&db, &root_str, 5, &rules, &before).unwrap());
+
+        let moved = db.get_video(&id).unwrap().unwrap();
+        assert_eq!(moved.path, after.to_string_lossy());
+        assert_eq!(moved.availability, Availability::Online);
+        assert!(db.get_video_by_path(&before.to_string_lossy()).unwrap().is_none());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
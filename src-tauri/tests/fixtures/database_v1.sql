@@ -0,0 +1,95 @@
+-- Schema and sample data of a database written by the first release
+-- (schema version 1). Used by the migration tests.
+
+CREATE TABLE mounted_folders (
+    id TEXT PRIMARY KEY,
+    path TEXT NOT NULL UNIQUE,
+    name TEXT NOT NULL,
+    scan_depth INTEGER NOT NULL DEFAULT 2,
+    created_at TEXT NOT NULL
+);
+
+CREATE TABLE videos (
+    id TEXT PRIMARY KEY,
+    path TEXT NOT NULL UNIQUE,
+    filename TEXT NOT NULL,
+    folder_path TEXT NOT NULL,
+    size INTEGER NOT NULL DEFAULT 0,
+    duration REAL,
+    thumbnail_path TEXT,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+
+CREATE TABLE tags (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE,
+    color TEXT NOT NULL DEFAULT '#6366f1'
+);
+
+CREATE TABLE video_tags (
+    video_id TEXT NOT NULL,
+    tag_id TEXT NOT NULL,
+    PRIMARY KEY (video_id, tag_id),
+    FOREIGN KEY (video_id) REFERENCES videos(id) ON DELETE CASCADE,
+    FOREIGN KEY (tag_id) REFERENCES tags(id) ON DELETE CASCADE
+);
+
+CREATE TABLE participants (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE
+);
+
+CREATE TABLE video_participants (
+    video_id TEXT NOT NULL,
+    participant_id TEXT NOT NULL,
+    PRIMARY KEY (video_id, participant_id),
+    FOREIGN KEY (video_id) REFERENCES videos(id) ON DELETE CASCADE,
+    FOREIGN KEY (participant_id) REFERENCES participants(id) ON DELETE CASCADE
+);
+
+CREATE TABLE languages (
+    id TEXT PRIMARY KEY,
+    code TEXT NOT NULL UNIQUE,
+    name TEXT NOT NULL
+);
+
+CREATE TABLE video_languages (
+    video_id TEXT NOT NULL,
+    language_id TEXT NOT NULL,
+    PRIMARY KEY (video_id, language_id),
+    FOREIGN KEY (video_id) REFERENCES videos(id) ON DELETE CASCADE,
+    FOREIGN KEY (language_id) REFERENCES languages(id) ON DELETE CASCADE
+);
+
+CREATE TABLE playback_history (
+    video_id TEXT PRIMARY KEY,
+    position REAL NOT NULL DEFAULT 0,
+    last_played TEXT NOT NULL,
+    FOREIGN KEY (video_id) REFERENCES videos(id) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_videos_folder ON videos(folder_path);
+CREATE INDEX idx_videos_filename ON videos(filename);
+
+INSERT INTO mounted_folders VALUES
+    ('folder-1', '/media/videos', 'videos', 2, '2024-01-01T00:00:00+00:00');
+
+INSERT INTO videos VALUES
+    ('video-1', '/media/videos/holiday.mp4', 'holiday.mp4', '/media/videos', 1048576, 120.0, NULL,
+     '2024-01-01T00:00:00+00:00', '2024-01-01T00:00:00+00:00'),
+    ('video-2', '/media/videos/2023/surfing.mkv', 'surfing.mkv', '/media/videos/2023', 2097152, NULL, NULL,
+     '2024-01-01T00:00:00+00:00', '2024-01-01T00:00:00+00:00');
+
+INSERT INTO tags VALUES ('tag-1', 'favorite', '#ef4444');
+INSERT INTO video_tags VALUES ('video-1', 'tag-1'), ('video-2', 'tag-1');
+
+INSERT INTO participants VALUES ('participant-1', 'Alice');
+INSERT INTO video_participants VALUES ('video-1', 'participant-1');
+
+INSERT INTO languages VALUES ('language-1', 'en', 'English');
+INSERT INTO video_languages VALUES ('video-2', 'language-1');
+
+INSERT INTO playback_history VALUES ('video-1', 42.5, '2024-02-01T20:00:00+00:00');
+
+PRAGMA user_version = 1;
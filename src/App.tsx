@@ -1,10 +1,12 @@
 import { useEffect } from 'react';
+import { listen } from '@tauri-apps/api/event';
 import { Layout } from './components/Layout/Layout';
 import { Header } from './components/Header/Header';
 import { VideoGrid } from './components/VideoGrid/VideoGrid';
 import { VideoDetail } from './components/VideoDetail/VideoDetail';
 import { Player } from './components/Player/Player';
 import { useAppStore } from './stores/appStore';
+import type { ThumbnailProgress } from './types';
 import './App.css';
 
 function App() {
@@ -14,6 +16,7 @@ function App() {
     loadParticipants, 
     loadLanguages,
     loadVideos,
+    loadCollections,
     selectedVideo,
     selectVideo,
     isPlayerOpen,
@@ -34,6 +37,26 @@ function App() {
     };
     
     initApp();
+    
+    // Folder watcher applied filesystem changes to the library
+    const unlisten = listen('library-changed', () => {
+      loadVideos();
+      loadCollections();
+    });
+    
+    // Show generated thumbnails as they arrive
+    const unlistenThumbnails = listen<ThumbnailProgress>('thumbnail-progress', (event) => {
+      const { video_id, thumbnail_path } = event.payload;
+      if (!thumbnail_path) return;
+      useAppStore.setState(state => ({
+        videos: state.videos.map(v => v.id === video_id ? { ...v, thumbnail_path } : v),
+      }));
+    });
+    
+    return () => {
+      unlisten.then(fn => fn());
+      unlistenThumbnails.then(fn => fn());
+    };
   }, []);
 
   return (
@@ -8,6 +8,7 @@ import {
   SortDesc
 } from 'lucide-react';
 import { useAppStore } from '../../stores/appStore';
+import type { FilterOptions } from '../../types';
 import './Header.css';
 
 export function Header() {
@@ -26,7 +27,7 @@ export function Header() {
   };
 
   const handleSortChange = (e: React.ChangeEvent<HTMLSelectElement>) => {
-    setFilter({ sort_by: e.target.value as 'filename' | 'size' | 'created_at' | 'updated_at' });
+    setFilter({ sort_by: e.target.value as FilterOptions['sort_by'] });
   };
 
   const toggleSortOrder = () => {
@@ -66,6 +67,7 @@ export function Header() {
             <option value="size">크기</option>
             <option value="created_at">생성일</option>
             <option value="updated_at">수정일</option>
+            <option value="relevance">관련도</option>
           </select>
           <button className="sort-order-btn" onClick={toggleSortOrder}>
             {filter.sort_order === 'asc' ? <SortAsc size={18} /> : <SortDesc size={18} />}
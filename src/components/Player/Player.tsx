@@ -85,19 +85,51 @@ export function Player({ video, onClose }: PlayerProps) {
     }
   };
 
-  // Load saved playback position
+  // Resume from the saved position, then log this viewing into the watch
+  // history starting there
   useEffect(() => {
-    const loadPosition = async () => {
+    let cancelled = false;
+    let watchedSeconds = 0;
+
+    const session = (async () => {
+      let position = 0;
       try {
-        const position = await invoke<number | null>('get_playback_position', { videoId: video.id });
-        if (position && videoRef.current) {
+        position = (await invoke<number | null>('get_playback_position', { videoId: video.id })) || 0;
+        if (position && videoRef.current && !cancelled) {
           videoRef.current.currentTime = position;
         }
       } catch (err) {
         console.error('Failed to load playback position:', err);
       }
+      if (cancelled) {
+        return null;
+      }
+      try {
+        return await invoke<string>('begin_playback_session', { videoId: video.id, position });
+      } catch (err) {
+        console.error('Failed to start playback session:', err);
+        return null;
+      }
+    })();
+
+    const watchInterval = setInterval(() => {
+      if (videoRef.current && !videoRef.current.paused) {
+        watchedSeconds += 1;
+      }
+    }, 1000);
+
+    return () => {
+      cancelled = true;
+      clearInterval(watchInterval);
+      const position = videoRef.current?.currentTime || 0;
+      // The session may still be starting; end it once its id arrives
+      session.then((sessionId) => {
+        if (sessionId) {
+          invoke('end_playback_session', { sessionId, position, watchedSeconds })
+            .catch((err) => console.error('Failed to end playback session:', err));
+        }
+      });
     };
-    loadPosition();
   }, [video.id]);
 
   // Save playback position periodically
@@ -107,7 +139,8 @@ export function Player({ video, onClose }: PlayerProps) {
         try {
           await invoke('save_playback_position', { 
             videoId: video.id, 
-            position: currentTime 
+            position: currentTime,
+            duration: videoRef.current.duration || null,
           });
         } catch (err) {
           console.error('Failed to save playback position:', err);
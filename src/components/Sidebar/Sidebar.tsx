@@ -10,11 +10,49 @@ import {
   X,
   RefreshCw,
   Loader2,
-  Settings
+  Settings,
+  Sparkles,
+  Plus
 } from 'lucide-react';
 import { open } from '@tauri-apps/plugin-dialog';
 import { useAppStore } from '../../stores/appStore';
-import type { FolderNode, MountedFolder } from '../../types';
+import type { FolderNode, MountedFolder, ScanReport, ScanRules } from '../../types';
+
+/** Scan rules as edited in the folder settings, lists as raw text */
+interface ScanRulesForm {
+  extraExtensions: string;
+  includePatterns: string;
+  excludePatterns: string;
+  minFileSizeMb: number;
+  followSymlinks: boolean;
+  includeHidden: boolean;
+}
+
+const MB = 1024 * 1024;
+
+function toRulesForm(rules: ScanRules): ScanRulesForm {
+  return {
+    extraExtensions: rules.extra_extensions.join(', '),
+    includePatterns: rules.include_patterns.join('\n'),
+    excludePatterns: rules.exclude_patterns.join('\n'),
+    minFileSizeMb: rules.min_file_size / MB,
+    followSymlinks: rules.follow_symlinks,
+    includeHidden: rules.include_hidden,
+  };
+}
+
+function fromRulesForm(form: ScanRulesForm): ScanRules {
+  const list = (text: string, separator: RegExp) =>
+    text.split(separator).map(item => item.trim()).filter(Boolean);
+  return {
+    extra_extensions: list(form.extraExtensions, /[,\s]+/),
+    include_patterns: list(form.includePatterns, /\n/),
+    exclude_patterns: list(form.excludePatterns, /\n/),
+    min_file_size: Math.round(form.minFileSizeMb * MB),
+    follow_symlinks: form.followSymlinks,
+    include_hidden: form.includeHidden,
+  };
+}
 import './Sidebar.css';
 
 interface SidebarProps {
@@ -28,15 +66,24 @@ export function Sidebar({ isOpen }: SidebarProps) {
     tags,
     participants,
     languages,
+    collections,
     filter,
-    isScanningFolder,
+    scans,
+    cancelScan,
     loadMountedFolders,
+    loadFolderChildren,
     loadTags,
     loadParticipants,
     loadLanguages,
+    loadCollections,
+    saveFilterAsCollection,
+    deleteCollection,
+    openCollection,
     addMountedFolder,
     removeMountedFolder,
     updateFolderScanDepth,
+    updateFolderScanRules,
+    getLastScanReport,
     scanFolder,
     setFilter,
     resetFilter,
@@ -46,14 +93,28 @@ export function Sidebar({ isOpen }: SidebarProps) {
   const [activeTab, setActiveTab] = useState<'folders' | 'tags' | 'participants' | 'languages'>('folders');
   const [settingsFolder, setSettingsFolder] = useState<MountedFolder | null>(null);
   const [tempScanDepth, setTempScanDepth] = useState<number>(2);
+  const [tempScanRules, setTempScanRules] = useState<ScanRulesForm | null>(null);
+  const [settingsError, setSettingsError] = useState<string | null>(null);
+  const [lastScanReport, setLastScanReport] = useState<ScanReport | null>(null);
 
   useEffect(() => {
     loadMountedFolders();
     loadTags();
     loadParticipants();
     loadLanguages();
+    loadCollections();
   }, []);
 
+  const handleSaveCollection = async () => {
+    const name = window.prompt('컬렉션 이름');
+    if (!name?.trim()) return;
+    try {
+      await saveFilterAsCollection(name.trim());
+    } catch (err) {
+      console.error('Failed to save collection:', err);
+    }
+  };
+
   const handleAddFolder = async () => {
     const selected = await open({
       directory: true,
@@ -73,23 +134,44 @@ export function Sidebar({ isOpen }: SidebarProps) {
   const handleOpenSettings = (folder: MountedFolder) => {
     setSettingsFolder(folder);
     setTempScanDepth(folder.scan_depth);
+    setTempScanRules(toRulesForm(folder.scan_rules));
+    setSettingsError(null);
+    setLastScanReport(null);
+    getLastScanReport(folder.path)
+      .then(setLastScanReport)
+      .catch(err => console.error('Failed to load scan report:', err));
   };
 
   const handleSaveSettings = async () => {
-    if (settingsFolder) {
+    if (settingsFolder && tempScanRules) {
+      try {
+        // Invalid patterns are rejected before anything is saved
+        await updateFolderScanRules(settingsFolder.path, fromRulesForm(tempScanRules));
+      } catch (err) {
+        setSettingsError(String(err));
+        return;
+      }
       await updateFolderScanDepth(settingsFolder.path, tempScanDepth);
-      // Rescan with new depth
-      await scanFolder(settingsFolder.path);
+      // Rescan with new depth and rules
       setSettingsFolder(null);
+      await scanFolder(settingsFolder.path);
     }
   };
 
-  const toggleFolderExpand = (path: string) => {
+  const updateRulesForm = (changes: Partial<ScanRulesForm>) => {
+    setTempScanRules(rules => rules && { ...rules, ...changes });
+  };
+
+  const toggleFolderExpand = (root: string, node: FolderNode) => {
     const newExpanded = new Set(expandedFolders);
-    if (newExpanded.has(path)) {
-      newExpanded.delete(path);
+    if (newExpanded.has(node.path)) {
+      newExpanded.delete(node.path);
     } else {
-      newExpanded.add(path);
+      newExpanded.add(node.path);
+      // Subfolders are loaded on first expand
+      if (node.children.length === 0) {
+        loadFolderChildren(root, node.path);
+      }
     }
     setExpandedFolders(newExpanded);
   };
@@ -119,10 +201,12 @@ export function Sidebar({ isOpen }: SidebarProps) {
     setFilter({ language_ids: newIds });
   };
 
-  const renderFolderTree = (node: FolderNode, depth: number = 0) => {
+  const renderFolderTree = (root: string, node: FolderNode, depth: number = 0) => {
     const isExpanded = expandedFolders.has(node.path);
     const isSelected = filter.folder_path === node.path;
-    const hasChildren = node.children.length > 0;
+    const hasChildren = node.has_children;
+    const sizeGb = (node.total_size / 1024 ** 3).toFixed(1);
+    const hours = (node.total_duration / 3600).toFixed(1);
 
     return (
       <div key={node.path} className="folder-tree-item">
@@ -133,7 +217,7 @@ export function Sidebar({ isOpen }: SidebarProps) {
           {hasChildren ? (
             <button 
               className="folder-expand-btn"
-              onClick={() => toggleFolderExpand(node.path)}
+              onClick={() => toggleFolderExpand(root, node)}
             >
               {isExpanded ? <ChevronDown size={14} /> : <ChevronRight size={14} />}
             </button>
@@ -143,6 +227,7 @@ export function Sidebar({ isOpen }: SidebarProps) {
           <button 
             className="folder-name-btn"
             onClick={() => handleFolderClick(node.path)}
+            title={`${sizeGb} GB · ${hours}시간`}
           >
             <Folder size={14} />
             <span className="folder-name">{node.name}</span>
@@ -151,7 +236,7 @@ export function Sidebar({ isOpen }: SidebarProps) {
         </div>
         {isExpanded && hasChildren && (
           <div className="folder-children">
-            {node.children.map(child => renderFolderTree(child, depth + 1))}
+            {node.children.map(child => renderFolderTree(root, child, depth + 1))}
           </div>
         )}
       </div>
@@ -218,7 +303,8 @@ export function Sidebar({ isOpen }: SidebarProps) {
             ) : (
               <div className="mounted-folders-list">
                 {mountedFolders.map(folder => {
-                  const isScanning = isScanningFolder === folder.path;
+                  const scan = Object.values(scans).find(s => s.job.folder_path === folder.path);
+                  const isScanning = scan !== undefined;
                   const folderTree = folderTrees.get(folder.path);
                   
                   return (
@@ -255,15 +341,26 @@ export function Sidebar({ isOpen }: SidebarProps) {
                           </button>
                         </div>
                       </div>
-                      {isScanning && (
+                      {scan && (
                         <div className="scanning-indicator">
                           <Loader2 size={12} className="spinning" />
-                          <span>스캔 중...</span>
+                          <span>
+                            {scan.progress
+                              ? `스캔 중... ${scan.progress.files_found}개 발견`
+                              : '스캔 대기 중...'}
+                          </span>
+                          <button
+                            className="icon-btn small"
+                            onClick={() => cancelScan(scan.job.id)}
+                            title="스캔 취소"
+                          >
+                            <X size={12} />
+                          </button>
                         </div>
                       )}
                       {folderTree && (
                         <div className="folder-tree">
-                          {renderFolderTree(folderTree)}
+                          {renderFolderTree(folder.path, folderTree)}
                         </div>
                       )}
                     </div>
@@ -271,6 +368,32 @@ export function Sidebar({ isOpen }: SidebarProps) {
                 })}
               </div>
             )}
+
+            <div className="section-header">
+              <span>스마트 컬렉션</span>
+              <button className="icon-btn" onClick={handleSaveCollection} title="현재 필터 저장">
+                <Plus size={16} />
+              </button>
+            </div>
+            <div className="filter-list">
+              {collections.map(collection => (
+                <div key={collection.id} className="filter-item" onClick={() => openCollection(collection.id)}>
+                  <Sparkles size={14} />
+                  <span>{collection.name}</span>
+                  <span className="folder-count">{collection.video_count}</span>
+                  <button
+                    className="icon-btn small danger"
+                    onClick={(e) => {
+                      e.stopPropagation();
+                      deleteCollection(collection.id);
+                    }}
+                    title="삭제"
+                  >
+                    <X size={14} />
+                  </button>
+                </div>
+              ))}
+            </div>
           </div>
         )}
 
@@ -385,6 +508,99 @@ export function Sidebar({ isOpen }: SidebarProps) {
                   </span>
                 </div>
               </div>
+              {tempScanRules && (
+                <>
+                  <div className="setting-item">
+                    <label htmlFor="extraExtensions">추가 확장자</label>
+                    <input
+                      id="extraExtensions"
+                      className="setting-input"
+                      placeholder="ts, m2ts, vob"
+                      value={tempScanRules.extraExtensions}
+                      onChange={(e) => updateRulesForm({ extraExtensions: e.target.value })}
+                    />
+                  </div>
+                  <div className="setting-item">
+                    <label htmlFor="includePatterns">포함 패턴 (한 줄에 하나)</label>
+                    <textarea
+                      id="includePatterns"
+                      className="setting-input"
+                      rows={2}
+                      placeholder="Movies/**"
+                      value={tempScanRules.includePatterns}
+                      onChange={(e) => updateRulesForm({ includePatterns: e.target.value })}
+                    />
+                  </div>
+                  <div className="setting-item">
+                    <label htmlFor="excludePatterns">제외 패턴 (한 줄에 하나)</label>
+                    <textarea
+                      id="excludePatterns"
+                      className="setting-input"
+                      rows={3}
+                      value={tempScanRules.excludePatterns}
+                      onChange={(e) => updateRulesForm({ excludePatterns: e.target.value })}
+                    />
+                    <span className="scan-depth-hint">
+                      폴더 안의 .videoignore 파일도 같은 형식으로 적용됩니다
+                    </span>
+                  </div>
+                  <div className="setting-item">
+                    <label htmlFor="minFileSize">최소 파일 크기 (MB)</label>
+                    <div className="scan-depth-input">
+                      <input
+                        id="minFileSize"
+                        type="number"
+                        min={0}
+                        value={tempScanRules.minFileSizeMb}
+                        onChange={(e) => updateRulesForm({ minFileSizeMb: Math.max(0, parseFloat(e.target.value) || 0) })}
+                      />
+                    </div>
+                  </div>
+                  <div className="setting-item">
+                    <label className="setting-checkbox">
+                      <input
+                        type="checkbox"
+                        checked={tempScanRules.followSymlinks}
+                        onChange={(e) => updateRulesForm({ followSymlinks: e.target.checked })}
+                      />
+                      심볼릭 링크 따라가기
+                    </label>
+                    <label className="setting-checkbox">
+                      <input
+                        type="checkbox"
+                        checked={tempScanRules.includeHidden}
+                        onChange={(e) => updateRulesForm({ includeHidden: e.target.checked })}
+                      />
+                      숨김 파일 포함
+                    </label>
+                  </div>
+                </>
+              )}
+              {lastScanReport && (
+                <div className="setting-item">
+                  <label>마지막 스캔</label>
+                  <p className="setting-value">
+                    {new Date(lastScanReport.started_at).toLocaleString()}
+                    {' · '}
+                    {lastScanReport.status === 'completed'
+                      ? `동영상 ${lastScanReport.total_videos}개 (신규 ${lastScanReport.new_videos}개)`
+                      : lastScanReport.status === 'cancelled' ? '취소됨' : `실패: ${lastScanReport.error}`}
+                    {' · '}
+                    {(lastScanReport.duration_ms / 1000).toFixed(1)}초
+                  </p>
+                  {lastScanReport.errors.length > 0 && (
+                    <ul className="scan-report-errors">
+                      {lastScanReport.errors.map((error, index) => (
+                        <li key={index} title={error.path}>
+                          <span className="scan-report-path">{error.path}</span>
+                          {error.message}
+                        </li>
+                      ))}
+                    </ul>
+                  )}
+                </div>
+              )}
+              {settingsError && <p className="setting-error">{settingsError}</p>}
             </div>
             <div className="modal-footer">
               <button className="btn-secondary" onClick={() => setSettingsFolder(null)}>
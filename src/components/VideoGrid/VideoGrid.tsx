@@ -186,7 +186,7 @@ function VideoCard({ video, isSelected, onPlay, onPlayWithMpv, onSelect, mpvInst
   if (viewMode === 'list') {
     return (
       <div 
-        className={`video-list-item ${isSelected ? 'selected' : ''}`}
+        className={`video-list-item ${isSelected ? 'selected' : ''} ${video.availability !== 'online' ? 'unavailable' : ''}`}
         onClick={handleClick}
         onDoubleClick={handleDoubleClick}
       >
@@ -234,7 +234,7 @@ function VideoCard({ video, isSelected, onPlay, onPlayWithMpv, onSelect, mpvInst
 
   return (
     <div 
-      className={`video-card ${isSelected ? 'selected' : ''}`}
+      className={`video-card ${isSelected ? 'selected' : ''} ${video.availability !== 'online' ? 'unavailable' : ''}`}
       onClick={handleClick}
       onDoubleClick={handleDoubleClick}
       draggable
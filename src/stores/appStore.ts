@@ -1,16 +1,29 @@
 import { create } from 'zustand';
 import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
 import type {
   Video,
   Tag,
   Participant,
   Language,
   MountedFolder,
+  ScanRules,
   FolderNode,
   FilterOptions,
   ViewMode,
   ScanResult,
+  ScanJob,
+  ScanProgress,
+  ScanFinished,
+  ScanReport,
+  QueryError,
   VideoWithMetadata,
+  SidebarData,
+  SidebarCollection,
+  SmartCollection,
+  Series,
+  Season,
+  Episode,
 } from '../types';
 
 interface PaginatedVideos {
@@ -19,6 +32,51 @@ interface PaginatedVideos {
   has_more: boolean;
 }
 
+/** A scan started from this window, queued or running */
+interface ActiveScan {
+  job: ScanJob;
+  progress: ScanProgress | null;
+}
+
+/** Queue a scan of `path` and wait for its result, reporting the job and its progress on the way */
+async function runScan(
+  path: string,
+  onQueued: (job: ScanJob) => void,
+  onProgress: (progress: ScanProgress) => void,
+): Promise<ScanResult> {
+  let jobId: string | null = null;
+  const finishedJobs = new Map<string, ScanFinished>();
+  let settle: (() => void) | null = null;
+  
+  // Listen before queueing so a fast scan can't finish unnoticed
+  const unlistenProgress = await listen<ScanProgress>('scan-progress', (event) => {
+    if (event.payload.job_id === jobId) onProgress(event.payload);
+  });
+  const unlistenFinished = await listen<ScanFinished>('scan-finished', (event) => {
+    finishedJobs.set(event.payload.job_id, event.payload);
+    settle?.();
+  });
+  
+  try {
+    const job = await invoke<ScanJob>('scan_folder', { folderPath: path });
+    jobId = job.id;
+    onQueued(job);
+    const finished = await new Promise<ScanFinished>((resolve) => {
+      settle = () => {
+        const done = finishedJobs.get(job.id);
+        if (done) resolve(done);
+      };
+      settle();
+    });
+    
+    if (finished.status === 'completed') return finished.result;
+    throw finished.status === 'failed' ? finished.error : 'Scan cancelled';
+  } finally {
+    unlistenProgress();
+    unlistenFinished();
+  }
+}
+
 interface AppState {
   // Data
   videos: Video[];
@@ -29,6 +87,8 @@ interface AppState {
   languages: Language[];
   mountedFolders: MountedFolder[];
   folderTrees: Map<string, FolderNode>;
+  collections: SidebarCollection[];
+  series: Series[];
   
   // UI State
   selectedVideo: Video | null;
@@ -37,10 +97,13 @@ interface AppState {
   viewMode: ViewMode;
   isSidebarOpen: boolean;
   isLoading: boolean;
-  isScanningFolder: string | null;
+  /** Scans by job id, so each one can be followed and cancelled on its own */
+  scans: Record<string, ActiveScan>;
   
   // Filter
   filter: FilterOptions;
+  /** Why `filter.query` was rejected, located for highlighting */
+  queryError: QueryError | null;
   
   // Actions - Data Loading
   loadMountedFolders: () => Promise<void>;
@@ -49,12 +112,18 @@ interface AppState {
   loadTags: () => Promise<void>;
   loadParticipants: () => Promise<void>;
   loadLanguages: () => Promise<void>;
+  loadCollections: () => Promise<void>;
+  loadFolderTree: (root: string) => Promise<void>;
+  loadFolderChildren: (root: string, path: string) => Promise<void>;
   
   // Actions - Folders
   addMountedFolder: (path: string, scanDepth?: number) => Promise<MountedFolder>;
   removeMountedFolder: (path: string) => Promise<void>;
   updateFolderScanDepth: (path: string, scanDepth: number) => Promise<void>;
+  updateFolderScanRules: (path: string, scanRules: ScanRules) => Promise<void>;
+  getLastScanReport: (folderPath: string) => Promise<ScanReport | null>;
   scanFolder: (path: string) => Promise<ScanResult>;
+  cancelScan: (jobId: string) => Promise<void>;
   
   // Actions - Videos
   selectVideo: (video: Video | null) => void;
@@ -86,6 +155,17 @@ interface AppState {
   deleteLanguage: (id: string) => Promise<void>;
   setVideoLanguages: (videoId: string, languageIds: string[]) => Promise<void>;
   
+  // Actions - Smart Collections
+  saveFilterAsCollection: (name: string) => Promise<SmartCollection>;
+  deleteCollection: (id: string) => Promise<void>;
+  openCollection: (id: string) => Promise<void>;
+  
+  // Actions - Series
+  loadSeries: () => Promise<void>;
+  getSeasons: (seriesId: string) => Promise<Season[]>;
+  getEpisodes: (seriesId: string, season?: number) => Promise<Episode[]>;
+  playNextEpisode: (seriesId: string) => Promise<Episode>;
+  
   // Actions - Filter
   setFilter: (filter: Partial<FilterOptions>) => void;
   resetFilter: () => void;
@@ -119,25 +199,64 @@ export const useAppStore = create<AppState>((set, get) => ({
   languages: [],
   mountedFolders: [],
   folderTrees: new Map(),
+  collections: [],
+  series: [],
   selectedVideo: null,
   selectedVideoMetadata: null,
   isPlayerOpen: false,
   viewMode: 'grid',
   isSidebarOpen: true,
   isLoading: false,
-  isScanningFolder: null,
+  scans: {},
   filter: defaultFilter,
+  queryError: null,
   
   // Data Loading
   loadMountedFolders: async () => {
     try {
       const folders = await invoke<MountedFolder[]>('get_mounted_folders');
       set({ mountedFolders: folders });
+      await Promise.all(folders.map((folder) => get().loadFolderTree(folder.path)));
     } catch (err) {
       console.error('Failed to load mounted folders:', err);
     }
   },
   
+  loadFolderTree: async (root) => {
+    try {
+      const tree = await invoke<FolderNode>('get_folder_tree', { folderPath: root, depth: 1 });
+      const newTrees = new Map(get().folderTrees);
+      newTrees.set(root, tree);
+      set({ folderTrees: newTrees });
+    } catch (err) {
+      console.error('Failed to load folder tree:', err);
+    }
+  },
+  
+  loadFolderChildren: async (root, path) => {
+    const tree = get().folderTrees.get(root);
+    if (!tree) return;
+    try {
+      const subtree = await invoke<FolderNode>('get_folder_tree', { folderPath: path, depth: 1 });
+      const replace = (node: FolderNode): FolderNode =>
+        node.path === path ? subtree : { ...node, children: node.children.map(replace) };
+      const newTrees = new Map(get().folderTrees);
+      newTrees.set(root, replace(tree));
+      set({ folderTrees: newTrees });
+    } catch (err) {
+      console.error('Failed to load folder:', err);
+    }
+  },
+  
+  loadCollections: async () => {
+    try {
+      const sidebar = await invoke<SidebarData>('get_sidebar');
+      set({ collections: sidebar.collections });
+    } catch (err) {
+      console.error('Failed to load collections:', err);
+    }
+  },
+  
   loadVideos: async () => {
     set({ isLoading: true });
     try {
@@ -148,9 +267,14 @@ export const useAppStore = create<AppState>((set, get) => ({
         totalVideos: result.total,
         hasMore: result.has_more,
         filter,
+        queryError: null,
         isLoading: false,
       });
     } catch (err) {
+      if (typeof err === 'object' && err !== null && 'start' in err) {
+        set({ queryError: err as QueryError, isLoading: false });
+        return;
+      }
       console.error('Failed to load videos:', err);
       set({ isLoading: false });
     }
@@ -235,10 +359,35 @@ export const useAppStore = create<AppState>((set, get) => ({
     await get().loadMountedFolders();
   },
   
+  updateFolderScanRules: async (path, scanRules) => {
+    await invoke('update_folder_scan_rules', { path, scanRules });
+    await get().loadMountedFolders();
+  },
+  
+  getLastScanReport: async (folderPath) => {
+    return await invoke<ScanReport | null>('get_last_scan_report', { folderPath });
+  },
+  
   scanFolder: async (path) => {
-    set({ isScanningFolder: path });
+    let jobId = null as string | null;
     try {
-      const result = await invoke<ScanResult>('scan_folder', { folderPath: path });
+      const result = await runScan(
+        path,
+        (job) => {
+          jobId = job.id;
+          set({ scans: { ...get().scans, [job.id]: { job, progress: null } } });
+        },
+        (progress) => {
+          const scan = get().scans[progress.job_id];
+          if (scan) {
+            set({ scans: { ...get().scans, [progress.job_id]: { ...scan, progress } } });
+          }
+        },
+      );
+      
+      for (const error of result.errors) {
+        console.warn(`Skipped ${error.path}: ${error.message}`);
+      }
       
       // Update folder trees
       if (result.folders.length > 0) {
@@ -252,10 +401,18 @@ export const useAppStore = create<AppState>((set, get) => ({
       
       return result;
     } finally {
-      set({ isScanningFolder: null });
+      if (jobId) {
+        const scans = { ...get().scans };
+        delete scans[jobId];
+        set({ scans });
+      }
     }
   },
   
+  cancelScan: async (jobId) => {
+    await invoke('cancel_scan', { jobId });
+  },
+  
   // Videos
   selectVideo: (video) => {
     set({ selectedVideo: video });
@@ -419,7 +576,45 @@ export const useAppStore = create<AppState>((set, get) => ({
     }
   },
   
-  // Filter
+  // Smart Collections
+  saveFilterAsCollection: async (name) => {
+    const collection = await invoke<SmartCollection>('create_smart_collection', { name, filter: get().filter });
+    await get().loadCollections();
+    return collection;
+  },
+  
+  deleteCollection: async (id) => {
+    await invoke('delete_smart_collection', { id });
+    await get().loadCollections();
+  },
+  
+  openCollection: async (id) => {
+    const collection = await invoke<SmartCollection | null>('get_smart_collection', { id });
+    if (collection) {
+      get().setFilter({ ...defaultFilter, ...collection.filter, limit: PAGE_SIZE });
+    }
+  },
+  
+  // Series
+  loadSeries: async () => {
+    const series = await invoke<Series[]>('get_series');
+    set({ series });
+  },
+  
+  getSeasons: async (seriesId) => {
+    return await invoke<Season[]>('get_seasons', { seriesId });
+  },
+  
+  getEpisodes: async (seriesId, season) => {
+    return await invoke<Episode[]>('get_episodes', { seriesId, season: season ?? null });
+  },
+  
+  playNextEpisode: async (seriesId) => {
+    const episode = await invoke<Episode>('play_next_episode', { seriesId });
+    await get().loadSeries();
+    return episode;
+  },
+  
   setFilter: (newFilter) => {
     set({ filter: { ...get().filter, ...newFilter, offset: 0 } });
     get().loadVideos();
@@ -6,10 +6,14 @@ export interface Video {
   size: number;
   duration: number | null;
   thumbnail_path: string | null;
+  mtime: number | null;
   created_at: string;
   updated_at: string;
+  availability: Availability;
 }
 
+export type Availability = 'online' | 'missing' | 'offline_volume' | 'excluded';
+
 export interface Tag {
   id: string;
   name: string;
@@ -33,6 +37,28 @@ export interface MountedFolder {
   name: string;
   scan_depth: number;
   created_at: string;
+  scan_rules: ScanRules;
+}
+
+export interface ScanRules {
+  extra_extensions: string[];
+  include_patterns: string[];
+  exclude_patterns: string[];
+  /** Bytes */
+  min_file_size: number;
+  follow_symlinks: boolean;
+  include_hidden: boolean;
+}
+
+export interface MediaInfo {
+  duration: number | null;
+  width: number | null;
+  height: number | null;
+  video_codec: string | null;
+  audio_codec: string | null;
+  bitrate: number | null;
+  frame_rate: number | null;
+  audio_tracks: number;
 }
 
 export interface VideoWithMetadata {
@@ -40,19 +66,198 @@ export interface VideoWithMetadata {
   tags: Tag[];
   participants: Participant[];
   languages: Language[];
+  media_info: MediaInfo | null;
+  notes: string | null;
 }
 
+export interface DatabaseCheck {
+  problems: string[];
+  orphans_removed: number;
+}
+
+export interface DuplicateGroup {
+  content_hash: string;
+  size: number;
+  videos: VideoWithMetadata[];
+}
+
+export type DuplicateAction = 'merge' | 'trash';
+
 export interface FolderNode {
   path: string;
   name: string;
   children: FolderNode[];
+  /** Videos in this folder and every folder below it */
   video_count: number;
+  total_size: number;
+  /** Seconds */
+  total_duration: number;
+  /** Subfolders exist; `children` is empty until they are loaded */
+  has_children: boolean;
 }
 
 export interface ScanResult {
   total_videos: number;
   new_videos: number;
+  updated_videos: number;
+  removed_videos: number;
+  relinked: RelinkedVideo[];
   folders: FolderNode[];
+  /** Directories that couldn't be read; their videos were left as they were */
+  errors: ScanError[];
+}
+
+export interface ScanError {
+  path: string;
+  kind: 'read_dir' | 'timeout' | 'metadata' | 'symlink' | 'invalid_name';
+  message: string;
+  /** Raw error code from the OS, e.g. errno */
+  os_error: number | null;
+}
+
+/** Summary of the most recent scan of a mounted folder */
+export interface ScanReport {
+  folder_path: string;
+  started_at: string;
+  duration_ms: number;
+  status: 'completed' | 'cancelled' | 'failed';
+  error: string | null;
+  total_videos: number;
+  new_videos: number;
+  updated_videos: number;
+  removed_videos: number;
+  relinked_videos: number;
+  errors: ScanError[];
+}
+
+export interface ScanJob {
+  id: string;
+  folder_path: string;
+  status: 'queued' | 'running';
+}
+
+export interface ScanProgress {
+  job_id: string;
+  folder_path: string;
+  dirs_visited: number;
+  files_found: number;
+  current_path: string;
+}
+
+export type ScanFinished = { job_id: string; folder_path: string } & (
+  | { status: 'completed'; result: ScanResult }
+  | { status: 'cancelled' }
+  | { status: 'failed'; error: string }
+);
+
+export interface RelinkedVideo {
+  video_id: string;
+  old_path: string;
+  new_path: string;
+}
+
+export interface MpvState {
+  running: boolean;
+  path: string | null;
+  time_pos: number | null;
+  duration: number | null;
+  paused: boolean;
+  eof_reached: boolean;
+  volume: number | null;
+  playlist_pos: number | null;
+}
+
+export interface PlaybackProgress {
+  video_id: string;
+  position: number;
+  duration: number | null;
+  paused: boolean;
+  completed: boolean;
+}
+
+export interface ThumbnailProgress {
+  video_id: string;
+  thumbnail_path: string | null;
+  completed: number;
+  total: number;
+}
+
+export interface PlaybackHistory {
+  video_id: string;
+  position: number;
+  completed: boolean;
+  play_count: number;
+  total_watch_time: number;
+  first_played: string | null;
+  last_played: string;
+}
+
+export interface PlaybackSession {
+  id: string;
+  video_id: string;
+  started_at: string;
+  ended_at: string | null;
+  start_position: number;
+  end_position: number | null;
+}
+
+export interface WatchHistoryEntry {
+  session: PlaybackSession;
+  video: Video;
+}
+
+export interface VideoWithHistory {
+  video: Video;
+  history: PlaybackHistory;
+}
+
+export interface Series {
+  id: string;
+  title: string;
+  year: number | null;
+  season_count: number;
+  episode_count: number;
+  watched_count: number;
+  last_played: string | null;
+}
+
+export interface Season {
+  season: number;
+  episode_count: number;
+  watched_count: number;
+}
+
+export interface Episode {
+  video: Video;
+  series_id: string;
+  season: number;
+  episode: number;
+  episode_end: number | null;
+  resolution: string | null;
+  release_group: string | null;
+  availability: Availability;
+  history: PlaybackHistory | null;
+}
+
+export interface DateRange {
+  from: string | null;
+  to: string | null;
+}
+
+export type MatchMode = 'any' | 'all';
+
+export type WatchState = 'watched' | 'unwatched' | 'in_progress';
+
+export interface SearchSuggestion {
+  text: string;
+  kind: 'tag' | 'participant' | 'language' | 'term';
+}
+
+/** Rejection of `validate_query` and `get_videos`; offsets are UTF-16 code units, like JS string indices */
+export interface QueryError {
+  message: string;
+  start: number;
+  end: number;
 }
 
 export interface FilterOptions {
@@ -60,13 +265,75 @@ export interface FilterOptions {
   tag_ids: string[];
   participant_ids: string[];
   language_ids: string[];
+  tag_match?: MatchMode;
+  exclude_tag_ids?: string[];
+  participant_match?: MatchMode;
+  exclude_participant_ids?: string[];
+  language_match?: MatchMode;
+  exclude_language_ids?: string[];
   search_query: string | null;
-  sort_by: 'filename' | 'size' | 'created_at' | 'updated_at';
+  watch_state?: WatchState | null;
+  query?: string | null;
+  sort_by: 'filename' | 'size' | 'created_at' | 'updated_at' | 'relevance';
   sort_order: 'asc' | 'desc';
   limit: number;
   offset: number;
 }
 
+export interface SmartCollection {
+  id: string;
+  name: string;
+  filter: FilterOptions;
+  created_at: string;
+  updated_at: string;
+}
+
+export interface SidebarCollection {
+  id: string;
+  name: string;
+  video_count: number;
+}
+
+export interface SidebarData {
+  folders: MountedFolder[];
+  collections: SidebarCollection[];
+}
+
+export interface Playlist {
+  id: string;
+  name: string;
+  item_count: number;
+  created_at: string;
+  updated_at: string;
+}
+
+export interface PlaylistItem {
+  id: string;
+  playlist_id: string;
+  position: number;
+  video: Video;
+}
+
+export interface PlaylistImport {
+  playlist: Playlist;
+  missing: string[];
+}
+
+export interface PlaylistPlayback {
+  playlist_id: string;
+  index: number;
+  item_id: string;
+}
+
+export type RepeatMode = 'off' | 'one' | 'all';
+
+export interface QueueState {
+  items: Video[];
+  current: number | null;
+  repeat: RepeatMode;
+  playing: boolean;
+}
+
 export type ViewMode = 'grid' | 'list';
 
 export interface PlayerState {